@@ -1,11 +1,38 @@
-use crate::library::option::{AnkhaAsyncOption, AnkhaOption};
+use crate::library::{
+    closure::Closure,
+    iter::AnkhaIterator,
+    option::{AnkhaAsyncOption, AnkhaOption},
+};
 use intuicio_core::{
     IntuicioStruct,
+    context::Context,
     registry::Registry,
     transformer::{DynamicManagedValueTransformer, ValueTransformer},
 };
 use intuicio_derive::{IntuicioStruct, intuicio_method, intuicio_methods};
 
+// Stable merge sort asking `less` once per comparison - slice sort needs
+// full ordering instead and panics when it gets inconsistent one.
+fn merge_sort<T>(mut items: Vec<T>, less: &mut impl FnMut(&T, &T) -> bool) -> Vec<T> {
+    if items.len() < 2 {
+        return items;
+    }
+    let right = items.split_off(items.len() / 2);
+    let mut left = merge_sort(items, less).into_iter().peekable();
+    let mut right = merge_sort(right, less).into_iter().peekable();
+    let mut result = Vec::with_capacity(left.len() + right.len());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        if less(b, a) {
+            result.extend(right.next());
+        } else {
+            result.extend(left.next());
+        }
+    }
+    result.extend(left);
+    result.extend(right);
+    result
+}
+
 pub fn install(registry: &mut Registry) {
     registry.add_type(Array::define_struct(registry));
     registry.add_function(Array::with_capacity__define_function(registry));
@@ -25,6 +52,19 @@ pub fn install(registry: &mut Registry) {
     registry.add_function(Array::clear__define_function(registry));
     registry.add_function(Array::swap_remove__define_function(registry));
     registry.add_function(Array::swap__define_function(registry));
+    registry.add_function(Array::script_iter__define_function(registry));
+    registry.add_function(Array::script_iter_mut__define_function(registry));
+    registry.add_function(Array::script_into_iter__define_function(registry));
+    registry.add_function(Array::map__define_function(registry));
+    registry.add_function(Array::filter__define_function(registry));
+    registry.add_function(Array::into_filter__define_function(registry));
+    registry.add_function(Array::fold__define_function(registry));
+    registry.add_function(Array::for_each__define_function(registry));
+    registry.add_function(Array::find__define_function(registry));
+    registry.add_function(Array::any__define_function(registry));
+    registry.add_function(Array::all__define_function(registry));
+    registry.add_function(Array::sort_by__define_function(registry));
+    registry.add_function(Array::dedup_by__define_function(registry));
     registry.add_type(AsyncArray::define_struct(registry));
     registry.add_function(AsyncArray::with_capacity__define_function(registry));
     registry.add_function(AsyncArray::reserve__define_function(registry));
@@ -204,6 +244,239 @@ impl Array {
             self.items.swap(from, to);
         }
     }
+
+    #[intuicio_method(name = "iter", transformer = "DynamicManagedValueTransformer")]
+    pub fn script_iter(&self) -> AnkhaIterator {
+        let items = self
+            .items
+            .iter()
+            .filter_map(|item| item.borrow())
+            .map(|item| item.into())
+            .collect::<Vec<AnkhaOption>>();
+        AnkhaIterator::new(items.into_iter())
+    }
+
+    #[intuicio_method(name = "iter_mut", transformer = "DynamicManagedValueTransformer")]
+    pub fn script_iter_mut(&mut self) -> AnkhaIterator {
+        let items = self
+            .items
+            .iter_mut()
+            .filter_map(|item| item.borrow_mut())
+            .map(|item| item.into())
+            .collect::<Vec<AnkhaOption>>();
+        AnkhaIterator::new(items.into_iter())
+    }
+
+    #[intuicio_method(name = "into_iter", transformer = "DynamicManagedValueTransformer")]
+    pub fn script_into_iter(self) -> AnkhaIterator {
+        AnkhaIterator::new(self.items.into_iter().filter(|item| item.is_some()))
+    }
+
+    // Closures get elements borrowed, so owned elements stay in array.
+    // Empty elements are never passed to closures.
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn map(&self, context: &mut Context, registry: &Registry, closure: &mut Closure) -> Array {
+        self.items
+            .iter()
+            .map(|item| match item.borrow() {
+                Some(item) => closure.call(context, registry, vec![item.into()].into()),
+                None => AnkhaOption::None,
+            })
+            .collect()
+    }
+
+    // Elements accepted by closure are borrowed, so array stays intact.
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn filter(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        closure: &mut Closure,
+    ) -> Array {
+        self.items
+            .iter()
+            .filter_map(|item| item.borrow())
+            .filter(|item| {
+                closure.predicate(
+                    context,
+                    registry,
+                    vec![item.borrow().unwrap().into()],
+                    "filter",
+                )
+            })
+            .map(|item| item.into())
+            .collect()
+    }
+
+    // Consumes array and moves out elements accepted by closure.
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn into_filter(
+        self,
+        context: &mut Context,
+        registry: &Registry,
+        closure: &mut Closure,
+    ) -> Array {
+        self.items
+            .into_iter()
+            .filter(|item| match item.borrow() {
+                Some(item) => {
+                    closure.predicate(context, registry, vec![item.into()], "into_filter")
+                }
+                None => false,
+            })
+            .collect()
+    }
+
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn fold(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        initial: AnkhaOption,
+        closure: &mut Closure,
+    ) -> AnkhaOption {
+        self.items
+            .iter()
+            .filter_map(|item| item.borrow())
+            .fold(initial, |accumulator, item| {
+                closure.call(context, registry, vec![accumulator, item.into()].into())
+            })
+    }
+
+    // Elements are borrowed mutably, unless they are refs.
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn for_each(&mut self, context: &mut Context, registry: &Registry, closure: &mut Closure) {
+        for item in self.items.iter_mut() {
+            let item = match item.borrow_mut() {
+                Some(item) => item.into(),
+                None => match item.borrow() {
+                    Some(item) => item.into(),
+                    None => continue,
+                },
+            };
+            closure.call(context, registry, vec![item].into());
+        }
+    }
+
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn find(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        closure: &mut Closure,
+    ) -> AnkhaOption {
+        self.items
+            .iter()
+            .filter_map(|item| item.borrow())
+            .find(|item| {
+                closure.predicate(
+                    context,
+                    registry,
+                    vec![item.borrow().unwrap().into()],
+                    "find",
+                )
+            })
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn any(&self, context: &mut Context, registry: &Registry, closure: &mut Closure) -> bool {
+        self.items
+            .iter()
+            .filter_map(|item| item.borrow())
+            .any(|item| closure.predicate(context, registry, vec![item.into()], "any"))
+    }
+
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn all(&self, context: &mut Context, registry: &Registry, closure: &mut Closure) -> bool {
+        self.items
+            .iter()
+            .filter_map(|item| item.borrow())
+            .all(|item| closure.predicate(context, registry, vec![item.into()], "all"))
+    }
+
+    // Closure tells if first element is less than second one, empty elements
+    // go last. Sorting is stable when closure defines strict order, like `lt`,
+    // while with non-strict one, like `le`, equal elements may swap places.
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn sort_by(&mut self, context: &mut Context, registry: &Registry, closure: &mut Closure) {
+        let items = &self.items;
+        let mut less = |a: &usize, b: &usize| {
+            let arguments = vec![
+                items[*a].borrow().unwrap().into(),
+                items[*b].borrow().unwrap().into(),
+            ];
+            closure.predicate(context, registry, arguments, "sort_by")
+        };
+        let indices = (0..items.len())
+            .filter(|index| items[*index].is_some())
+            .collect();
+        let indices = merge_sort(indices, &mut less);
+        let empty = self.items.len() - indices.len();
+        let mut sorted = indices
+            .into_iter()
+            .map(|index| std::mem::take(&mut self.items[index]))
+            .collect::<Vec<_>>();
+        sorted.resize_with(sorted.len() + empty, Default::default);
+        self.items = sorted;
+    }
+
+    // Closure tells if consecutive elements are equal, keeping the first one.
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn dedup_by(&mut self, context: &mut Context, registry: &Registry, closure: &mut Closure) {
+        self.items
+            .dedup_by(|next, previous| match (previous.borrow(), next.borrow()) {
+                (Some(previous), Some(next)) => closure.predicate(
+                    context,
+                    registry,
+                    vec![previous.into(), next.into()],
+                    "dedup_by",
+                ),
+                (None, None) => true,
+                _ => false,
+            });
+    }
 }
 
 impl FromIterator<AnkhaOption> for Array {
@@ -394,3 +667,182 @@ impl From<Vec<AnkhaAsyncOption>> for AsyncArray {
         Self { items: value }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        library::{
+            reflection,
+            tests::{TestHost, i32s, literal, literals, math_function},
+        },
+        script::AnkhaLiteral,
+    };
+    use intuicio_core::prelude::*;
+    use intuicio_data::prelude::*;
+
+    #[test]
+    fn test_array_closures() {
+        let TestHost {
+            registry,
+            mut context,
+        } = TestHost::default();
+        let math = |name: &str| math_function(&registry, name);
+        // Captures go on stack top, so captured value is first math input.
+        let with = |function: reflection::Function, value: &DynamicManaged| {
+            Closure::new(
+                function,
+                vec![AnkhaOption::Ref(value.borrow().unwrap())].into(),
+            )
+        };
+        let ten = DynamicManaged::new(10i32).ok().unwrap();
+        let four = DynamicManaged::new(4i32).ok().unwrap();
+        let two = DynamicManaged::new(2i32).ok().unwrap();
+        // Shared `literals` helper expects no empty elements, so these are checked apart.
+        let filled = |array: &Array| literals(array.iter().filter(|item| item.is_some()));
+        let empty = |array: &Array| array.iter().position(|item| item.is_none());
+        let mut array = [3, 1, 0, 2, 2, 5]
+            .into_iter()
+            .map(|value| {
+                if value == 0 {
+                    AnkhaOption::None
+                } else {
+                    AnkhaOption::from_literal(AnkhaLiteral::I32(value))
+                }
+            })
+            .collect::<Array>();
+
+        let mapped = array.map(&mut context, &registry, &mut with(math("mul"), &ten));
+        assert_eq!(filled(&mapped), i32s(&[30, 10, 20, 20, 50]));
+        assert_eq!(empty(&mapped), Some(2));
+        let sum = array.fold(
+            &mut context,
+            &registry,
+            AnkhaOption::from_literal(AnkhaLiteral::I32(0)),
+            &mut Closure::from_function(math("add")),
+        );
+        assert_eq!(sum.read_literal(), Some(AnkhaLiteral::I32(13)));
+        assert!(array.any(&mut context, &registry, &mut with(math("gt"), &four)));
+        assert!(!array.all(&mut context, &registry, &mut with(math("gt"), &four)));
+        let found = array.find(&mut context, &registry, &mut with(math("lt"), &four));
+        assert!(found.is_ref());
+        assert_eq!(found.read_literal(), Some(AnkhaLiteral::I32(5)));
+        drop(found);
+        let found = array.find(&mut context, &registry, &mut with(math("lt"), &ten));
+        assert!(found.is_none());
+
+        array.sort_by(
+            &mut context,
+            &registry,
+            &mut Closure::from_function(math("le")),
+        );
+        assert_eq!(filled(&array), i32s(&[1, 2, 2, 3, 5]));
+        assert_eq!(empty(&array), Some(5));
+        array.sort_by(
+            &mut context,
+            &registry,
+            &mut Closure::from_function(math("lt")),
+        );
+        assert_eq!(filled(&array), i32s(&[1, 2, 2, 3, 5]));
+        assert_eq!(empty(&array), Some(5));
+        array.dedup_by(
+            &mut context,
+            &registry,
+            &mut Closure::from_function(math("eq")),
+        );
+        assert_eq!(filled(&array), i32s(&[1, 2, 3, 5]));
+        assert_eq!(empty(&array), Some(4));
+
+        let increment = intuicio_core::function::Function::new(
+            FunctionSignature::new("increment").with_input(FunctionParameter::new(
+                "value",
+                registry
+                    .find_type(TypeQuery::of::<DynamicManagedRefMut>())
+                    .unwrap(),
+            )),
+            FunctionBody::closure(|context, _| {
+                let mut value = context.stack().pop::<DynamicManagedRefMut>().unwrap();
+                *value.write::<i32>().unwrap() += 1;
+            }),
+        );
+        array.for_each(
+            &mut context,
+            &registry,
+            &mut Closure::from_function(reflection::Function(increment.into())),
+        );
+        assert_eq!(filled(&array), i32s(&[2, 3, 4, 6]));
+        assert_eq!(empty(&array), Some(4));
+
+        let filtered = array.filter(&mut context, &registry, &mut with(math("lt"), &two));
+        assert!(filtered.iter().all(|item| item.is_ref()));
+        assert_eq!(literals(filtered.iter()), i32s(&[3, 4, 6]));
+        drop(filtered);
+        assert_eq!(filled(&array), i32s(&[2, 3, 4, 6]));
+        let filtered = array.into_filter(&mut context, &registry, &mut with(math("lt"), &two));
+        assert!(filtered.iter().all(|item| item.is_owned()));
+        assert_eq!(literals(filtered.iter()), i32s(&[3, 4, 6]));
+        assert_eq!(context.stack().position(), 0);
+    }
+
+    #[test]
+    fn test_array_sort_ties() {
+        let TestHost {
+            registry,
+            mut context,
+        } = TestHost::default();
+        // Orders values by their tens only, so values within same ten tie.
+        let by_tens = |strict: bool| {
+            let function = Function::new(
+                FunctionSignature::new("by_tens")
+                    .with_input(FunctionParameter::new(
+                        "a",
+                        registry
+                            .find_type(TypeQuery::of::<DynamicManagedRef>())
+                            .unwrap(),
+                    ))
+                    .with_input(FunctionParameter::new(
+                        "b",
+                        registry
+                            .find_type(TypeQuery::of::<DynamicManagedRef>())
+                            .unwrap(),
+                    ))
+                    .with_output(FunctionParameter::new(
+                        "result",
+                        registry
+                            .find_type(TypeQuery::of::<DynamicManaged>())
+                            .unwrap(),
+                    )),
+                FunctionBody::closure(move |context, _| {
+                    let a = context.stack().pop::<DynamicManagedRef>().unwrap();
+                    let b = context.stack().pop::<DynamicManagedRef>().unwrap();
+                    let a = *a.read::<i32>().unwrap() / 10;
+                    let b = *b.read::<i32>().unwrap() / 10;
+                    let result = if strict { a < b } else { a <= b };
+                    context
+                        .stack()
+                        .push(DynamicManaged::new(result).ok().unwrap());
+                }),
+            );
+            Closure::from_function(reflection::Function(function.into()))
+        };
+        let values = [21, 12, 25, 10, 20, 11];
+
+        // Strict order keeps tied elements in place.
+        let mut array = values.into_iter().map(literal).collect::<Array>();
+        array.sort_by(&mut context, &registry, &mut by_tens(true));
+        assert_eq!(literals(array.iter()), i32s(&[12, 10, 11, 21, 25, 20]));
+
+        // Non-strict order still sorts, in no particular order among ties.
+        let mut array = values.into_iter().map(literal).collect::<Array>();
+        array.sort_by(&mut context, &registry, &mut by_tens(false));
+        let tens = literals(array.iter())
+            .into_iter()
+            .map(|item| match item {
+                AnkhaLiteral::I32(value) => value / 10,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(tens, vec![1, 1, 1, 2, 2, 2]);
+        assert_eq!(context.stack().position(), 0);
+    }
+}
@@ -4,7 +4,7 @@ use crate::{
         option::{AnkhaAsyncOption, AnkhaOption},
         reflection::Function,
     },
-    script::stack_managed_variant,
+    script::{AnkhaCapabilities, AnkhaLiteral, stack_managed_variant},
 };
 use intuicio_core::{
     context::Context,
@@ -33,6 +33,13 @@ pub fn install(registry: &mut Registry) {
     registry.add_function(AsyncClosure::call__define_function(registry));
 }
 
+fn predicate_result(result: Option<AnkhaLiteral>, operation: &str) -> bool {
+    match result {
+        Some(AnkhaLiteral::Bool(value)) => value,
+        _ => panic!("Closure passed to `{}` must return boolean!", operation),
+    }
+}
+
 pub struct Closure {
     function: Function,
     captured: Array,
@@ -109,6 +116,7 @@ impl Closure {
                 }
             }
         }
+        AnkhaCapabilities::ensure_function_allowed(context, &self.function.0);
         self.function.0.invoke(context, registry);
     }
 
@@ -137,6 +145,20 @@ impl Closure {
             AnkhaOption::None
         }
     }
+
+    pub fn predicate(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        arguments: Vec<AnkhaOption>,
+        operation: &str,
+    ) -> bool {
+        predicate_result(
+            self.call(context, registry, arguments.into())
+                .read_literal(),
+            operation,
+        )
+    }
 }
 
 pub struct AsyncClosure {
@@ -209,6 +231,7 @@ impl AsyncClosure {
                 }
             }
         }
+        AnkhaCapabilities::ensure_function_allowed(context, &self.function.0);
         self.function.0.invoke(context, registry);
     }
 
@@ -237,4 +260,18 @@ impl AsyncClosure {
             AnkhaAsyncOption::None
         }
     }
+
+    pub fn predicate(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        arguments: Vec<AnkhaAsyncOption>,
+        operation: &str,
+    ) -> bool {
+        predicate_result(
+            self.call(context, registry, arguments.into())
+                .read_literal(),
+            operation,
+        )
+    }
 }
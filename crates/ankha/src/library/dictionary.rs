@@ -1,38 +1,349 @@
 use crate::{
-    library::option::{AnkhaAsyncOption, AnkhaOption},
-    script::AnkhaLiteral,
+    library::{
+        array::{Array, AsyncArray},
+        closure::{AsyncClosure, Closure},
+        format::managed_field,
+        iter::AnkhaIterator,
+        option::{AnkhaAsyncOption, AnkhaOption},
+    },
+    script::{AnkhaLiteral, enum_variant, find_value_type, type_handle_query, type_identity},
 };
 use intuicio_core::{
     IntuicioStruct,
+    context::Context,
+    function::{FunctionHandle, FunctionQuery},
     registry::Registry,
     transformer::{DynamicManagedValueTransformer, ValueTransformer},
+    types::Type as CoreType,
+};
+use intuicio_data::{
+    lifetime::Lifetime,
+    managed::{DynamicManaged, DynamicManagedRef},
+    type_hash::TypeHash,
 };
 use intuicio_derive::{IntuicioStruct, intuicio_method, intuicio_methods};
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+pub const HASH_FUNCTION_NAME: &str = "hash";
+pub const EQ_FUNCTION_NAME: &str = "eq";
 
 pub fn install(registry: &mut Registry) {
     registry.add_type(Dictionary::define_struct(registry));
     registry.add_function(Dictionary::is_empty__define_function(registry));
     registry.add_function(Dictionary::size__define_function(registry));
-    registry.add_function(Dictionary::exists__define_function(registry));
-    registry.add_function(Dictionary::is_valid__define_function(registry));
-    registry.add_function(Dictionary::get__define_function(registry));
-    registry.add_function(Dictionary::get_mut__define_function(registry));
-    registry.add_function(Dictionary::get_lazy__define_function(registry));
-    registry.add_function(Dictionary::insert__define_function(registry));
-    registry.add_function(Dictionary::remove__define_function(registry));
+    registry.add_function(Dictionary::exists_value__define_function(registry));
+    registry.add_function(Dictionary::is_valid_value__define_function(registry));
+    registry.add_function(Dictionary::get_value__define_function(registry));
+    registry.add_function(Dictionary::get_mut_value__define_function(registry));
+    registry.add_function(Dictionary::get_lazy_value__define_function(registry));
+    registry.add_function(Dictionary::insert_value__define_function(registry));
+    registry.add_function(Dictionary::remove_value__define_function(registry));
     registry.add_function(Dictionary::clear__define_function(registry));
+    registry.add_function(Dictionary::iter_keys__define_function(registry));
+    registry.add_function(Dictionary::iter_values__define_function(registry));
+    registry.add_function(Dictionary::iter_values_mut__define_function(registry));
+    registry.add_function(Dictionary::script_keys__define_function(registry));
+    registry.add_function(Dictionary::values_ref__define_function(registry));
+    registry.add_function(Dictionary::entries__define_function(registry));
+    registry.add_function(Dictionary::extend_value__define_function(registry));
+    registry.add_function(Dictionary::merge_value__define_function(registry));
+    registry.add_function(Dictionary::retain__define_function(registry));
     registry.add_type(AsyncDictionary::define_struct(registry));
     registry.add_function(AsyncDictionary::is_empty__define_function(registry));
     registry.add_function(AsyncDictionary::size__define_function(registry));
-    registry.add_function(AsyncDictionary::exists__define_function(registry));
-    registry.add_function(AsyncDictionary::is_valid__define_function(registry));
-    registry.add_function(AsyncDictionary::get__define_function(registry));
-    registry.add_function(AsyncDictionary::get_mut__define_function(registry));
-    registry.add_function(AsyncDictionary::get_lazy__define_function(registry));
-    registry.add_function(AsyncDictionary::insert__define_function(registry));
-    registry.add_function(AsyncDictionary::remove__define_function(registry));
+    registry.add_function(AsyncDictionary::exists_value__define_function(registry));
+    registry.add_function(AsyncDictionary::is_valid_value__define_function(registry));
+    registry.add_function(AsyncDictionary::get_value__define_function(registry));
+    registry.add_function(AsyncDictionary::get_mut_value__define_function(registry));
+    registry.add_function(AsyncDictionary::get_lazy_value__define_function(registry));
+    registry.add_function(AsyncDictionary::insert_value__define_function(registry));
+    registry.add_function(AsyncDictionary::remove_value__define_function(registry));
     registry.add_function(AsyncDictionary::clear__define_function(registry));
+    registry.add_function(AsyncDictionary::script_keys__define_function(registry));
+    registry.add_function(AsyncDictionary::values_ref__define_function(registry));
+    registry.add_function(AsyncDictionary::entries__define_function(registry));
+    registry.add_function(AsyncDictionary::extend_value__define_function(registry));
+    registry.add_function(AsyncDictionary::merge_value__define_function(registry));
+    registry.add_function(AsyncDictionary::retain__define_function(registry));
+}
+
+// Hashes key that is not literal with its `hash` method, or structurally over
+// its type name and fields when type has no such method.
+pub fn key_hash(context: &mut Context, registry: &Registry, key: &DynamicManagedRef) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    write_key_hash(context, registry, key, &mut hasher);
+    hasher.finish()
+}
+
+// Compares keys with `eq` method of their type, or structurally over fields
+// when type has no such method. Types with `eq` method should have `hash`
+// method too, so equal keys get equal hashes.
+pub fn key_eq(
+    context: &mut Context,
+    registry: &Registry,
+    a: &DynamicManagedRef,
+    b: &DynamicManagedRef,
+) -> bool {
+    if a.type_hash() != b.type_hash() {
+        return false;
+    }
+    if let Some(literal) = key_literal(a) {
+        return key_literal(b) == Some(literal);
+    }
+    if let Some(function) = key_method(registry, EQ_FUNCTION_NAME, *a.type_hash()) {
+        context.stack().push(b.borrow().unwrap());
+        context.stack().push(a.borrow().unwrap());
+        function.invoke(context, registry);
+        return match AnkhaOption::pop(context).read_literal() {
+            Some(AnkhaLiteral::Bool(value)) => value,
+            _ => panic!("Key `eq` method must return `bool` value!"),
+        };
+    }
+    let lifetime_a = Lifetime::default();
+    let lifetime_b = Lifetime::default();
+    let (_, variant_a, fields_a) = key_fields(registry, a, &lifetime_a);
+    let (_, variant_b, fields_b) = key_fields(registry, b, &lifetime_b);
+    variant_a == variant_b
+        && fields_a.len() == fields_b.len()
+        && fields_a
+            .iter()
+            .zip(fields_b.iter())
+            .all(|(a, b)| key_eq(context, registry, a, b))
+}
+
+fn write_key_hash(
+    context: &mut Context,
+    registry: &Registry,
+    key: &DynamicManagedRef,
+    hasher: &mut DefaultHasher,
+) {
+    if let Some(literal) = key_literal(key) {
+        literal.hash(hasher);
+        return;
+    }
+    if let Some(function) = key_method(registry, HASH_FUNCTION_NAME, *key.type_hash()) {
+        context.stack().push(key.borrow().unwrap());
+        function.invoke(context, registry);
+        match AnkhaOption::pop(context).read_literal() {
+            Some(AnkhaLiteral::U64(value)) => value.hash(hasher),
+            _ => panic!("Key `hash` method must return `u64` value!"),
+        }
+        return;
+    }
+    let lifetime = Lifetime::default();
+    let (name, variant, fields) = key_fields(registry, key, &lifetime);
+    name.hash(hasher);
+    variant.hash(hasher);
+    for field in &fields {
+        write_key_hash(context, registry, field, hasher);
+    }
+}
+
+fn key_literal(key: &DynamicManagedRef) -> Option<AnkhaLiteral> {
+    key.borrow()
+        .and_then(|key| AnkhaOption::Ref(key).read_literal())
+}
+
+fn key_method(registry: &Registry, name: &str, type_hash: TypeHash) -> Option<FunctionHandle> {
+    let type_ = find_value_type(registry, type_hash)?;
+    registry.find_function(FunctionQuery {
+        name: Some(name.into()),
+        type_query: Some(type_handle_query(&type_)),
+        ..Default::default()
+    })
+}
+
+// Borrows fields of key through reflection, together with type and variant
+// name. Native types without fields and native enums cannot be told apart this
+// way, so these must have `hash` and `eq` methods to be keys.
+fn key_fields(
+    registry: &Registry,
+    key: &DynamicManagedRef,
+    lifetime: &Lifetime,
+) -> (String, Option<String>, Vec<DynamicManagedRef>) {
+    let type_ = find_value_type(registry, *key.type_hash())
+        .expect("Could not find type of dictionary key!");
+    let pointer = unsafe { key.as_ptr_raw() }.expect("Could not read dictionary key!");
+    let (variant, fields) = match &*type_ {
+        CoreType::Struct(struct_type) => (None, struct_type.fields()),
+        CoreType::Enum(enum_type) => match unsafe { enum_variant(enum_type, pointer) } {
+            Some(variant) => (Some(variant.name.to_owned()), variant.fields.as_slice()),
+            None => (None, Default::default()),
+        },
+    };
+    if type_.is_native() && variant.is_none() && fields.is_empty() {
+        panic!(
+            "Dictionary key of `{}` type needs `hash` and `eq` methods!",
+            type_.name()
+        );
+    }
+    let fields = fields
+        .iter()
+        .map(|field| {
+            let type_hash = type_identity(field.type_handle());
+            let pointer = unsafe { pointer.add(field.address_offset()) };
+            unsafe { managed_field(type_hash, pointer) }
+                .or_else(|| unsafe {
+                    DynamicManagedRef::new_raw(type_hash, lifetime.borrow().unwrap(), pointer)
+                })
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Could not borrow `{}` field of `{}` key type!",
+                        field.name,
+                        type_.name()
+                    )
+                })
+        })
+        .collect();
+    (type_.name().to_owned(), variant, fields)
+}
+
+// Options that can be keys of dictionary without being literals.
+trait ValueKey {
+    fn borrow_key(&self) -> Option<DynamicManagedRef>;
+}
+
+impl ValueKey for AnkhaOption {
+    fn borrow_key(&self) -> Option<DynamicManagedRef> {
+        self.borrow()
+    }
+}
+
+impl ValueKey for AnkhaAsyncOption {
+    fn borrow_key(&self) -> Option<DynamicManagedRef> {
+        self.borrow()
+    }
+}
+
+// Entries with keys that are not literals, bucketed by key hash, since key
+// comparison needs context.
+struct ValueEntries<K, V> {
+    buckets: HashMap<u64, Vec<(K, V)>>,
+}
+
+impl<K, V> Default for ValueEntries<K, V> {
+    fn default() -> Self {
+        Self {
+            buckets: Default::default(),
+        }
+    }
+}
+
+impl<K: ValueKey, V> ValueEntries<K, V> {
+    fn len(&self) -> usize {
+        self.buckets.values().map(|bucket| bucket.len()).sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.buckets.clear();
+    }
+
+    fn position(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        hash: u64,
+        key: &DynamicManagedRef,
+    ) -> Option<usize> {
+        self.buckets.get(&hash)?.iter().position(|(item, _)| {
+            item.borrow_key()
+                .map(|item| key_eq(context, registry, &item, key))
+                .unwrap_or_default()
+        })
+    }
+
+    fn get(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        key: &DynamicManagedRef,
+    ) -> Option<&V> {
+        let hash = key_hash(context, registry, key);
+        let index = self.position(context, registry, hash, key)?;
+        Some(&self.buckets[&hash][index].1)
+    }
+
+    fn get_mut(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: &DynamicManagedRef,
+    ) -> Option<&mut V> {
+        let hash = key_hash(context, registry, key);
+        let index = self.position(context, registry, hash, key)?;
+        Some(&mut self.buckets.get_mut(&hash)?[index].1)
+    }
+
+    fn insert(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: K,
+        value: V,
+    ) -> Option<V> {
+        let key_ref = key.borrow_key().expect("Could not borrow dictionary key!");
+        let hash = key_hash(context, registry, &key_ref);
+        if let Some(index) = self.position(context, registry, hash, &key_ref) {
+            let bucket = self.buckets.get_mut(&hash)?;
+            return Some(std::mem::replace(&mut bucket[index].1, value));
+        }
+        drop(key_ref);
+        self.buckets.entry(hash).or_default().push((key, value));
+        None
+    }
+
+    fn remove(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: &DynamicManagedRef,
+    ) -> Option<V> {
+        let hash = key_hash(context, registry, key);
+        let index = self.position(context, registry, hash, key)?;
+        let bucket = self.buckets.get_mut(&hash)?;
+        let (_, value) = bucket.remove(index);
+        if bucket.is_empty() {
+            self.buckets.remove(&hash);
+        }
+        Some(value)
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        self.buckets.retain(|_, bucket| {
+            bucket.retain_mut(|(key, value)| f(key, value));
+            !bucket.is_empty()
+        });
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.buckets
+            .values()
+            .flatten()
+            .map(|(key, value)| (key, value))
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.buckets
+            .values_mut()
+            .flatten()
+            .map(|(key, value)| (&*key, value))
+    }
+}
+
+impl<K, V> IntoIterator for ValueEntries<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::iter::Flatten<std::collections::hash_map::IntoValues<u64, Vec<(K, V)>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.buckets.into_values().flatten()
+    }
 }
 
 #[derive(IntuicioStruct, Default)]
@@ -40,6 +351,8 @@ pub fn install(registry: &mut Registry) {
 pub struct Dictionary {
     #[intuicio(ignore)]
     items: HashMap<AnkhaLiteral, AnkhaOption>,
+    #[intuicio(ignore)]
+    values: ValueEntries<AnkhaOption, AnkhaOption>,
 }
 
 impl Dictionary {
@@ -78,37 +391,55 @@ impl Dictionary {
     pub fn into_values(self) -> impl Iterator<Item = AnkhaOption> {
         self.items.into_values()
     }
-}
 
-impl IntoIterator for Dictionary {
-    type Item = (AnkhaLiteral, AnkhaOption);
-    type IntoIter = std::collections::hash_map::IntoIter<AnkhaLiteral, AnkhaOption>;
+    pub fn value_entries(&self) -> impl Iterator<Item = (&AnkhaOption, &AnkhaOption)> {
+        self.values.iter()
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.items.into_iter()
+    pub fn value_entries_mut(&mut self) -> impl Iterator<Item = (&AnkhaOption, &mut AnkhaOption)> {
+        self.values.iter_mut()
     }
-}
 
-#[intuicio_methods(module_name = "dictionary")]
-impl Dictionary {
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
-    pub fn is_empty(&self) -> bool {
-        self.items.is_empty()
+    fn all_values(&self) -> impl Iterator<Item = &AnkhaOption> {
+        self.items
+            .values()
+            .chain(self.values.iter().map(|(_, item)| item))
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
-    pub fn size(&self) -> usize {
-        self.items.len()
+    fn find(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        key: &AnkhaOption,
+    ) -> Option<&AnkhaOption> {
+        if let Some(key) = key.read_literal() {
+            self.items.get(&key)
+        } else {
+            self.values.get(context, registry, &key.borrow()?)
+        }
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    fn find_mut(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: &AnkhaOption,
+    ) -> Option<&mut AnkhaOption> {
+        if let Some(key) = key.read_literal() {
+            self.items.get_mut(&key)
+        } else {
+            self.values.get_mut(context, registry, &key.borrow()?)
+        }
+    }
+
+    // Rust-side methods take literal keys only. Other keys are compared with
+    // their methods, so they need `*_value` variants that take context.
     pub fn exists(&self, key: AnkhaOption) -> bool {
         key.into_literal()
             .map(|key| self.items.contains_key(&key))
             .unwrap_or_default()
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn is_valid(&self, key: AnkhaOption) -> bool {
         key.into_literal()
             .and_then(|key| self.items.get(&key))
@@ -116,7 +447,6 @@ impl Dictionary {
             .unwrap_or_default()
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn get(&self, key: AnkhaOption) -> AnkhaOption {
         key.into_literal()
             .and_then(|key| self.items.get(&key))
@@ -125,7 +455,6 @@ impl Dictionary {
             .unwrap_or_default()
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn get_mut(&mut self, key: AnkhaOption) -> AnkhaOption {
         key.into_literal()
             .and_then(|key| self.items.get_mut(&key))
@@ -134,7 +463,6 @@ impl Dictionary {
             .unwrap_or_default()
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn get_lazy(&mut self, key: AnkhaOption) -> AnkhaOption {
         key.into_literal()
             .and_then(|key| self.items.get_mut(&key))
@@ -143,23 +471,320 @@ impl Dictionary {
             .unwrap_or_default()
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn insert(&mut self, key: AnkhaOption, value: AnkhaOption) -> AnkhaOption {
         key.into_literal()
             .and_then(|key| self.items.insert(key, value))
             .unwrap_or_default()
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn remove(&mut self, key: AnkhaOption) -> AnkhaOption {
         key.into_literal()
             .and_then(|key| self.items.remove(&key))
             .unwrap_or_default()
     }
 
+    pub fn extend(&mut self, other: Self) {
+        if !other.values.is_empty() {
+            panic!("Dictionary with non-literal keys has to be moved with `extend_value`!");
+        }
+        self.items.extend(other.items);
+    }
+
+    pub fn merge(&mut self, other: Self) {
+        if !other.values.is_empty() {
+            panic!("Dictionary with non-literal keys has to be moved with `merge_value`!");
+        }
+        for (key, item) in other.items {
+            self.items.entry(key).or_insert(item);
+        }
+    }
+}
+
+// Rust-side iteration covers literal keys, other keys are reachable with
+// `value_entries`.
+impl IntoIterator for Dictionary {
+    type Item = (AnkhaLiteral, AnkhaOption);
+    type IntoIter = std::collections::hash_map::IntoIter<AnkhaLiteral, AnkhaOption>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+#[intuicio_methods(module_name = "dictionary")]
+impl Dictionary {
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty() && self.values.is_empty()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn size(&self) -> usize {
+        self.items.len() + self.values.len()
+    }
+
+    #[intuicio_method(
+        name = "exists",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn exists_value(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaOption,
+    ) -> bool {
+        self.find(context, registry, &key).is_some()
+    }
+
+    #[intuicio_method(
+        name = "is_valid",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn is_valid_value(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaOption,
+    ) -> bool {
+        self.find(context, registry, &key)
+            .map(|item| item.is_some())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(
+        name = "get",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn get_value(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaOption,
+    ) -> AnkhaOption {
+        self.find(context, registry, &key)
+            .and_then(|item| item.borrow())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(
+        name = "get_mut",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn get_mut_value(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaOption,
+    ) -> AnkhaOption {
+        self.find_mut(context, registry, &key)
+            .and_then(|item| item.borrow_mut())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(
+        name = "get_lazy",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn get_lazy_value(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaOption,
+    ) -> AnkhaOption {
+        self.find_mut(context, registry, &key)
+            .and_then(|item| item.lazy())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    // Literal keys are stored by value, other keys are kept as they are and
+    // compared with their `hash` and `eq` methods, or structurally. Key hash is
+    // taken on insert, so changing key afterwards, or value behind borrowed key,
+    // leaves its entry unreachable.
+    #[intuicio_method(
+        name = "insert",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn insert_value(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaOption,
+        value: AnkhaOption,
+    ) -> AnkhaOption {
+        let result = if let Some(key) = key.read_literal() {
+            self.items.insert(key, value)
+        } else if key.is_some() {
+            self.values.insert(context, registry, key, value)
+        } else {
+            None
+        };
+        result.unwrap_or_default()
+    }
+
+    #[intuicio_method(
+        name = "remove",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn remove_value(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaOption,
+    ) -> AnkhaOption {
+        let result = if let Some(key) = key.read_literal() {
+            self.items.remove(&key)
+        } else {
+            key.borrow()
+                .and_then(|key| self.values.remove(context, registry, &key))
+        };
+        result.unwrap_or_default()
+    }
+
     #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn clear(&mut self) {
         self.items.clear();
+        self.values.clear();
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn iter_keys(&self) -> AnkhaIterator {
+        AnkhaIterator::new(self.script_keys().into_iter().filter(|key| key.is_some()))
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn iter_values(&self) -> AnkhaIterator {
+        let values = self
+            .all_values()
+            .filter_map(|item| item.borrow())
+            .map(|item| item.into())
+            .collect::<Vec<AnkhaOption>>();
+        AnkhaIterator::new(values.into_iter())
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn iter_values_mut(&mut self) -> AnkhaIterator {
+        let values = self
+            .items
+            .values_mut()
+            .chain(self.values.iter_mut().map(|(_, item)| item))
+            .filter_map(|item| item.borrow_mut())
+            .map(|item| item.into())
+            .collect::<Vec<AnkhaOption>>();
+        AnkhaIterator::new(values.into_iter())
+    }
+
+    // Literal keys are owned copies, other keys are borrowed.
+    #[intuicio_method(name = "keys", transformer = "DynamicManagedValueTransformer")]
+    pub fn script_keys(&self) -> Array {
+        self.items
+            .keys()
+            .map(|key| AnkhaOption::from_literal(key.to_owned()))
+            .chain(
+                self.values
+                    .iter()
+                    .map(|(key, _)| key.borrow().map(|key| key.into()).unwrap_or_default()),
+            )
+            .collect()
+    }
+
+    // Values are in same order as keys, empty values stay empty.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn values_ref(&self) -> Array {
+        self.all_values()
+            .map(|item| item.borrow().map(|item| item.into()).unwrap_or_default())
+            .collect()
+    }
+
+    // Each entry is array of key and borrowed value.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn entries(&self) -> Array {
+        self.script_keys()
+            .into_iter()
+            .zip(self.values_ref())
+            .map(|(key, item)| {
+                let entry = [key, item].into_iter().collect::<Array>();
+                DynamicManaged::new(entry).ok().unwrap().into()
+            })
+            .collect()
+    }
+
+    // Moves all entries of other dictionary, replacing existing values.
+    #[intuicio_method(
+        name = "extend",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn extend_value(&mut self, context: &mut Context, registry: &Registry, other: Self) {
+        self.items.extend(other.items);
+        for (key, item) in other.values {
+            self.values.insert(context, registry, key, item);
+        }
+    }
+
+    // Moves entries of other dictionary, keeping existing values.
+    #[intuicio_method(
+        name = "merge",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn merge_value(&mut self, context: &mut Context, registry: &Registry, other: Self) {
+        for (key, item) in other.items {
+            self.items.entry(key).or_insert(item);
+        }
+        for (key, item) in other.values {
+            let exists = key
+                .borrow()
+                .map(|key| self.values.get(context, registry, &key).is_some())
+                .unwrap_or_default();
+            if !exists {
+                self.values.insert(context, registry, key, item);
+            }
+        }
+    }
+
+    // Closure gets key and borrowed value, entries with empty values are kept
+    // without asking closure.
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn retain(&mut self, context: &mut Context, registry: &Registry, closure: &mut Closure) {
+        self.items.retain(|key, item| match item.borrow() {
+            Some(item) => closure.predicate(
+                context,
+                registry,
+                vec![AnkhaOption::from_literal(key.to_owned()), item.into()],
+                "retain",
+            ),
+            None => true,
+        });
+        self.values
+            .retain(|key, item| match (key.borrow(), item.borrow()) {
+                (Some(key), Some(item)) => {
+                    closure.predicate(context, registry, vec![key.into(), item.into()], "retain")
+                }
+                _ => true,
+            });
     }
 }
 
@@ -167,13 +792,17 @@ impl FromIterator<(AnkhaLiteral, AnkhaOption)> for Dictionary {
     fn from_iter<T: IntoIterator<Item = (AnkhaLiteral, AnkhaOption)>>(iter: T) -> Self {
         Self {
             items: iter.into_iter().collect(),
+            values: Default::default(),
         }
     }
 }
 
 impl From<HashMap<AnkhaLiteral, AnkhaOption>> for Dictionary {
     fn from(value: HashMap<AnkhaLiteral, AnkhaOption>) -> Self {
-        Self { items: value }
+        Self {
+            items: value,
+            values: Default::default(),
+        }
     }
 }
 
@@ -182,6 +811,8 @@ impl From<HashMap<AnkhaLiteral, AnkhaOption>> for Dictionary {
 pub struct AsyncDictionary {
     #[intuicio(ignore)]
     items: HashMap<AnkhaLiteral, AnkhaAsyncOption>,
+    #[intuicio(ignore)]
+    values: ValueEntries<AnkhaAsyncOption, AnkhaAsyncOption>,
 }
 
 impl AsyncDictionary {
@@ -220,37 +851,57 @@ impl AsyncDictionary {
     pub fn into_values(self) -> impl Iterator<Item = AnkhaAsyncOption> {
         self.items.into_values()
     }
-}
 
-impl IntoIterator for AsyncDictionary {
-    type Item = (AnkhaLiteral, AnkhaAsyncOption);
-    type IntoIter = std::collections::hash_map::IntoIter<AnkhaLiteral, AnkhaAsyncOption>;
+    pub fn value_entries(&self) -> impl Iterator<Item = (&AnkhaAsyncOption, &AnkhaAsyncOption)> {
+        self.values.iter()
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.items.into_iter()
+    pub fn value_entries_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&AnkhaAsyncOption, &mut AnkhaAsyncOption)> {
+        self.values.iter_mut()
     }
-}
 
-#[intuicio_methods(module_name = "dictionary")]
-impl AsyncDictionary {
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
-    pub fn is_empty(&self) -> bool {
-        self.items.is_empty()
+    fn all_values(&self) -> impl Iterator<Item = &AnkhaAsyncOption> {
+        self.items
+            .values()
+            .chain(self.values.iter().map(|(_, item)| item))
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
-    pub fn size(&self) -> usize {
-        self.items.len()
+    fn find(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        key: &AnkhaAsyncOption,
+    ) -> Option<&AnkhaAsyncOption> {
+        if let Some(key) = key.read_literal() {
+            self.items.get(&key)
+        } else {
+            self.values.get(context, registry, &key.borrow()?)
+        }
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    fn find_mut(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: &AnkhaAsyncOption,
+    ) -> Option<&mut AnkhaAsyncOption> {
+        if let Some(key) = key.read_literal() {
+            self.items.get_mut(&key)
+        } else {
+            self.values.get_mut(context, registry, &key.borrow()?)
+        }
+    }
+
+    // Rust-side methods take literal keys only. Other keys are compared with
+    // their methods, so they need `*_value` variants that take context.
     pub fn exists(&self, key: AnkhaAsyncOption) -> bool {
         key.into_literal()
             .map(|key| self.items.contains_key(&key))
             .unwrap_or_default()
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn is_valid(&self, key: AnkhaAsyncOption) -> bool {
         key.into_literal()
             .and_then(|key| self.items.get(&key))
@@ -258,7 +909,6 @@ impl AsyncDictionary {
             .unwrap_or_default()
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn get(&self, key: AnkhaAsyncOption) -> AnkhaAsyncOption {
         key.into_literal()
             .and_then(|key| self.items.get(&key))
@@ -267,7 +917,6 @@ impl AsyncDictionary {
             .unwrap_or_default()
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn get_mut(&mut self, key: AnkhaAsyncOption) -> AnkhaAsyncOption {
         key.into_literal()
             .and_then(|key| self.items.get_mut(&key))
@@ -276,7 +925,6 @@ impl AsyncDictionary {
             .unwrap_or_default()
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn get_lazy(&mut self, key: AnkhaAsyncOption) -> AnkhaAsyncOption {
         key.into_literal()
             .and_then(|key| self.items.get_mut(&key))
@@ -285,23 +933,298 @@ impl AsyncDictionary {
             .unwrap_or_default()
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn insert(&mut self, key: AnkhaAsyncOption, value: AnkhaAsyncOption) -> AnkhaAsyncOption {
         key.into_literal()
             .and_then(|key| self.items.insert(key, value))
             .unwrap_or_default()
     }
 
-    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn remove(&mut self, key: AnkhaAsyncOption) -> AnkhaAsyncOption {
         key.into_literal()
             .and_then(|key| self.items.remove(&key))
             .unwrap_or_default()
     }
 
+    pub fn extend(&mut self, other: Self) {
+        if !other.values.is_empty() {
+            panic!("Dictionary with non-literal keys has to be moved with `extend_value`!");
+        }
+        self.items.extend(other.items);
+    }
+
+    pub fn merge(&mut self, other: Self) {
+        if !other.values.is_empty() {
+            panic!("Dictionary with non-literal keys has to be moved with `merge_value`!");
+        }
+        for (key, item) in other.items {
+            self.items.entry(key).or_insert(item);
+        }
+    }
+}
+
+// Rust-side iteration covers literal keys, other keys are reachable with
+// `value_entries`.
+impl IntoIterator for AsyncDictionary {
+    type Item = (AnkhaLiteral, AnkhaAsyncOption);
+    type IntoIter = std::collections::hash_map::IntoIter<AnkhaLiteral, AnkhaAsyncOption>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+#[intuicio_methods(module_name = "dictionary")]
+impl AsyncDictionary {
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty() && self.values.is_empty()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn size(&self) -> usize {
+        self.items.len() + self.values.len()
+    }
+
+    #[intuicio_method(
+        name = "exists",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn exists_value(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaAsyncOption,
+    ) -> bool {
+        self.find(context, registry, &key).is_some()
+    }
+
+    #[intuicio_method(
+        name = "is_valid",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn is_valid_value(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaAsyncOption,
+    ) -> bool {
+        self.find(context, registry, &key)
+            .map(|item| item.is_some())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(
+        name = "get",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn get_value(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaAsyncOption,
+    ) -> AnkhaAsyncOption {
+        self.find(context, registry, &key)
+            .and_then(|item| item.borrow())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(
+        name = "get_mut",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn get_mut_value(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaAsyncOption,
+    ) -> AnkhaAsyncOption {
+        self.find_mut(context, registry, &key)
+            .and_then(|item| item.borrow_mut())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(
+        name = "get_lazy",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn get_lazy_value(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaAsyncOption,
+    ) -> AnkhaAsyncOption {
+        self.find_mut(context, registry, &key)
+            .and_then(|item| item.lazy())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    // Literal keys are stored by value, other keys are kept as they are and
+    // compared with their `hash` and `eq` methods, or structurally. Key hash is
+    // taken on insert, so changing key afterwards, or value behind borrowed key,
+    // leaves its entry unreachable.
+    #[intuicio_method(
+        name = "insert",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn insert_value(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaAsyncOption,
+        value: AnkhaAsyncOption,
+    ) -> AnkhaAsyncOption {
+        let result = if let Some(key) = key.read_literal() {
+            self.items.insert(key, value)
+        } else if key.is_some() {
+            self.values.insert(context, registry, key, value)
+        } else {
+            None
+        };
+        result.unwrap_or_default()
+    }
+
+    #[intuicio_method(
+        name = "remove",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn remove_value(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        key: AnkhaAsyncOption,
+    ) -> AnkhaAsyncOption {
+        let result = if let Some(key) = key.read_literal() {
+            self.items.remove(&key)
+        } else {
+            key.borrow()
+                .and_then(|key| self.values.remove(context, registry, &key))
+        };
+        result.unwrap_or_default()
+    }
+
     #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
     pub fn clear(&mut self) {
         self.items.clear();
+        self.values.clear();
+    }
+
+    // Literal keys are owned copies, other keys are borrowed.
+    #[intuicio_method(name = "keys", transformer = "DynamicManagedValueTransformer")]
+    pub fn script_keys(&self) -> AsyncArray {
+        self.items
+            .keys()
+            .map(|key| AnkhaAsyncOption::from_literal(key.to_owned()))
+            .chain(
+                self.values
+                    .iter()
+                    .map(|(key, _)| key.borrow().map(|key| key.into()).unwrap_or_default()),
+            )
+            .collect()
+    }
+
+    // Values are in same order as keys, empty values stay empty.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn values_ref(&self) -> AsyncArray {
+        self.all_values()
+            .map(|item| item.borrow().map(|item| item.into()).unwrap_or_default())
+            .collect()
+    }
+
+    // Each entry is array of key and borrowed value.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn entries(&self) -> AsyncArray {
+        self.script_keys()
+            .into_iter()
+            .zip(self.values_ref())
+            .map(|(key, item)| {
+                let entry = [key, item].into_iter().collect::<AsyncArray>();
+                DynamicManaged::new(entry).ok().unwrap().into()
+            })
+            .collect()
+    }
+
+    // Moves all entries of other dictionary, replacing existing values.
+    #[intuicio_method(
+        name = "extend",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn extend_value(&mut self, context: &mut Context, registry: &Registry, other: Self) {
+        self.items.extend(other.items);
+        for (key, item) in other.values {
+            self.values.insert(context, registry, key, item);
+        }
+    }
+
+    // Moves entries of other dictionary, keeping existing values.
+    #[intuicio_method(
+        name = "merge",
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn merge_value(&mut self, context: &mut Context, registry: &Registry, other: Self) {
+        for (key, item) in other.items {
+            self.items.entry(key).or_insert(item);
+        }
+        for (key, item) in other.values {
+            let exists = key
+                .borrow()
+                .map(|key| self.values.get(context, registry, &key).is_some())
+                .unwrap_or_default();
+            if !exists {
+                self.values.insert(context, registry, key, item);
+            }
+        }
+    }
+
+    // Closure gets key and borrowed value, entries with empty values are kept
+    // without asking closure.
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn retain(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        closure: &mut AsyncClosure,
+    ) {
+        self.items.retain(|key, item| match item.borrow() {
+            Some(item) => closure.predicate(
+                context,
+                registry,
+                vec![AnkhaAsyncOption::from_literal(key.to_owned()), item.into()],
+                "retain",
+            ),
+            None => true,
+        });
+        self.values
+            .retain(|key, item| match (key.borrow(), item.borrow()) {
+                (Some(key), Some(item)) => {
+                    closure.predicate(context, registry, vec![key.into(), item.into()], "retain")
+                }
+                _ => true,
+            });
     }
 }
 
@@ -309,12 +1232,723 @@ impl FromIterator<(AnkhaLiteral, AnkhaAsyncOption)> for AsyncDictionary {
     fn from_iter<T: IntoIterator<Item = (AnkhaLiteral, AnkhaAsyncOption)>>(iter: T) -> Self {
         Self {
             items: iter.into_iter().collect(),
+            values: Default::default(),
         }
     }
 }
 
 impl From<HashMap<AnkhaLiteral, AnkhaAsyncOption>> for AsyncDictionary {
     fn from(value: HashMap<AnkhaLiteral, AnkhaAsyncOption>) -> Self {
-        Self { items: value }
+        Self {
+            items: value,
+            values: Default::default(),
+        }
+    }
+}
+
+// Dictionaries with literal keys only differ by map that keeps their entries,
+// so they share type definition and method set. Async variants have no
+// iterators, since `AnkhaIterator` yields non-async options.
+macro_rules! literal_dictionary {
+    (
+        $name:ident as $script_name:literal in $module_name:literal,
+        $map:ident<$key:ident, $option:ident>,
+        $array:ident,
+        $closure:ident
+    ) => {
+        #[derive(IntuicioStruct, Default)]
+        #[intuicio(name = $script_name, module_name = $module_name)]
+        pub struct $name {
+            #[intuicio(ignore)]
+            items: $map<$key, $option>,
+        }
+
+        impl $name {
+            fn install(registry: &mut Registry) {
+                registry.add_type(Self::define_struct(registry));
+                registry.add_function(Self::is_empty__define_function(registry));
+                registry.add_function(Self::size__define_function(registry));
+                registry.add_function(Self::exists__define_function(registry));
+                registry.add_function(Self::is_valid__define_function(registry));
+                registry.add_function(Self::get__define_function(registry));
+                registry.add_function(Self::get_mut__define_function(registry));
+                registry.add_function(Self::get_lazy__define_function(registry));
+                registry.add_function(Self::insert__define_function(registry));
+                registry.add_function(Self::remove__define_function(registry));
+                registry.add_function(Self::clear__define_function(registry));
+                registry.add_function(Self::script_keys__define_function(registry));
+                registry.add_function(Self::values_ref__define_function(registry));
+                registry.add_function(Self::entries__define_function(registry));
+                registry.add_function(Self::extend__define_function(registry));
+                registry.add_function(Self::merge__define_function(registry));
+                registry.add_function(Self::retain__define_function(registry));
+            }
+
+            pub fn inner(&self) -> &$map<$key, $option> {
+                &self.items
+            }
+
+            pub fn inner_mut(&mut self) -> &mut $map<$key, $option> {
+                &mut self.items
+            }
+
+            pub fn iter(&self) -> impl Iterator<Item = (&$key, &$option)> {
+                self.items.iter()
+            }
+
+            pub fn iter_mut(&mut self) -> impl Iterator<Item = (&$key, &mut $option)> {
+                self.items.iter_mut()
+            }
+
+            pub fn keys(&self) -> impl Iterator<Item = &$key> {
+                self.items.keys()
+            }
+
+            pub fn values(&self) -> impl Iterator<Item = &$option> {
+                self.items.values()
+            }
+
+            pub fn values_mut(&mut self) -> impl Iterator<Item = &mut $option> {
+                self.items.values_mut()
+            }
+        }
+
+        impl IntoIterator for $name {
+            type Item = ($key, $option);
+            type IntoIter = <$map<$key, $option> as IntoIterator>::IntoIter;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.items.into_iter()
+            }
+        }
+
+        #[intuicio_methods(module_name = $module_name)]
+        impl $name {
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn is_empty(&self) -> bool {
+                self.items.is_empty()
+            }
+
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn size(&self) -> usize {
+                self.items.len()
+            }
+
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn exists(&self, key: $option) -> bool {
+                key.into_literal()
+                    .map(|key| self.items.contains_key(&key.into()))
+                    .unwrap_or_default()
+            }
+
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn is_valid(&self, key: $option) -> bool {
+                key.into_literal()
+                    .and_then(|key| self.items.get(&key.into()))
+                    .map(|item| item.is_some())
+                    .unwrap_or_default()
+            }
+
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn get(&self, key: $option) -> $option {
+                key.into_literal()
+                    .and_then(|key| self.items.get(&key.into()))
+                    .and_then(|item| item.borrow())
+                    .map(|item| item.into())
+                    .unwrap_or_default()
+            }
+
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn get_mut(&mut self, key: $option) -> $option {
+                key.into_literal()
+                    .and_then(|key| self.items.get_mut(&key.into()))
+                    .and_then(|item| item.borrow_mut())
+                    .map(|item| item.into())
+                    .unwrap_or_default()
+            }
+
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn get_lazy(&mut self, key: $option) -> $option {
+                key.into_literal()
+                    .and_then(|key| self.items.get_mut(&key.into()))
+                    .and_then(|item| item.lazy())
+                    .map(|item| item.into())
+                    .unwrap_or_default()
+            }
+
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn insert(&mut self, key: $option, value: $option) -> $option {
+                key.into_literal()
+                    .and_then(|key| self.items.insert(key.into(), value))
+                    .unwrap_or_default()
+            }
+
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn remove(&mut self, key: $option) -> $option {
+                key.into_literal()
+                    .and_then(|key| self.items.remove(&key.into()))
+                    .unwrap_or_default()
+            }
+
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn clear(&mut self) {
+                self.items.clear();
+            }
+
+            #[intuicio_method(name = "keys", transformer = "DynamicManagedValueTransformer")]
+            pub fn script_keys(&self) -> $array {
+                self.items
+                    .keys()
+                    .map(|key| $option::from_literal(AnkhaLiteral::from(key.to_owned())))
+                    .collect()
+            }
+
+            // Values are in same order as keys, empty values stay empty.
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn values_ref(&self) -> $array {
+                self.items
+                    .values()
+                    .map(|item| item.borrow().map(|item| item.into()).unwrap_or_default())
+                    .collect()
+            }
+
+            // Each entry is array of owned key and borrowed value.
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn entries(&self) -> $array {
+                self.items
+                    .iter()
+                    .map(|(key, item)| {
+                        let entry = [
+                            $option::from_literal(AnkhaLiteral::from(key.to_owned())),
+                            item.borrow().map(|item| item.into()).unwrap_or_default(),
+                        ]
+                        .into_iter()
+                        .collect::<$array>();
+                        DynamicManaged::new(entry).ok().unwrap().into()
+                    })
+                    .collect()
+            }
+
+            // Moves all entries of other dictionary, replacing existing values.
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn extend(&mut self, other: Self) {
+                self.items.extend(other.items);
+            }
+
+            // Moves entries of other dictionary, keeping existing values.
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn merge(&mut self, other: Self) {
+                for (key, item) in other.items {
+                    if !self.items.contains_key(&key) {
+                        self.items.insert(key, item);
+                    }
+                }
+            }
+
+            // Closure gets owned key and borrowed value, entries with empty values
+            // are kept without asking closure.
+            #[intuicio_method(
+                use_context,
+                use_registry,
+                transformer = "DynamicManagedValueTransformer"
+            )]
+            pub fn retain(
+                &mut self,
+                context: &mut Context,
+                registry: &Registry,
+                closure: &mut $closure,
+            ) {
+                self.items.retain(|key, item| match item.borrow() {
+                    Some(item) => closure.predicate(
+                        context,
+                        registry,
+                        vec![
+                            $option::from_literal(AnkhaLiteral::from(key.to_owned())),
+                            item.into(),
+                        ],
+                        "retain",
+                    ),
+                    None => true,
+                });
+            }
+        }
+
+        impl FromIterator<(AnkhaLiteral, $option)> for $name {
+            fn from_iter<T: IntoIterator<Item = (AnkhaLiteral, $option)>>(iter: T) -> Self {
+                Self {
+                    items: iter
+                        .into_iter()
+                        .map(|(key, item)| (key.into(), item))
+                        .collect(),
+                }
+            }
+        }
+
+        impl From<$map<$key, $option>> for $name {
+            fn from(value: $map<$key, $option>) -> Self {
+                Self { items: value }
+            }
+        }
+    };
+    (
+        $name:ident in $module_name:literal,
+        iterators
+    ) => {
+        impl $name {
+            fn install_iterators(registry: &mut Registry) {
+                registry.add_function(Self::iter_keys__define_function(registry));
+                registry.add_function(Self::iter_values__define_function(registry));
+                registry.add_function(Self::iter_values_mut__define_function(registry));
+            }
+        }
+
+        #[intuicio_methods(module_name = $module_name)]
+        impl $name {
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn iter_keys(&self) -> AnkhaIterator {
+                let keys = self
+                    .items
+                    .keys()
+                    .map(|key| AnkhaOption::from_literal(AnkhaLiteral::from(key.to_owned())))
+                    .collect::<Vec<_>>();
+                AnkhaIterator::new(keys.into_iter())
+            }
+
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn iter_values(&self) -> AnkhaIterator {
+                let values = self
+                    .items
+                    .values()
+                    .filter_map(|item| item.borrow())
+                    .map(|item| item.into())
+                    .collect::<Vec<AnkhaOption>>();
+                AnkhaIterator::new(values.into_iter())
+            }
+
+            #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+            pub fn iter_values_mut(&mut self) -> AnkhaIterator {
+                let values = self
+                    .items
+                    .values_mut()
+                    .filter_map(|item| item.borrow_mut())
+                    .map(|item| item.into())
+                    .collect::<Vec<AnkhaOption>>();
+                AnkhaIterator::new(values.into_iter())
+            }
+        }
+    };
+}
+
+pub(crate) use literal_dictionary;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        library::{
+            event::EventHandle,
+            tests::{TestHost, literal, math_function},
+        },
+        script::AnkhaError,
+    };
+
+    #[test]
+    fn test_dictionary_bulk() {
+        let TestHost {
+            registry,
+            mut context,
+        } = TestHost::default();
+        let lt = math_function(&registry, "lt");
+        let pairs = |values: &[(i32, i32)]| {
+            values
+                .iter()
+                .map(|(key, value)| (AnkhaLiteral::I32(*key), AnkhaLiteral::I32(*value)))
+                .collect::<Vec<_>>()
+        };
+        let sorted = |mut values: Vec<(AnkhaLiteral, AnkhaLiteral)>| {
+            values.sort_by_key(|(key, _)| key.format());
+            values
+        };
+        let entries = |array: &Array| {
+            sorted(
+                array
+                    .iter()
+                    .map(|entry| {
+                        let entry = entry.borrow().unwrap();
+                        let entry = entry.read::<Array>().unwrap();
+                        (
+                            entry.inner()[0].read_literal().unwrap(),
+                            entry.inner()[1].read_literal().unwrap(),
+                        )
+                    })
+                    .collect(),
+            )
+        };
+        let dictionary = |values: &[(i32, i32)]| {
+            values
+                .iter()
+                .map(|(key, value)| {
+                    (
+                        AnkhaLiteral::I32(*key),
+                        AnkhaOption::from_literal(AnkhaLiteral::I32(*value)),
+                    )
+                })
+                .collect::<Dictionary>()
+        };
+
+        let mut items = dictionary(&[(1, 5), (2, 1)]);
+        let keys = items.script_keys();
+        let values = items.values_ref();
+        assert!(values.iter().all(|item| item.is_ref()));
+        assert_eq!(
+            sorted(
+                keys.iter()
+                    .zip(values.iter())
+                    .map(|(key, value)| (
+                        key.read_literal().unwrap(),
+                        value.read_literal().unwrap()
+                    ))
+                    .collect()
+            ),
+            pairs(&[(1, 5), (2, 1)])
+        );
+        drop(values);
+        assert_eq!(entries(&items.entries()), pairs(&[(1, 5), (2, 1)]));
+
+        items.merge(dictionary(&[(2, 9), (3, 7)]));
+        assert_eq!(entries(&items.entries()), pairs(&[(1, 5), (2, 1), (3, 7)]));
+        items.extend(dictionary(&[(2, 9), (4, 3)]));
+        assert_eq!(
+            entries(&items.entries()),
+            pairs(&[(1, 5), (2, 9), (3, 7), (4, 3)])
+        );
+        // Keeps entries with key less than value.
+        items.retain(
+            &mut context,
+            &registry,
+            &mut Closure::from_function(lt.clone()),
+        );
+        assert_eq!(entries(&items.entries()), pairs(&[(1, 5), (2, 9), (3, 7)]));
+
+        let mut items = [(1, 5), (6, 2)]
+            .into_iter()
+            .map(|(key, value)| {
+                (
+                    AnkhaLiteral::I32(key),
+                    AnkhaAsyncOption::from_literal(AnkhaLiteral::I32(value)),
+                )
+            })
+            .collect::<AsyncDictionary>();
+        items.retain(
+            &mut context,
+            &registry,
+            &mut AsyncClosure::from_function(lt),
+        );
+        let entries = items.entries();
+        assert_eq!(entries.inner().len(), 1);
+        let entry = entries.inner()[0].borrow().unwrap();
+        let entry = entry.read::<AsyncArray>().unwrap();
+        assert_eq!(entry.inner()[0].read_literal(), Some(AnkhaLiteral::I32(1)));
+        assert_eq!(entry.inner()[1].read_literal(), Some(AnkhaLiteral::I32(5)));
+        assert_eq!(context.stack().position(), 0);
+    }
+
+    #[derive(IntuicioStruct)]
+    #[intuicio(module_name = "test")]
+    struct KeyPoint {
+        pub x: i32,
+        pub y: DynamicManaged,
+    }
+
+    impl Default for KeyPoint {
+        fn default() -> Self {
+            Self {
+                x: 0,
+                y: DynamicManaged::new(0).unwrap(),
+            }
+        }
+    }
+
+    #[derive(IntuicioStruct, Default)]
+    #[intuicio(module_name = "test")]
+    struct KeyName {
+        pub name: String,
+    }
+
+    #[intuicio_methods(module_name = "test")]
+    impl KeyName {
+        #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+        fn hash(&self) -> u64 {
+            let mut hasher = std::hash::DefaultHasher::new();
+            self.name.to_lowercase().hash(&mut hasher);
+            hasher.finish()
+        }
+
+        #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+        fn eq(&self, other: &Self) -> bool {
+            self.name.to_lowercase() == other.name.to_lowercase()
+        }
+    }
+
+    #[test]
+    fn test_dictionary_value_keys() {
+        let TestHost {
+            mut registry,
+            mut context,
+        } = TestHost::default();
+        registry.add_type(KeyPoint::define_struct(&registry));
+        registry.add_type(KeyName::define_struct(&registry));
+        registry.add_function(KeyName::hash__define_function(&registry));
+        registry.add_function(KeyName::eq__define_function(&registry));
+        let owned = |value| AnkhaOption::Owned(value);
+        let point = |x: i32, y: i32| {
+            owned(
+                DynamicManaged::new(KeyPoint {
+                    x,
+                    y: DynamicManaged::new(y).ok().unwrap(),
+                })
+                .ok()
+                .unwrap(),
+            )
+        };
+        let name = |name: &str| {
+            owned(
+                DynamicManaged::new(KeyName {
+                    name: name.to_owned(),
+                })
+                .ok()
+                .unwrap(),
+            )
+        };
+        let mut dictionary = Dictionary::default();
+
+        // Structural keys are compared over their fields.
+        dictionary.insert_value(&mut context, &registry, point(1, 2), literal(12));
+        dictionary.insert_value(&mut context, &registry, point(2, 1), literal(21));
+        let old = dictionary.insert_value(&mut context, &registry, point(1, 2), literal(42));
+        assert_eq!(old.read_literal(), Some(AnkhaLiteral::I32(12)));
+        assert_eq!(
+            dictionary
+                .get_value(&mut context, &registry, point(1, 2))
+                .read_literal(),
+            Some(AnkhaLiteral::I32(42))
+        );
+        assert!(!dictionary.exists_value(&mut context, &registry, point(3, 3)));
+        // Rust-side methods take literal keys only.
+        assert!(!dictionary.exists(point(1, 2)));
+        assert!(dictionary.insert(point(1, 2), literal(0)).is_none());
+        assert!(dictionary.remove(point(1, 2)).is_none());
+        assert_eq!(dictionary.size(), 2);
+
+        // Keys with `hash` and `eq` methods are compared with them.
+        dictionary.insert_value(&mut context, &registry, name("Alice"), literal(1));
+        assert_eq!(
+            dictionary
+                .get_value(&mut context, &registry, name("ALICE"))
+                .read_literal(),
+            Some(AnkhaLiteral::I32(1))
+        );
+
+        let handle_a = EventHandle::default();
+        let handle_b = EventHandle::default();
+        let handle = |handle| owned(DynamicManaged::new(handle).ok().unwrap());
+        dictionary.insert_value(&mut context, &registry, handle(handle_a), literal(100));
+        dictionary.insert_value(&mut context, &registry, handle(handle_b), literal(200));
+        assert_eq!(
+            dictionary
+                .get_value(&mut context, &registry, handle(handle_b))
+                .read_literal(),
+            Some(AnkhaLiteral::I32(200))
+        );
+
+        dictionary.insert(literal(7), literal(7));
+        assert_eq!(dictionary.size(), 6);
+        assert_eq!(dictionary.script_keys().inner().len(), 6);
+        let removed = dictionary.remove_value(&mut context, &registry, name("alice"));
+        assert_eq!(removed.read_literal(), Some(AnkhaLiteral::I32(1)));
+        assert_eq!(dictionary.size(), 5);
+
+        // Opaque native keys cannot be told apart without methods.
+        let error = AnkhaError::catch(&mut context, |context| {
+            let key = owned(DynamicManaged::new(Dictionary::default()).ok().unwrap());
+            dictionary.insert_value(context, &registry, key, literal(0));
+        })
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            AnkhaError::Panic { ref message, .. }
+                if message == "Dictionary key of `Dictionary` type needs `hash` and `eq` methods!"
+        ));
+        assert_eq!(dictionary.size(), 5);
+        assert_eq!(context.stack().position(), 0);
+    }
+
+    #[derive(IntuicioStruct, Default)]
+    #[intuicio(module_name = "test")]
+    struct KeyBucket {
+        pub id: i32,
+    }
+
+    #[intuicio_methods(module_name = "test")]
+    impl KeyBucket {
+        #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+        fn hash(&self) -> u64 {
+            0
+        }
+
+        #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    #[test]
+    fn test_dictionary_colliding_keys() {
+        let TestHost {
+            mut registry,
+            mut context,
+        } = TestHost::default();
+        registry.add_type(KeyBucket::define_struct(&registry));
+        registry.add_function(KeyBucket::hash__define_function(&registry));
+        registry.add_function(KeyBucket::eq__define_function(&registry));
+        let bucket =
+            |id: i32| AnkhaOption::Owned(DynamicManaged::new(KeyBucket { id }).ok().unwrap());
+        let mut dictionary = Dictionary::default();
+
+        // Keys with same hash share bucket, yet stay apart unless equal.
+        for id in 1..=3 {
+            dictionary.insert_value(&mut context, &registry, bucket(id), literal(id * 10));
+        }
+        let old = dictionary.insert_value(&mut context, &registry, bucket(2), literal(22));
+        assert_eq!(old.read_literal(), Some(AnkhaLiteral::I32(20)));
+        assert_eq!(dictionary.size(), 3);
+        let removed = dictionary.remove_value(&mut context, &registry, bucket(1));
+        assert_eq!(removed.read_literal(), Some(AnkhaLiteral::I32(10)));
+        assert!(!dictionary.exists_value(&mut context, &registry, bucket(1)));
+        let found = [2, 3]
+            .into_iter()
+            .map(|id| {
+                dictionary
+                    .get_value(&mut context, &registry, bucket(id))
+                    .read_literal()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![Some(AnkhaLiteral::I32(22)), Some(AnkhaLiteral::I32(30))]
+        );
+        assert_eq!(dictionary.size(), 2);
+        assert_eq!(context.stack().position(), 0);
+    }
+
+    #[test]
+    fn test_dictionary_script_keys() {
+        let mut host = TestHost::default();
+        // Script types share runtime type hash, yet keys of different types
+        // never compare equal and use methods of their own type only.
+        host.install(
+            r#"(file
+                (mod "test"
+                    (struct "Cell" (field "x" (kind owned)))
+                    (struct "Slot" (field "x" (kind owned)))
+                    (enum "Shape" (var "Empty") (var "Dot" (field "x" (kind owned))))
+                    (enum "Mark" (var "Empty") (var "Dot" (field "x" (kind owned))))
+                    (fn "hash"
+                        (type "Cell")
+                        (in "self" (kind ref))
+                        (out "result" (kind owned))
+                        (body (stack_drop) (lit u64 0))
+                    )
+                    (fn "eq"
+                        (type "Cell")
+                        (in "self" (kind ref))
+                        (in "other" (kind ref))
+                        (out "result" (kind owned))
+                        (body (stack_drop) (stack_drop) (lit bool true))
+                    )
+                    (fn "cell"
+                        (in "x" (kind owned))
+                        (out "result" (kind owned))
+                        (body (structure (type "Cell") "x"))
+                    )
+                    (fn "slot"
+                        (in "x" (kind owned))
+                        (out "result" (kind owned))
+                        (body (structure (type "Slot") "x"))
+                    )
+                    (fn "shape"
+                        (in "x" (kind owned))
+                        (out "result" (kind owned))
+                        (body (variant (type "Shape") "Dot" "x"))
+                    )
+                    (fn "mark"
+                        (in "x" (kind owned))
+                        (out "result" (kind owned))
+                        (body (variant (type "Mark") "Dot" "x"))
+                    )
+                    (fn "empty_shape"
+                        (out "result" (kind owned))
+                        (body (variant (type "Shape") "Empty"))
+                    )
+                    (fn "empty_mark"
+                        (out "result" (kind owned))
+                        (body (variant (type "Mark") "Empty"))
+                    )
+                )
+            )"#,
+        );
+        let mut make = |name: &str, x: Option<i32>| {
+            if let Some(x) = x {
+                host.context
+                    .stack()
+                    .push(DynamicManaged::new(x).ok().unwrap());
+            }
+            host.invoke(name).unwrap();
+            AnkhaOption::Owned(host.context.stack().pop::<DynamicManaged>().unwrap())
+        };
+        let keys = [
+            make("cell", Some(1)),
+            make("cell", Some(2)),
+            make("slot", Some(1)),
+            make("slot", Some(2)),
+            make("shape", Some(1)),
+            make("mark", Some(1)),
+            make("empty_shape", None),
+            make("empty_mark", None),
+        ];
+        let probes = [
+            make("cell", Some(3)),
+            make("slot", Some(2)),
+            make("mark", Some(1)),
+            make("empty_shape", None),
+        ];
+        let mut dictionary = Dictionary::default();
+        for (index, key) in keys.into_iter().enumerate() {
+            dictionary.insert_value(
+                &mut host.context,
+                &host.registry,
+                key,
+                literal(index as i32),
+            );
+        }
+        assert_eq!(dictionary.size(), 7);
+        let found = probes
+            .into_iter()
+            .map(|key| {
+                dictionary
+                    .get_value(&mut host.context, &host.registry, key)
+                    .read_literal()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                Some(AnkhaLiteral::I32(1)),
+                Some(AnkhaLiteral::I32(3)),
+                Some(AnkhaLiteral::I32(5)),
+                Some(AnkhaLiteral::I32(6)),
+            ]
+        );
+        assert_eq!(host.context.stack().position(), 0);
     }
 }
@@ -10,11 +10,16 @@ use intuicio_core::{
     transformer::{DynamicManagedValueTransformer, ValueTransformer},
 };
 use intuicio_derive::{IntuicioStruct, intuicio_method, intuicio_methods};
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
 use typid::ID;
 
 pub fn install(registry: &mut Registry) {
     registry.add_type(EventHandle::define_struct(registry));
+    registry.add_function(EventHandle::script_hash__define_function(registry));
+    registry.add_function(EventHandle::script_eq__define_function(registry));
     registry.add_type(Event::define_struct(registry));
     registry.add_function(Event::is_bound__define_function(registry));
     registry.add_function(Event::subscribe__define_function(registry));
@@ -34,6 +39,22 @@ pub struct EventHandle {
     id: ID<Event>,
 }
 
+// Handles can be dictionary keys.
+#[intuicio_methods(module_name = "event")]
+impl EventHandle {
+    #[intuicio_method(name = "hash", transformer = "DynamicManagedValueTransformer")]
+    fn script_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[intuicio_method(name = "eq", transformer = "DynamicManagedValueTransformer")]
+    fn script_eq(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
 #[derive(IntuicioStruct, Default)]
 #[intuicio(module_name = "event")]
 pub struct Event {
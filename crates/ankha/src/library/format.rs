@@ -0,0 +1,479 @@
+// `DynamicManagedValueTransformer` borrows managed strings as `String`, hence `&String` inputs.
+#![allow(clippy::ptr_arg)]
+
+use crate::{
+    library::{array::Array, option::AnkhaOption},
+    script::{enum_variant, find_value_type, type_handle_query, type_identity},
+};
+use intuicio_core::{
+    IntuicioStruct,
+    context::Context,
+    function::{Function, FunctionBody, FunctionParameter, FunctionQuery, FunctionSignature},
+    registry::Registry,
+    transformer::{DynamicManagedValueTransformer, ValueTransformer},
+    types::{TypeQuery, struct_type::StructField},
+};
+use intuicio_data::{
+    lifetime::Lifetime,
+    managed::{DynamicManaged, DynamicManagedLazy, DynamicManagedRef, DynamicManagedRefMut},
+    managed_box::DynamicManagedBox,
+    type_hash::TypeHash,
+};
+use intuicio_derive::{IntuicioStruct, intuicio_function, intuicio_method, intuicio_methods};
+
+pub const FORMAT_FUNCTION_NAME: &str = "format";
+
+pub fn install(registry: &mut Registry) {
+    registry.add_type(Formatter::define_struct(registry));
+    registry.add_function(Formatter::new__define_function(registry));
+    registry.add_function(Formatter::write__define_function(registry));
+    registry.add_function(Formatter::write_value__define_function(registry));
+    registry.add_function(Formatter::build__define_function(registry));
+    registry.add_function(format::define_function(registry));
+    install_primitive::<()>(registry);
+    install_primitive::<bool>(registry);
+    install_primitive::<i8>(registry);
+    install_primitive::<i16>(registry);
+    install_primitive::<i32>(registry);
+    install_primitive::<i64>(registry);
+    install_primitive::<i128>(registry);
+    install_primitive::<isize>(registry);
+    install_primitive::<u8>(registry);
+    install_primitive::<u16>(registry);
+    install_primitive::<u32>(registry);
+    install_primitive::<u64>(registry);
+    install_primitive::<u128>(registry);
+    install_primitive::<usize>(registry);
+    install_primitive::<f32>(registry);
+    install_primitive::<f64>(registry);
+    install_primitive::<char>(registry);
+    install_primitive::<String>(registry);
+}
+
+// Primitives get `format` method with same signature as user-defined ones,
+// so scripts can format any field with `call_method`.
+fn install_primitive<T: 'static>(registry: &mut Registry) {
+    let type_handle = registry.find_type(TypeQuery::of::<T>()).unwrap_or_else(|| {
+        panic!(
+            "Format library requires `{}` type to be registered!",
+            std::any::type_name::<T>()
+        )
+    });
+    let ref_handle = registry
+        .find_type(TypeQuery::of::<DynamicManagedRef>())
+        .expect("Could not find `DynamicManagedRef` type!");
+    let ref_mut_handle = registry
+        .find_type(TypeQuery::of::<DynamicManagedRefMut>())
+        .expect("Could not find `DynamicManagedRefMut` type!");
+    let signature = FunctionSignature::new(FORMAT_FUNCTION_NAME)
+        .with_module_name("format")
+        .with_type_handle(type_handle)
+        .with_input(FunctionParameter::new("self", ref_handle))
+        .with_input(FunctionParameter::new("fmt", ref_mut_handle));
+    registry.add_function(Function::new(
+        signature,
+        FunctionBody::closure(|context, _| {
+            let value = AnkhaOption::pop(context)
+                .read_literal()
+                .expect("Could not format non-primitive value!");
+            let mut formatter = AnkhaOption::pop(context);
+            let mut formatter = formatter
+                .borrow_mut()
+                .expect("Could not borrow formatter mutably!");
+            let mut formatter = formatter
+                .write::<Formatter>()
+                .expect("Value passed as formatter is not `Formatter`!");
+            formatter.buffer.push_str(&value.format());
+        }),
+    ));
+}
+
+#[intuicio_function(
+    module_name = "format",
+    use_context,
+    use_registry,
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn format(
+    context: &mut Context,
+    registry: &Registry,
+    template: &String,
+    arguments: &Array,
+) -> String {
+    let mut formatter = Formatter::default();
+    let mut items = arguments.iter();
+    let mut chars = template.chars().peekable();
+    while let Some(character) = chars.next() {
+        match (character, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                formatter.buffer.push(character);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                let item = items.next().unwrap_or_else(|| {
+                    panic!("Format template `{}` expects more arguments!", template)
+                });
+                formatter.write_option(context, registry, item);
+            }
+            _ => {
+                formatter.buffer.push(character);
+            }
+        }
+    }
+    if items.next().is_some() {
+        panic!("Format template `{}` got too many arguments!", template);
+    }
+    formatter.buffer
+}
+
+#[derive(IntuicioStruct, Default)]
+#[intuicio(name = "Formatter", module_name = "format")]
+pub struct Formatter {
+    #[intuicio(ignore)]
+    buffer: String,
+    // Values currently being formatted, so reference cycles print `<cycle>`
+    // instead of recursing forever.
+    #[intuicio(ignore)]
+    visiting: Vec<(TypeHash, usize)>,
+}
+
+impl Formatter {
+    pub fn as_str(&self) -> &str {
+        &self.buffer
+    }
+
+    pub fn into_inner(self) -> String {
+        self.buffer
+    }
+
+    pub fn write_option(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        value: &AnkhaOption,
+    ) {
+        if let Some(value) = value.borrow() {
+            self.write_managed(context, registry, value);
+        } else {
+            self.buffer.push_str("none");
+        }
+    }
+
+    // Primitives are formatted natively, other types with their `format`
+    // method, falling back to reflection over their fields.
+    pub fn write_managed(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        value: DynamicManagedRef,
+    ) {
+        if let Some(literal) = value
+            .borrow()
+            .and_then(|value| AnkhaOption::Ref(value).read_literal())
+        {
+            self.buffer.push_str(&literal.format());
+            return;
+        }
+        let pointer = unsafe { value.as_ptr_raw() }.expect("Could not read formatted value!");
+        // Type is part of key, because struct shares address with its first field.
+        let key = (*value.type_hash(), pointer as usize);
+        if self.visiting.contains(&key) {
+            self.buffer.push_str("<cycle>");
+            return;
+        }
+        self.visiting.push(key);
+        self.write_object(context, registry, value, pointer);
+        self.visiting.pop();
+    }
+
+    fn write_object(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        value: DynamicManagedRef,
+        pointer: *const u8,
+    ) {
+        let type_ = find_value_type(registry, *value.type_hash())
+            .expect("Could not find type of formatted value!");
+        if let Some(function) = registry.find_function(FunctionQuery {
+            name: Some(FORMAT_FUNCTION_NAME.into()),
+            type_query: Some(type_handle_query(&type_)),
+            ..Default::default()
+        }) {
+            let lifetime = Lifetime::default();
+            let formatter = DynamicManagedRefMut::new(self, lifetime.borrow_mut().unwrap());
+            context.stack().push(formatter);
+            context.stack().push(value);
+            function.invoke(context, registry);
+            return;
+        }
+        self.buffer.push_str(type_.name());
+        if let Some(struct_type) = type_.as_struct() {
+            self.write_fields(
+                context,
+                registry,
+                type_.name(),
+                struct_type.fields(),
+                pointer,
+            );
+        } else if let Some(enum_type) = type_.as_enum()
+            && let Some(variant) = unsafe { enum_variant(enum_type, pointer) }
+        {
+            self.buffer.push_str("::");
+            self.buffer.push_str(&variant.name);
+            self.write_fields(context, registry, type_.name(), &variant.fields, pointer);
+        }
+    }
+
+    fn write_fields(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        owner: &str,
+        fields: &[StructField],
+        pointer: *const u8,
+    ) {
+        if fields.is_empty() {
+            return;
+        }
+        self.buffer.push_str(" { ");
+        for (index, field) in fields.iter().enumerate() {
+            if index > 0 {
+                self.buffer.push_str(", ");
+            }
+            self.buffer.push_str(&field.name);
+            self.buffer.push_str(": ");
+            let pointer = unsafe { pointer.add(field.address_offset()) };
+            let value = unsafe { managed_field(field.type_handle().type_hash(), pointer) };
+            let lifetime = Lifetime::default();
+            let value = value.or_else(|| unsafe {
+                DynamicManagedRef::new_raw(
+                    type_identity(field.type_handle()),
+                    lifetime.borrow().unwrap(),
+                    pointer,
+                )
+            });
+            if let Some(value) = value {
+                self.write_managed(context, registry, value);
+            } else {
+                panic!(
+                    "Could not borrow `{}` field of `{}` type for formatting!",
+                    field.name, owner
+                );
+            }
+        }
+        self.buffer.push_str(" }");
+    }
+}
+
+// Borrows content of managed field, or none if field is not managed.
+pub(crate) unsafe fn managed_field(
+    type_hash: TypeHash,
+    pointer: *const u8,
+) -> Option<DynamicManagedRef> {
+    unsafe {
+        if type_hash == TypeHash::of::<DynamicManaged>() {
+            (*pointer.cast::<DynamicManaged>()).borrow()
+        } else if type_hash == TypeHash::of::<DynamicManagedRef>() {
+            (*pointer.cast::<DynamicManagedRef>()).borrow()
+        } else if type_hash == TypeHash::of::<DynamicManagedRefMut>() {
+            (*pointer.cast::<DynamicManagedRefMut>()).borrow()
+        } else if type_hash == TypeHash::of::<DynamicManagedLazy>() {
+            (*pointer.cast::<DynamicManagedLazy>()).borrow()
+        } else if type_hash == TypeHash::of::<DynamicManagedBox>() {
+            (*pointer.cast::<DynamicManagedBox>()).borrow()
+        } else {
+            None
+        }
+    }
+}
+
+#[intuicio_methods(module_name = "format")]
+impl Formatter {
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn write(&mut self, value: &String) {
+        self.buffer.push_str(value);
+    }
+
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn write_value(&mut self, context: &mut Context, registry: &Registry, value: AnkhaOption) {
+        self.write_option(context, registry, &value);
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn build(&self) -> String {
+        self.buffer.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::tests::TestHost;
+
+    #[test]
+    fn test_format() {
+        let mut host = TestHost::default();
+        host.install(
+            r#"(file
+                (mod "test"
+                    (struct "Point" (field "x" (kind owned)))
+                    (struct "Size" (field "w" (kind owned)))
+                    (struct "Player"
+                        (field "id" (kind owned))
+                        (field "name" (kind owned))
+                    )
+                    (fn "format"
+                        (type "Point")
+                        (in "self" (kind ref))
+                        (in "fmt" (kind refmut))
+                        (body
+                            (stack_drop)
+                            (lit string "<point>")
+                            (borrow)
+                            (make_register "text" (kind owned))
+                            (pop_to_register "text")
+                            (call_method (fn "format" "format"))
+                        )
+                    )
+                    (fn "format"
+                        (type "Size")
+                        (in "self" (kind ref))
+                        (in "fmt" (kind refmut))
+                        (body
+                            (stack_drop)
+                            (lit string "<size>")
+                            (borrow)
+                            (make_register "text" (kind owned))
+                            (pop_to_register "text")
+                            (call_method (fn "format" "format"))
+                        )
+                    )
+                    (fn "make_point"
+                        (out "result" (kind owned))
+                        (body
+                            (lit i32 1)
+                            (structure (type "Point") "x")
+                        )
+                    )
+                    (fn "make_size"
+                        (out "result" (kind owned))
+                        (body
+                            (lit i32 2)
+                            (structure (type "Size") "w")
+                        )
+                    )
+                    (fn "make_player"
+                        (out "result" (kind owned))
+                        (body
+                            (lit string "Hero")
+                            (lit u8 7)
+                            (structure (type "Player") "id" "name")
+                        )
+                    )
+                )
+            )"#,
+        );
+        let mut run = |name: &str| {
+            host.invoke(name).unwrap();
+            let value = host.context.stack().pop::<DynamicManaged>().unwrap();
+            let step = DynamicManaged::new(3usize).ok().unwrap();
+            let arguments = Array::from(vec![
+                AnkhaOption::Ref(step.borrow().unwrap()),
+                AnkhaOption::Ref(value.borrow().unwrap()),
+                AnkhaOption::None,
+            ]);
+            let result = format(
+                &mut host.context,
+                &host.registry,
+                &"#{}: {} {{{}}}".to_owned(),
+                &arguments,
+            );
+            assert_eq!(host.context.stack().position(), 0);
+            result
+        };
+
+        assert_eq!(run("make_point"), "#3: <point> {none}");
+        assert_eq!(run("make_size"), "#3: <size> {none}");
+        assert_eq!(
+            run("make_player"),
+            "#3: Player { id: 7, name: Hero } {none}"
+        );
+    }
+
+    #[derive(IntuicioStruct)]
+    #[intuicio(name = "Link", module_name = "test", uninitialized)]
+    struct Link {
+        next: DynamicManagedRef,
+    }
+
+    #[derive(IntuicioStruct)]
+    #[intuicio(name = "Pair", module_name = "test", uninitialized)]
+    struct Pair {
+        left: DynamicManagedRef,
+        right: DynamicManagedRef,
+    }
+
+    #[test]
+    fn test_format_cycle() {
+        let TestHost {
+            mut registry,
+            mut context,
+        } = TestHost::default();
+        registry.add_type(Link::define_struct(&registry));
+        registry.add_type(Pair::define_struct(&registry));
+        let lifetime = Lifetime::default();
+        let zero = || DynamicManagedRef::new(&0u8, lifetime.borrow().unwrap());
+        let link_ref = |link: &Link| {
+            unsafe {
+                DynamicManagedRef::new_raw(
+                    TypeHash::of::<Link>(),
+                    lifetime.borrow().unwrap(),
+                    (link as *const Link).cast::<u8>(),
+                )
+            }
+            .unwrap()
+        };
+        let mut run = |value: DynamicManagedRef| {
+            let arguments = Array::from(vec![AnkhaOption::Ref(value)]);
+            format(&mut context, &registry, &"{}".to_owned(), &arguments)
+        };
+
+        let mut link = Link { next: zero() };
+        link.next = link_ref(&link);
+        assert_eq!(run(link_ref(&link)), "Link { next: <cycle> }");
+
+        // Cycle through other value is cut where it returns to first one.
+        let mut first = Link { next: zero() };
+        let second = Link {
+            next: link_ref(&first),
+        };
+        first.next = link_ref(&second);
+        assert_eq!(
+            run(link_ref(&first)),
+            "Link { next: Link { next: <cycle> } }"
+        );
+
+        // Value reached twice without cycle prints in full both times.
+        let shared = Link { next: zero() };
+        let pair = Pair {
+            left: link_ref(&shared),
+            right: link_ref(&shared),
+        };
+        assert_eq!(
+            run(DynamicManagedRef::new(&pair, lifetime.borrow().unwrap())),
+            "Pair { left: Link { next: 0 }, right: Link { next: 0 } }"
+        );
+        assert_eq!(context.stack().position(), 0);
+    }
+}
@@ -0,0 +1,179 @@
+// `DynamicManagedValueTransformer` borrows managed strings as `String`, hence `&String` inputs.
+#![allow(clippy::ptr_arg)]
+
+use intuicio_core::{
+    context::Context,
+    registry::Registry,
+    transformer::{DynamicManagedValueTransformer, ValueTransformer},
+};
+use intuicio_derive::intuicio_function;
+use std::io::Write;
+
+const OUTPUT_CUSTOM: &str = "ankha/output";
+
+pub fn install(registry: &mut Registry) {
+    registry.add_function(print::define_function(registry));
+    registry.add_function(print_nl::define_function(registry));
+    registry.add_function(eprint::define_function(registry));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnkhaOutputStream {
+    Out,
+    Err,
+}
+
+pub type AnkhaOutputCallback = Box<dyn FnMut(AnkhaOutputStream, &str) + Send + Sync>;
+
+// Sink of script output, stored in context by host.
+#[derive(Default)]
+pub enum AnkhaOutput {
+    #[default]
+    Standard,
+    Buffer {
+        out: String,
+        err: String,
+    },
+    Callback(AnkhaOutputCallback),
+}
+
+impl AnkhaOutput {
+    pub fn buffer() -> Self {
+        Self::Buffer {
+            out: Default::default(),
+            err: Default::default(),
+        }
+    }
+
+    pub fn callback(callback: impl FnMut(AnkhaOutputStream, &str) + Send + Sync + 'static) -> Self {
+        Self::Callback(Box::new(callback))
+    }
+
+    pub fn install(self, context: &mut Context) {
+        context.set_custom(OUTPUT_CUSTOM, self);
+    }
+
+    pub fn get(context: &Context) -> Option<&Self> {
+        context.custom::<Self>(OUTPUT_CUSTOM)
+    }
+
+    pub fn get_mut(context: &mut Context) -> Option<&mut Self> {
+        context.custom_mut::<Self>(OUTPUT_CUSTOM)
+    }
+
+    // Drains out and err contents of buffer sink, or none for other sinks.
+    pub fn take_buffer(context: &mut Context) -> Option<(String, String)> {
+        if let Some(Self::Buffer { out, err }) = Self::get_mut(context) {
+            Some((std::mem::take(out), std::mem::take(err)))
+        } else {
+            None
+        }
+    }
+
+    pub fn write(context: &mut Context, stream: AnkhaOutputStream, text: &str) {
+        match Self::get_mut(context) {
+            None | Some(Self::Standard) => match stream {
+                AnkhaOutputStream::Out => {
+                    let mut out = std::io::stdout().lock();
+                    let _ = out.write_all(text.as_bytes());
+                    let _ = out.flush();
+                }
+                AnkhaOutputStream::Err => {
+                    let mut err = std::io::stderr().lock();
+                    let _ = err.write_all(text.as_bytes());
+                    let _ = err.flush();
+                }
+            },
+            Some(Self::Buffer { out, err }) => match stream {
+                AnkhaOutputStream::Out => out.push_str(text),
+                AnkhaOutputStream::Err => err.push_str(text),
+            },
+            Some(Self::Callback(callback)) => callback(stream, text),
+        }
+    }
+}
+
+#[intuicio_function(
+    module_name = "io",
+    use_context,
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn print(context: &mut Context, text: &String) {
+    AnkhaOutput::write(context, AnkhaOutputStream::Out, text);
+}
+
+#[intuicio_function(
+    module_name = "io",
+    use_context,
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn print_nl(context: &mut Context, text: &String) {
+    AnkhaOutput::write(context, AnkhaOutputStream::Out, &format!("{}\n", text));
+}
+
+#[intuicio_function(
+    module_name = "io",
+    use_context,
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn eprint(context: &mut Context, text: &String) {
+    AnkhaOutput::write(context, AnkhaOutputStream::Err, text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::tests::TestHost;
+
+    #[test]
+    fn test_io() {
+        use std::sync::{Arc, Mutex};
+
+        let mut host = TestHost::default();
+        host.install(
+            r#"(file
+                (mod "test"
+                    (fn "greet"
+                        (body
+                            (lit string "Hello")
+                            (borrow)
+                            (make_register "hello" (kind owned))
+                            (pop_to_register "hello")
+                            (call_function (fn "print_nl" "io"))
+                            (lit string "oops")
+                            (borrow)
+                            (make_register "oops" (kind owned))
+                            (pop_to_register "oops")
+                            (call_function (fn "eprint" "io"))
+                        )
+                    )
+                )
+            )"#,
+        );
+
+        AnkhaOutput::buffer().install(&mut host.context);
+        host.invoke("greet").unwrap();
+        host.invoke("greet").unwrap();
+        assert_eq!(
+            AnkhaOutput::take_buffer(&mut host.context),
+            Some(("Hello\nHello\n".to_owned(), "oopsoops".to_owned()))
+        );
+        assert_eq!(host.context.stack().position(), 0);
+
+        let lines = Arc::new(Mutex::new(vec![]));
+        let lines2 = lines.clone();
+        AnkhaOutput::callback(move |stream, text| {
+            lines2.lock().unwrap().push((stream, text.to_owned()));
+        })
+        .install(&mut host.context);
+        host.invoke("greet").unwrap();
+        assert_eq!(
+            *lines.lock().unwrap(),
+            vec![
+                (AnkhaOutputStream::Out, "Hello\n".to_owned()),
+                (AnkhaOutputStream::Err, "oops".to_owned()),
+            ]
+        );
+        assert_eq!(AnkhaOutput::take_buffer(&mut host.context), None);
+    }
+}
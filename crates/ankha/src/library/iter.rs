@@ -0,0 +1,310 @@
+use crate::{
+    library::{array::Array, option::AnkhaOption},
+    script::{AnkhaCastMode, AnkhaLiteral},
+};
+use intuicio_core::{
+    IntuicioStruct,
+    function::{Function, FunctionBody, FunctionParameter, FunctionSignature},
+    registry::Registry,
+    transformer::{DynamicManagedValueTransformer, ValueTransformer},
+    types::TypeQuery,
+};
+use intuicio_data::{managed::DynamicManaged, type_hash::TypeHash};
+use intuicio_derive::{IntuicioStruct, intuicio_method, intuicio_methods};
+
+pub fn install(registry: &mut Registry) {
+    registry.add_type(AnkhaIterator::define_struct(registry));
+    registry.add_function(AnkhaIterator::script_next__define_function(registry));
+    registry.add_function(AnkhaIterator::script_collect__define_function(registry));
+    registry.add_function(range_function(registry, "range_in_ex", false));
+    registry.add_function(range_function(registry, "range_in_in", true));
+}
+
+fn range_function(registry: &Registry, name: &str, inclusive: bool) -> Function {
+    let managed_handle = registry
+        .find_type(TypeQuery::of::<DynamicManaged>())
+        .expect("Could not find `DynamicManaged` type!");
+    Function::new(
+        FunctionSignature::new(name)
+            .with_module_name("iter")
+            .with_input(FunctionParameter::new("from", managed_handle.clone()))
+            .with_input(FunctionParameter::new("to", managed_handle.clone()))
+            .with_output(FunctionParameter::new("result", managed_handle)),
+        FunctionBody::closure(move |context, _| {
+            let from = AnkhaOption::pop(context);
+            let to = AnkhaOption::pop(context);
+            let result = AnkhaIterator::range(from, to, inclusive);
+            context
+                .stack()
+                .push(DynamicManaged::new(result).ok().unwrap());
+        }),
+    )
+}
+
+fn is_integer(type_hash: TypeHash) -> bool {
+    type_hash == TypeHash::of::<i8>()
+        || type_hash == TypeHash::of::<i16>()
+        || type_hash == TypeHash::of::<i32>()
+        || type_hash == TypeHash::of::<i64>()
+        || type_hash == TypeHash::of::<i128>()
+        || type_hash == TypeHash::of::<isize>()
+        || type_hash == TypeHash::of::<u8>()
+        || type_hash == TypeHash::of::<u16>()
+        || type_hash == TypeHash::of::<u32>()
+        || type_hash == TypeHash::of::<u64>()
+        || type_hash == TypeHash::of::<u128>()
+        || type_hash == TypeHash::of::<usize>()
+}
+
+// Any type with `next` method returning option is iterable with `call_method`,
+// this one wraps native iterators. Since none marks end of iteration,
+// collection iterators leave empty items out instead of stopping early, so
+// yielded items do not line up with slots - `size` with `get`, or `values_ref`
+// of dictionaries, visit empty ones too.
+#[derive(IntuicioStruct)]
+#[intuicio(name = "Iterator", module_name = "iter")]
+pub struct AnkhaIterator {
+    #[intuicio(ignore)]
+    inner: Box<dyn Iterator<Item = AnkhaOption>>,
+}
+
+impl Default for AnkhaIterator {
+    fn default() -> Self {
+        Self::new(std::iter::empty())
+    }
+}
+
+impl AnkhaIterator {
+    pub fn new(iter: impl Iterator<Item = AnkhaOption> + 'static) -> Self {
+        Self {
+            inner: Box::new(iter),
+        }
+    }
+
+    // Yields integers of bounds type, bounds must have same integer type.
+    pub fn range(from: AnkhaOption, to: AnkhaOption, inclusive: bool) -> Self {
+        let type_hash = from
+            .type_hash()
+            .filter(|type_hash| is_integer(*type_hash))
+            .expect("Range bounds must be integers!");
+        if to.type_hash() != Some(type_hash) {
+            panic!("Range bounds must have same type!");
+        }
+        // `u128` is the only integer type that does not fit in `i128`.
+        if type_hash == TypeHash::of::<u128>() {
+            let bound = |value: AnkhaOption| match value.read_literal() {
+                Some(AnkhaLiteral::U128(value)) => value,
+                _ => panic!("Range bounds must be integers!"),
+            };
+            let from = bound(from);
+            let to = bound(to);
+            let item = |value: u128| AnkhaOption::from_literal(AnkhaLiteral::U128(value));
+            return if inclusive {
+                Self::new((from..=to).map(item))
+            } else {
+                Self::new((from..to).map(item))
+            };
+        }
+        let bound = |value: AnkhaOption| match value
+            .read_literal()
+            .and_then(|value| value.cast(TypeHash::of::<i128>(), AnkhaCastMode::Checked))
+        {
+            Some(AnkhaLiteral::I128(value)) => value,
+            _ => panic!("Range bounds must be integers!"),
+        };
+        let from = bound(from);
+        let to = bound(to);
+        let item = move |value: i128| {
+            AnkhaOption::from_literal(
+                AnkhaLiteral::I128(value)
+                    .cast(type_hash, AnkhaCastMode::As)
+                    .unwrap(),
+            )
+        };
+        if inclusive {
+            Self::new((from..=to).map(item))
+        } else {
+            Self::new((from..to).map(item))
+        }
+    }
+}
+
+impl Iterator for AnkhaIterator {
+    type Item = AnkhaOption;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+#[intuicio_methods(module_name = "iter")]
+impl AnkhaIterator {
+    #[intuicio_method(name = "next", transformer = "DynamicManagedValueTransformer")]
+    fn script_next(&mut self) -> AnkhaOption {
+        self.inner.next().unwrap_or_default()
+    }
+
+    #[intuicio_method(name = "collect", transformer = "DynamicManagedValueTransformer")]
+    fn script_collect(self) -> Array {
+        self.inner.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        library::{
+            dictionary::Dictionary,
+            tests::{TestHost, i32s, literal, literals},
+        },
+        script::AnkhaError,
+    };
+    use intuicio_core::context::Context;
+
+    #[test]
+    fn test_iterator() {
+        let mut host = TestHost::default();
+        host.install(
+            r#"(file
+                    (mod "test"
+                        (fn "sum"
+                            (out "result" (kind owned))
+                            (body
+                                (make_register "iter" (kind owned))
+                                (lit i32 0)
+                                (lit i32 5)
+                                (lit i32 0)
+                                (call_function (fn "range_in_ex" "iter"))
+                                (lit bool true)
+                                (stack_unwrap_boolean)
+                                (loop
+                                    (borrow_mut)
+                                    (swap)
+                                    (call_method (fn "next" "iter"))
+                                    (borrow)
+                                    (swap)
+                                    (call_function (fn "is_some" "option"))
+                                    (stack_unwrap_boolean)
+                                    (branch
+                                        (success
+                                            (borrow_mut)
+                                            (swap)
+                                            (call_function (fn "take" "option"))
+                                            (swap)
+                                            (stack_drop)
+                                            (swap)
+                                            (pop_to_register 0)
+                                            (call_method (fn "math::add"))
+                                            (push_from_register 0)
+                                        )
+                                        (failure
+                                            (stack_drop)
+                                            (stack_drop)
+                                            (break)
+                                        )
+                                    )
+                                    (lit bool true)
+                                    (stack_unwrap_boolean)
+                                )
+                            )
+                        )
+                    )
+                )"#,
+        );
+        host.invoke("sum").unwrap();
+        let result = host.context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 10);
+        assert_eq!(host.context.stack().position(), 0);
+
+        let array = Array::from(vec![literal(1), AnkhaOption::None, literal(2)]);
+        let items = array.script_iter().collect::<Vec<_>>();
+        assert!(items.iter().all(|item| item.is_ref()));
+        assert_eq!(literals(&items), i32s(&[1, 2]));
+        drop(items);
+        assert_eq!(literals(array.script_into_iter()), i32s(&[1, 2]));
+
+        let mut dictionary = Dictionary::default();
+        dictionary.insert(
+            AnkhaOption::from_literal(AnkhaLiteral::String("a".to_owned())),
+            literal(42),
+        );
+        assert_eq!(
+            literals(dictionary.iter_keys()),
+            vec![AnkhaLiteral::String("a".to_owned())]
+        );
+        assert_eq!(literals(dictionary.iter_values()), i32s(&[42]));
+    }
+
+    #[test]
+    fn test_iterator_ranges() {
+        let range = |from: AnkhaLiteral, to: AnkhaLiteral, inclusive: bool| {
+            literals(AnkhaIterator::range(
+                AnkhaOption::from_literal(from),
+                AnkhaOption::from_literal(to),
+                inclusive,
+            ))
+        };
+
+        // Empty and reversed ranges yield nothing, inclusive ones yield bound.
+        let three = AnkhaLiteral::I32(3);
+        assert!(range(three.clone(), three.clone(), false).is_empty());
+        assert_eq!(range(three.clone(), three.clone(), true), i32s(&[3]));
+        assert!(range(AnkhaLiteral::I32(5), AnkhaLiteral::I32(2), false).is_empty());
+        assert!(range(AnkhaLiteral::I32(5), AnkhaLiteral::I32(2), true).is_empty());
+
+        // Bounds at type limits do not overflow past them.
+        assert_eq!(
+            range(AnkhaLiteral::U8(253), AnkhaLiteral::U8(255), true),
+            vec![
+                AnkhaLiteral::U8(253),
+                AnkhaLiteral::U8(254),
+                AnkhaLiteral::U8(255),
+            ]
+        );
+        assert_eq!(
+            range(AnkhaLiteral::I8(i8::MAX), AnkhaLiteral::I8(i8::MAX), true),
+            vec![AnkhaLiteral::I8(i8::MAX)]
+        );
+        assert_eq!(
+            range(
+                AnkhaLiteral::I128(i128::MIN),
+                AnkhaLiteral::I128(i128::MIN + 1),
+                false
+            ),
+            vec![AnkhaLiteral::I128(i128::MIN)]
+        );
+        assert_eq!(
+            range(
+                AnkhaLiteral::U128(u128::MAX - 2),
+                AnkhaLiteral::U128(u128::MAX),
+                false,
+            ),
+            vec![
+                AnkhaLiteral::U128(u128::MAX - 2),
+                AnkhaLiteral::U128(u128::MAX - 1),
+            ]
+        );
+        assert_eq!(
+            range(
+                AnkhaLiteral::U128(u128::MAX),
+                AnkhaLiteral::U128(u128::MAX),
+                true
+            ),
+            vec![AnkhaLiteral::U128(u128::MAX)]
+        );
+
+        let error = AnkhaError::catch(&mut Context::new(1024, 1024), |_| {
+            AnkhaIterator::range(
+                literal(0),
+                AnkhaOption::from_literal(AnkhaLiteral::U8(1)),
+                false,
+            );
+        })
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            AnkhaError::Panic { ref message, .. } if message == "Range bounds must have same type!"
+        ));
+    }
+}
@@ -0,0 +1,280 @@
+use crate::script::stack_managed_variant;
+use intuicio_core::{
+    context::Context,
+    function::{Function, FunctionBody, FunctionParameter, FunctionSignature},
+    registry::Registry,
+    types::{TypeHandle, TypeQuery},
+};
+use intuicio_data::managed::DynamicManaged;
+
+// Math functions are associated with type of their first input, so scripts
+// address them with `call_method` on any primitive value. Their names carry
+// module prefix, like `math::add`, so queries by plain name such as `add` keep
+// finding host functions.
+pub const MODULE_NAME: &str = "math";
+
+macro_rules! install_integer {
+    ($registry:expr, $type:ty) => {{
+        let registry: &mut Registry = $registry;
+        binary::<$type, $type, $type>(registry, "add", |a, b| {
+            a.checked_add(b)
+                .unwrap_or_else(|| panic!("Overflow in `add` of `{}`!", stringify!($type)))
+        });
+        binary::<$type, $type, $type>(registry, "sub", |a, b| {
+            a.checked_sub(b)
+                .unwrap_or_else(|| panic!("Overflow in `sub` of `{}`!", stringify!($type)))
+        });
+        binary::<$type, $type, $type>(registry, "mul", |a, b| {
+            a.checked_mul(b)
+                .unwrap_or_else(|| panic!("Overflow in `mul` of `{}`!", stringify!($type)))
+        });
+        binary::<$type, $type, $type>(registry, "div", |a, b| {
+            a.checked_div(b).unwrap_or_else(|| {
+                panic!(
+                    "Division by zero or overflow in `div` of `{}`!",
+                    stringify!($type)
+                )
+            })
+        });
+        binary::<$type, $type, $type>(registry, "rem", |a, b| {
+            a.checked_rem(b).unwrap_or_else(|| {
+                panic!(
+                    "Division by zero or overflow in `rem` of `{}`!",
+                    stringify!($type)
+                )
+            })
+        });
+        binary::<$type, $type, $type>(registry, "and", |a, b| a & b);
+        binary::<$type, $type, $type>(registry, "or", |a, b| a | b);
+        binary::<$type, $type, $type>(registry, "xor", |a, b| a ^ b);
+        unary::<$type, $type>(registry, "not", |a| !a);
+        binary::<$type, u32, $type>(registry, "shl", |a, b| {
+            a.checked_shl(b)
+                .unwrap_or_else(|| panic!("Overflow in `shl` of `{}`!", stringify!($type)))
+        });
+        binary::<$type, u32, $type>(registry, "shr", |a, b| {
+            a.checked_shr(b)
+                .unwrap_or_else(|| panic!("Overflow in `shr` of `{}`!", stringify!($type)))
+        });
+        install_ordered!(registry, $type);
+    }};
+}
+
+macro_rules! install_signed {
+    ($registry:expr, $type:ty) => {{
+        let registry: &mut Registry = $registry;
+        install_integer!(registry, $type);
+        unary::<$type, $type>(registry, "neg", |a| {
+            a.checked_neg()
+                .unwrap_or_else(|| panic!("Overflow in `neg` of `{}`!", stringify!($type)))
+        });
+        unary::<$type, $type>(registry, "abs", |a| {
+            a.checked_abs()
+                .unwrap_or_else(|| panic!("Overflow in `abs` of `{}`!", stringify!($type)))
+        });
+    }};
+}
+
+macro_rules! install_float {
+    ($registry:expr, $type:ty) => {{
+        let registry: &mut Registry = $registry;
+        binary::<$type, $type, $type>(registry, "add", |a, b| a + b);
+        binary::<$type, $type, $type>(registry, "sub", |a, b| a - b);
+        binary::<$type, $type, $type>(registry, "mul", |a, b| a * b);
+        binary::<$type, $type, $type>(registry, "div", |a, b| a / b);
+        binary::<$type, $type, $type>(registry, "rem", |a, b| a % b);
+        unary::<$type, $type>(registry, "neg", |a| -a);
+        unary::<$type, $type>(registry, "abs", |a| a.abs());
+        install_comparison!(registry, $type);
+        binary::<$type, $type, $type>(registry, "min", |a, b| a.min(b));
+        binary::<$type, $type, $type>(registry, "max", |a, b| a.max(b));
+        ternary::<$type, $type>(registry, "clamp", |value, min, max| {
+            if min.is_nan() || max.is_nan() {
+                panic!("NaN bound in `clamp` of `{}`!", stringify!($type));
+            }
+            if min > max {
+                panic!("Invalid `clamp` range of `{}`!", stringify!($type));
+            }
+            value.clamp(min, max)
+        });
+    }};
+}
+
+macro_rules! install_ordered {
+    ($registry:expr, $type:ty) => {{
+        let registry: &mut Registry = $registry;
+        install_comparison!(registry, $type);
+        binary::<$type, $type, $type>(registry, "min", |a, b| a.min(b));
+        binary::<$type, $type, $type>(registry, "max", |a, b| a.max(b));
+        ternary::<$type, $type>(registry, "clamp", |value, min, max| {
+            if min > max {
+                panic!("Invalid `clamp` range of `{}`!", stringify!($type));
+            }
+            value.clamp(min, max)
+        });
+    }};
+}
+
+macro_rules! install_comparison {
+    ($registry:expr, $type:ty) => {{
+        let registry: &mut Registry = $registry;
+        binary::<$type, $type, bool>(registry, "eq", |a, b| a == b);
+        binary::<$type, $type, bool>(registry, "ne", |a, b| a != b);
+        binary::<$type, $type, bool>(registry, "lt", |a, b| a < b);
+        binary::<$type, $type, bool>(registry, "le", |a, b| a <= b);
+        binary::<$type, $type, bool>(registry, "gt", |a, b| a > b);
+        binary::<$type, $type, bool>(registry, "ge", |a, b| a >= b);
+    }};
+}
+
+pub fn install(registry: &mut Registry) {
+    binary::<(), (), bool>(registry, "eq", |_, _| true);
+    binary::<(), (), bool>(registry, "ne", |_, _| false);
+    binary::<bool, bool, bool>(registry, "and", |a, b| a && b);
+    binary::<bool, bool, bool>(registry, "or", |a, b| a || b);
+    binary::<bool, bool, bool>(registry, "xor", |a, b| a ^ b);
+    unary::<bool, bool>(registry, "not", |a| !a);
+    install_ordered!(registry, bool);
+    install_signed!(registry, i8);
+    install_signed!(registry, i16);
+    install_signed!(registry, i32);
+    install_signed!(registry, i64);
+    install_signed!(registry, i128);
+    install_signed!(registry, isize);
+    install_integer!(registry, u8);
+    install_integer!(registry, u16);
+    install_integer!(registry, u32);
+    install_integer!(registry, u64);
+    install_integer!(registry, u128);
+    install_integer!(registry, usize);
+    install_float!(registry, f32);
+    install_float!(registry, f64);
+    install_ordered!(registry, char);
+    binary::<String, String, String>(registry, "add", |a, b| a + &b);
+    install_ordered!(registry, String);
+}
+
+fn unary<T: Clone + 'static, R: 'static>(registry: &mut Registry, name: &str, f: fn(T) -> R) {
+    let signature = signature::<T>(registry, name, &["a"]);
+    let name = name.to_owned();
+    registry.add_function(Function::new(
+        signature,
+        FunctionBody::closure(move |context, _| {
+            let a = pop_value::<T>(context, &name);
+            context
+                .stack()
+                .push(DynamicManaged::new(f(a)).ok().unwrap());
+        }),
+    ));
+}
+
+fn binary<T: Clone + 'static, U: Clone + 'static, R: 'static>(
+    registry: &mut Registry,
+    name: &str,
+    f: fn(T, U) -> R,
+) {
+    let signature = signature::<T>(registry, name, &["a", "b"]);
+    let name = name.to_owned();
+    registry.add_function(Function::new(
+        signature,
+        FunctionBody::closure(move |context, _| {
+            let a = pop_value::<T>(context, &name);
+            let b = pop_value::<U>(context, &name);
+            context
+                .stack()
+                .push(DynamicManaged::new(f(a, b)).ok().unwrap());
+        }),
+    ));
+}
+
+fn ternary<T: Clone + 'static, R: 'static>(
+    registry: &mut Registry,
+    name: &str,
+    f: fn(T, T, T) -> R,
+) {
+    let signature = signature::<T>(registry, name, &["value", "min", "max"]);
+    let name = name.to_owned();
+    registry.add_function(Function::new(
+        signature,
+        FunctionBody::closure(move |context, _| {
+            let value = pop_value::<T>(context, &name);
+            let min = pop_value::<T>(context, &name);
+            let max = pop_value::<T>(context, &name);
+            context
+                .stack()
+                .push(DynamicManaged::new(f(value, min, max)).ok().unwrap());
+        }),
+    ));
+}
+
+fn signature<T: 'static>(
+    registry: &mut Registry,
+    name: &str,
+    inputs: &[&str],
+) -> FunctionSignature {
+    let type_handle = primitive_type::<T>(registry);
+    let managed_handle = registry
+        .find_type(TypeQuery::of::<DynamicManaged>())
+        .expect("Could not find `DynamicManaged` type!");
+    let mut result = FunctionSignature::new(format!("{}::{}", MODULE_NAME, name))
+        .with_module_name(MODULE_NAME)
+        .with_type_handle(type_handle)
+        .with_output(FunctionParameter::new("result", managed_handle.clone()));
+    for input in inputs {
+        result = result.with_input(FunctionParameter::new(input, managed_handle.clone()));
+    }
+    result
+}
+
+fn primitive_type<T: 'static>(registry: &Registry) -> TypeHandle {
+    registry.find_type(TypeQuery::of::<T>()).unwrap_or_else(|| {
+        panic!(
+            "Math library requires `{}` type to be registered!",
+            std::any::type_name::<T>()
+        )
+    })
+}
+
+fn pop_value<T: Clone + 'static>(context: &mut Context, function: &str) -> T {
+    stack_managed_variant(
+        context,
+        |_, value| {
+            value
+                .consume::<T>()
+                .ok()
+                .unwrap_or_else(|| invalid_input::<T>(function))
+        },
+        |_, value| {
+            value
+                .read::<T>()
+                .map(|value| value.clone())
+                .unwrap_or_else(|| invalid_input::<T>(function))
+        },
+        |_, value| {
+            value
+                .read::<T>()
+                .map(|value| value.clone())
+                .unwrap_or_else(|| invalid_input::<T>(function))
+        },
+        |_, value| {
+            value
+                .read::<T>()
+                .map(|value| value.clone())
+                .unwrap_or_else(|| invalid_input::<T>(function))
+        },
+        |_, value| {
+            value
+                .read::<T>()
+                .map(|value| value.clone())
+                .unwrap_or_else(|| invalid_input::<T>(function))
+        },
+    )
+}
+
+fn invalid_input<T>(function: &str) -> ! {
+    panic!(
+        "Math `{}` function expects value of `{}` type!",
+        function,
+        std::any::type_name::<T>()
+    )
+}
@@ -3,9 +3,18 @@ pub mod channel;
 pub mod closure;
 pub mod dictionary;
 pub mod event;
+pub mod format;
+pub mod io;
+pub mod iter;
+pub mod math;
 pub mod option;
+pub mod ordered_dictionary;
 pub mod promise;
+pub mod queue;
 pub mod reflection;
+pub mod set;
+pub mod sorted_dictionary;
+pub mod string;
 pub mod thread;
 
 use crate::script::AnkhaExpression;
@@ -32,14 +41,23 @@ pub fn install(registry: &mut Registry) {
     registry.add_type(NativeStructBuilder::new_uninitialized::<DynamicManagedLazy>().build());
     registry.add_type(NativeStructBuilder::new_uninitialized::<DynamicManagedBox>().build());
     crate::library::option::install(registry);
+    crate::library::iter::install(registry);
     crate::library::array::install(registry);
     crate::library::dictionary::install(registry);
+    crate::library::ordered_dictionary::install(registry);
+    crate::library::sorted_dictionary::install(registry);
+    crate::library::set::install(registry);
+    crate::library::queue::install(registry);
     crate::library::reflection::install(registry);
     crate::library::channel::install(registry);
     crate::library::event::install(registry);
     crate::library::closure::install(registry);
     crate::library::promise::install(registry);
     crate::library::thread::install(registry);
+    crate::library::math::install(registry);
+    crate::library::string::install(registry);
+    crate::library::format::install(registry);
+    crate::library::io::install(registry);
 }
 
 pub fn printables(debugger: PrintDebugger) -> PrintDebugger {
@@ -145,3 +163,90 @@ impl<'a> AnkhaManagedStack<'a> {
         self.stack.pop::<DynamicManagedBox>()?.into_typed().ok()
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::{
+        library::{AnkhaVmScope, option::AnkhaOption, reflection},
+        parser::AnkhaContentParser,
+        script::{AnkhaError, AnkhaLiteral, AnkhaPackage},
+    };
+    use intuicio_core::prelude::*;
+    use std::borrow::Borrow;
+
+    // Registry with all libraries and context to run them, shared by library
+    // tests. Scripts are installed from content with functions in `test` module.
+    pub struct TestHost {
+        pub registry: Registry,
+        pub context: Context,
+    }
+
+    impl Default for TestHost {
+        fn default() -> Self {
+            let mut registry = Registry::default().with_basic_types();
+            crate::library::install(&mut registry);
+            Self {
+                registry,
+                context: Context::new(10240, 10240),
+            }
+        }
+    }
+
+    impl TestHost {
+        pub fn install(&mut self, content: &str) {
+            let file = AnkhaContentParser::default()
+                .parse_file_content(content)
+                .unwrap();
+            let package = AnkhaPackage {
+                files: [("main".to_owned(), file)].into_iter().collect(),
+                ..Default::default()
+            };
+            package.install::<AnkhaVmScope>(&mut self.context, &mut self.registry, None);
+        }
+
+        pub fn function(&self, name: &str) -> FunctionHandle {
+            self.registry
+                .find_function(FunctionQuery {
+                    name: Some(name.to_owned().into()),
+                    module_name: Some("test".into()),
+                    ..Default::default()
+                })
+                .unwrap()
+        }
+
+        pub fn invoke(&mut self, name: &str) -> Result<(), AnkhaError> {
+            let function = self.function(name);
+            AnkhaError::invoke(&function, &mut self.context, &self.registry)
+        }
+    }
+
+    // Math function of `i32`, to be wrapped in closures.
+    pub fn math_function(registry: &Registry, name: &str) -> reflection::Function {
+        reflection::Function(
+            registry
+                .find_function(FunctionQuery {
+                    name: Some(format!("math::{}", name).into()),
+                    type_query: Some(TypeQuery::of::<i32>()),
+                    ..Default::default()
+                })
+                .unwrap(),
+        )
+    }
+
+    pub fn i32s(values: &[i32]) -> Vec<AnkhaLiteral> {
+        values.iter().copied().map(AnkhaLiteral::I32).collect()
+    }
+
+    pub fn literal(value: i32) -> AnkhaOption {
+        AnkhaOption::from_literal(AnkhaLiteral::I32(value))
+    }
+
+    pub fn literals<T: Borrow<AnkhaOption>>(
+        items: impl IntoIterator<Item = T>,
+    ) -> Vec<AnkhaLiteral> {
+        items
+            .into_iter()
+            .map(|item| item.borrow().read_literal().unwrap())
+            .collect()
+    }
+}
@@ -12,6 +12,46 @@ use intuicio_data::{
 };
 use intuicio_derive::{IntuicioEnum, intuicio_method, intuicio_methods};
 
+// Reads primitive value of any kind into literal without consuming it.
+fn read_literal(value: &DynamicManagedRef) -> Option<AnkhaLiteral> {
+    macro_rules! read {
+        ($($type:ty => $variant:ident),+) => {{
+            let type_hash = *value.type_hash();
+            if type_hash == TypeHash::of::<()>() {
+                return Some(AnkhaLiteral::Unit);
+            }
+            $(
+                if type_hash == TypeHash::of::<$type>() {
+                    return value
+                        .read::<$type>()
+                        .map(|value| AnkhaLiteral::$variant(value.to_owned()));
+                }
+            )+
+            None
+        }};
+    }
+
+    read!(
+        bool => Bool,
+        i8 => I8,
+        i16 => I16,
+        i32 => I32,
+        i64 => I64,
+        i128 => I128,
+        isize => Isize,
+        u8 => U8,
+        u16 => U16,
+        u32 => U32,
+        u64 => U64,
+        u128 => U128,
+        usize => Usize,
+        f32 => F32,
+        f64 => F64,
+        char => Char,
+        String => String
+    )
+}
+
 pub fn install(registry: &mut Registry) {
     registry.add_type(AnkhaOption::define_enum(registry));
     registry.add_function(AnkhaOption::script_wrap__define_function(registry));
@@ -297,6 +337,22 @@ impl AnkhaOption {
             None
         }
     }
+
+    // Pops stack top value of any kind.
+    pub fn pop(context: &mut Context) -> Self {
+        stack_managed_variant(
+            context,
+            |_, value| value.into(),
+            |_, value| value.into(),
+            |_, value| value.into(),
+            |_, value| value.into(),
+            |_, value| value.into(),
+        )
+    }
+
+    pub fn read_literal(&self) -> Option<AnkhaLiteral> {
+        read_literal(&self.borrow()?)
+    }
 }
 
 #[intuicio_methods(module_name = "option")]
@@ -659,6 +715,10 @@ impl AnkhaAsyncOption {
             None
         }
     }
+
+    pub fn read_literal(&self) -> Option<AnkhaLiteral> {
+        read_literal(&self.borrow()?)
+    }
 }
 
 #[intuicio_methods(module_name = "option")]
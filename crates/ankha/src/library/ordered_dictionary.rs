@@ -0,0 +1,223 @@
+use crate::{
+    library::{
+        array::{Array, AsyncArray},
+        closure::{AsyncClosure, Closure},
+        dictionary::literal_dictionary,
+        iter::AnkhaIterator,
+        option::{AnkhaAsyncOption, AnkhaOption},
+    },
+    script::AnkhaLiteral,
+};
+use intuicio_core::{
+    IntuicioStruct,
+    context::Context,
+    registry::Registry,
+    transformer::{DynamicManagedValueTransformer, ValueTransformer},
+};
+use intuicio_data::managed::DynamicManaged;
+use intuicio_derive::{IntuicioStruct, intuicio_method, intuicio_methods};
+use std::{collections::HashMap, hash::Hash};
+
+pub fn install(registry: &mut Registry) {
+    OrderedDictionary::install(registry);
+    OrderedDictionary::install_iterators(registry);
+    AsyncOrderedDictionary::install(registry);
+}
+
+// Hash map that keeps insertion order of its entries. Replacing value keeps
+// entry in place, removing entry shifts following ones.
+pub struct OrderedMap<K, V> {
+    items: Vec<(K, V)>,
+    indices: HashMap<K, usize>,
+}
+
+impl<K, V> Default for OrderedMap<K, V> {
+    fn default() -> Self {
+        Self {
+            items: Default::default(),
+            indices: Default::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> OrderedMap<K, V> {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    pub fn index_of(&self, key: &K) -> Option<usize> {
+        self.indices.get(key).copied()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.index_of(key).map(|index| &self.items[index].1)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.index_of(key).map(|index| &mut self.items[index].1)
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        self.items.get(index).map(|(key, value)| (key, value))
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(index) = self.index_of(&key) {
+            Some(std::mem::replace(&mut self.items[index].1, value))
+        } else {
+            self.indices.insert(key.clone(), self.items.len());
+            self.items.push((key, value));
+            None
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let index = self.indices.remove(key)?;
+        let (_, value) = self.items.remove(index);
+        for (key, _) in &self.items[index..] {
+            if let Some(index) = self.indices.get_mut(key) {
+                *index -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.indices.clear();
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        self.items.retain_mut(|(key, value)| f(key, value));
+        self.reindex();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.items.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.items.iter_mut().map(|(key, value)| (&*key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.items.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.items.iter().map(|(_, value)| value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.items.iter_mut().map(|(_, value)| value)
+    }
+
+    fn reindex(&mut self) {
+        self.indices = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, (key, _))| (key.clone(), index))
+            .collect();
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Extend<(K, V)> for OrderedMap<K, V> {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> FromIterator<(K, V)> for OrderedMap<K, V> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut result = Self::default();
+        result.extend(iter);
+        result
+    }
+}
+
+impl<K, V> IntoIterator for OrderedMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+literal_dictionary!(
+    OrderedDictionary as "OrderedDictionary" in "ordered_dictionary",
+    OrderedMap<AnkhaLiteral, AnkhaOption>,
+    Array,
+    Closure
+);
+literal_dictionary!(OrderedDictionary in "ordered_dictionary", iterators);
+literal_dictionary!(
+    AsyncOrderedDictionary as "AsyncOrderedDictionary" in "ordered_dictionary",
+    OrderedMap<AnkhaLiteral, AnkhaAsyncOption>,
+    AsyncArray,
+    AsyncClosure
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ordered_dictionary() {
+        let literal = |value: AnkhaLiteral| AnkhaOption::from_literal(value);
+        let keys = |array: Array| {
+            array
+                .iter()
+                .map(|item| item.read_literal().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let mut items = OrderedDictionary::default();
+        for key in ["c", "a", "b"] {
+            items.insert(
+                literal(AnkhaLiteral::String(key.to_owned())),
+                literal(AnkhaLiteral::I32(key.len() as i32)),
+            );
+        }
+        items.insert(
+            literal(AnkhaLiteral::String("a".to_owned())),
+            literal(AnkhaLiteral::I32(42)),
+        );
+        items.remove(literal(AnkhaLiteral::String("c".to_owned())));
+        items.insert(
+            literal(AnkhaLiteral::String("c".to_owned())),
+            literal(AnkhaLiteral::I32(3)),
+        );
+        assert_eq!(
+            keys(items.script_keys()),
+            vec![
+                AnkhaLiteral::String("a".to_owned()),
+                AnkhaLiteral::String("b".to_owned()),
+                AnkhaLiteral::String("c".to_owned()),
+            ]
+        );
+        assert_eq!(
+            items
+                .get(literal(AnkhaLiteral::String("a".to_owned())))
+                .read_literal(),
+            Some(AnkhaLiteral::I32(42))
+        );
+        assert_eq!(
+            items
+                .get(literal(AnkhaLiteral::String("b".to_owned())))
+                .read_literal(),
+            Some(AnkhaLiteral::I32(1))
+        );
+    }
+}
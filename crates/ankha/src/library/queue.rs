@@ -0,0 +1,681 @@
+use crate::library::{closure::Closure, iter::AnkhaIterator, option::AnkhaOption};
+use intuicio_core::{
+    IntuicioStruct,
+    context::Context,
+    registry::Registry,
+    transformer::{DynamicManagedValueTransformer, ValueTransformer},
+};
+use intuicio_derive::{IntuicioStruct, intuicio_method, intuicio_methods};
+use std::collections::VecDeque;
+
+pub fn install(registry: &mut Registry) {
+    registry.add_type(Deque::define_struct(registry));
+    registry.add_function(Deque::with_capacity__define_function(registry));
+    registry.add_function(Deque::reserve__define_function(registry));
+    registry.add_function(Deque::is_empty__define_function(registry));
+    registry.add_function(Deque::size__define_function(registry));
+    registry.add_function(Deque::exists__define_function(registry));
+    registry.add_function(Deque::is_valid__define_function(registry));
+    registry.add_function(Deque::get__define_function(registry));
+    registry.add_function(Deque::get_mut__define_function(registry));
+    registry.add_function(Deque::get_lazy__define_function(registry));
+    registry.add_function(Deque::front__define_function(registry));
+    registry.add_function(Deque::back__define_function(registry));
+    registry.add_function(Deque::push_front__define_function(registry));
+    registry.add_function(Deque::push_back__define_function(registry));
+    registry.add_function(Deque::pop_front__define_function(registry));
+    registry.add_function(Deque::pop_back__define_function(registry));
+    registry.add_function(Deque::insert__define_function(registry));
+    registry.add_function(Deque::remove__define_function(registry));
+    registry.add_function(Deque::clear__define_function(registry));
+    registry.add_function(Deque::swap__define_function(registry));
+    registry.add_function(Deque::script_iter__define_function(registry));
+    registry.add_type(RingBuffer::define_struct(registry));
+    registry.add_function(RingBuffer::with_capacity__define_function(registry));
+    registry.add_function(RingBuffer::capacity__define_function(registry));
+    registry.add_function(RingBuffer::is_empty__define_function(registry));
+    registry.add_function(RingBuffer::is_full__define_function(registry));
+    registry.add_function(RingBuffer::size__define_function(registry));
+    registry.add_function(RingBuffer::exists__define_function(registry));
+    registry.add_function(RingBuffer::is_valid__define_function(registry));
+    registry.add_function(RingBuffer::get__define_function(registry));
+    registry.add_function(RingBuffer::get_mut__define_function(registry));
+    registry.add_function(RingBuffer::get_lazy__define_function(registry));
+    registry.add_function(RingBuffer::front__define_function(registry));
+    registry.add_function(RingBuffer::back__define_function(registry));
+    registry.add_function(RingBuffer::push__define_function(registry));
+    registry.add_function(RingBuffer::pop_front__define_function(registry));
+    registry.add_function(RingBuffer::pop_back__define_function(registry));
+    registry.add_function(RingBuffer::clear__define_function(registry));
+    registry.add_function(RingBuffer::script_iter__define_function(registry));
+    registry.add_type(PriorityQueue::define_struct(registry));
+    registry.add_function(PriorityQueue::new__define_function(registry));
+    registry.add_function(PriorityQueue::is_empty__define_function(registry));
+    registry.add_function(PriorityQueue::size__define_function(registry));
+    registry.add_function(PriorityQueue::peek__define_function(registry));
+    registry.add_function(PriorityQueue::push__define_function(registry));
+    registry.add_function(PriorityQueue::pop__define_function(registry));
+    registry.add_function(PriorityQueue::clear__define_function(registry));
+}
+
+#[derive(IntuicioStruct, Default)]
+#[intuicio(name = "Deque", module_name = "queue")]
+pub struct Deque {
+    #[intuicio(ignore)]
+    items: VecDeque<AnkhaOption>,
+}
+
+impl Deque {
+    pub fn inner(&self) -> &VecDeque<AnkhaOption> {
+        &self.items
+    }
+
+    pub fn inner_mut(&mut self) -> &mut VecDeque<AnkhaOption> {
+        &mut self.items
+    }
+
+    pub fn into_inner(self) -> VecDeque<AnkhaOption> {
+        self.items
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &AnkhaOption> {
+        self.items.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut AnkhaOption> {
+        self.items.iter_mut()
+    }
+}
+
+impl IntoIterator for Deque {
+    type Item = AnkhaOption;
+    type IntoIter = std::collections::vec_deque::IntoIter<AnkhaOption>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+#[intuicio_methods(module_name = "queue")]
+impl Deque {
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            items: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn reserve(&mut self, additional: usize) {
+        self.items.reserve_exact(additional);
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn size(&self) -> usize {
+        self.items.len()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn exists(&self, index: usize) -> bool {
+        index < self.size()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_valid(&self, index: usize) -> bool {
+        self.items
+            .get(index)
+            .map(|item| item.is_some())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn get(&self, index: usize) -> AnkhaOption {
+        self.items
+            .get(index)
+            .and_then(|item| item.borrow())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn get_mut(&mut self, index: usize) -> AnkhaOption {
+        self.items
+            .get_mut(index)
+            .and_then(|item| item.borrow_mut())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn get_lazy(&mut self, index: usize) -> AnkhaOption {
+        self.items
+            .get_mut(index)
+            .and_then(|item| item.lazy())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn front(&self) -> AnkhaOption {
+        self.items
+            .front()
+            .and_then(|item| item.borrow())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn back(&self) -> AnkhaOption {
+        self.items
+            .back()
+            .and_then(|item| item.borrow())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn push_front(&mut self, value: AnkhaOption) {
+        self.items.push_front(value);
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn push_back(&mut self, value: AnkhaOption) {
+        self.items.push_back(value);
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn pop_front(&mut self) -> AnkhaOption {
+        self.items.pop_front().unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn pop_back(&mut self) -> AnkhaOption {
+        self.items.pop_back().unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn insert(&mut self, index: usize, value: AnkhaOption) {
+        self.items.insert(index, value);
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn remove(&mut self, index: usize) -> AnkhaOption {
+        self.items.remove(index).unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn swap(&mut self, from: usize, to: usize) {
+        if from < self.size() && to < self.size() {
+            self.items.swap(from, to);
+        }
+    }
+
+    #[intuicio_method(name = "iter", transformer = "DynamicManagedValueTransformer")]
+    pub fn script_iter(&self) -> AnkhaIterator {
+        let items = self
+            .items
+            .iter()
+            .filter_map(|item| item.borrow())
+            .map(|item| item.into())
+            .collect::<Vec<AnkhaOption>>();
+        AnkhaIterator::new(items.into_iter())
+    }
+}
+
+impl FromIterator<AnkhaOption> for Deque {
+    fn from_iter<T: IntoIterator<Item = AnkhaOption>>(iter: T) -> Self {
+        Self {
+            items: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl From<VecDeque<AnkhaOption>> for Deque {
+    fn from(value: VecDeque<AnkhaOption>) -> Self {
+        Self { items: value }
+    }
+}
+
+// Pushing into full buffer drops its oldest item.
+#[derive(IntuicioStruct, Default)]
+#[intuicio(name = "RingBuffer", module_name = "queue")]
+pub struct RingBuffer {
+    #[intuicio(ignore)]
+    items: VecDeque<AnkhaOption>,
+    #[intuicio(ignore)]
+    capacity: usize,
+}
+
+impl RingBuffer {
+    pub fn inner(&self) -> &VecDeque<AnkhaOption> {
+        &self.items
+    }
+
+    pub fn into_inner(self) -> VecDeque<AnkhaOption> {
+        self.items
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &AnkhaOption> {
+        self.items.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut AnkhaOption> {
+        self.items.iter_mut()
+    }
+}
+
+impl IntoIterator for RingBuffer {
+    type Item = AnkhaOption;
+    type IntoIter = std::collections::vec_deque::IntoIter<AnkhaOption>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+#[intuicio_methods(module_name = "queue")]
+impl RingBuffer {
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            items: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_full(&self) -> bool {
+        self.items.len() >= self.capacity
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn size(&self) -> usize {
+        self.items.len()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn exists(&self, index: usize) -> bool {
+        index < self.size()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_valid(&self, index: usize) -> bool {
+        self.items
+            .get(index)
+            .map(|item| item.is_some())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn get(&self, index: usize) -> AnkhaOption {
+        self.items
+            .get(index)
+            .and_then(|item| item.borrow())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn get_mut(&mut self, index: usize) -> AnkhaOption {
+        self.items
+            .get_mut(index)
+            .and_then(|item| item.borrow_mut())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn get_lazy(&mut self, index: usize) -> AnkhaOption {
+        self.items
+            .get_mut(index)
+            .and_then(|item| item.lazy())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn front(&self) -> AnkhaOption {
+        self.items
+            .front()
+            .and_then(|item| item.borrow())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn back(&self) -> AnkhaOption {
+        self.items
+            .back()
+            .and_then(|item| item.borrow())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    // Returns item dropped out of buffer, buffer without capacity drops
+    // pushed value right away.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn push(&mut self, value: AnkhaOption) -> AnkhaOption {
+        if self.capacity == 0 {
+            return value;
+        }
+        let result = if self.is_full() {
+            self.items.pop_front().unwrap_or_default()
+        } else {
+            AnkhaOption::None
+        };
+        self.items.push_back(value);
+        result
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn pop_front(&mut self) -> AnkhaOption {
+        self.items.pop_front().unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn pop_back(&mut self) -> AnkhaOption {
+        self.items.pop_back().unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    #[intuicio_method(name = "iter", transformer = "DynamicManagedValueTransformer")]
+    pub fn script_iter(&self) -> AnkhaIterator {
+        let items = self
+            .items
+            .iter()
+            .filter_map(|item| item.borrow())
+            .map(|item| item.into())
+            .collect::<Vec<AnkhaOption>>();
+        AnkhaIterator::new(items.into_iter())
+    }
+}
+
+// Binary heap, comparator closure tells if first item goes before second one,
+// tied items pop in no particular order.
+// Queue has no default comparator, so scripts create it with `new`.
+#[derive(IntuicioStruct)]
+#[intuicio(name = "PriorityQueue", module_name = "queue", uninitialized)]
+pub struct PriorityQueue {
+    #[intuicio(ignore)]
+    items: Vec<AnkhaOption>,
+    #[intuicio(ignore)]
+    comparator: Closure,
+}
+
+impl PriorityQueue {
+    pub fn comparator(&self) -> &Closure {
+        &self.comparator
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &AnkhaOption> {
+        self.items.iter()
+    }
+
+    fn goes_before(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        a: usize,
+        b: usize,
+    ) -> bool {
+        let a = self.items[a].borrow().unwrap().into();
+        let b = self.items[b].borrow().unwrap().into();
+        self.comparator
+            .predicate(context, registry, vec![a, b], "PriorityQueue")
+    }
+
+    fn sift_up(&mut self, context: &mut Context, registry: &Registry, mut index: usize) {
+        while index > 0 {
+            let parent = (index - 1) / 2;
+            if !self.goes_before(context, registry, index, parent) {
+                break;
+            }
+            self.items.swap(index, parent);
+            index = parent;
+        }
+    }
+
+    fn sift_down(&mut self, context: &mut Context, registry: &Registry, mut index: usize) {
+        loop {
+            let left = index * 2 + 1;
+            let right = left + 1;
+            let mut first = index;
+            if left < self.items.len() && self.goes_before(context, registry, left, first) {
+                first = left;
+            }
+            if right < self.items.len() && self.goes_before(context, registry, right, first) {
+                first = right;
+            }
+            if first == index {
+                break;
+            }
+            self.items.swap(index, first);
+            index = first;
+        }
+    }
+}
+
+#[intuicio_methods(module_name = "queue")]
+impl PriorityQueue {
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn new(comparator: Closure) -> Self {
+        Self {
+            items: Default::default(),
+            comparator,
+        }
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn size(&self) -> usize {
+        self.items.len()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn peek(&self) -> AnkhaOption {
+        self.items
+            .first()
+            .and_then(|item| item.borrow())
+            .map(|item| item.into())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn push(&mut self, context: &mut Context, registry: &Registry, value: AnkhaOption) {
+        if value.is_none() {
+            panic!("Cannot push none value to priority queue!");
+        }
+        self.items.push(value);
+        self.sift_up(context, registry, self.items.len() - 1);
+    }
+
+    #[intuicio_method(
+        use_context,
+        use_registry,
+        transformer = "DynamicManagedValueTransformer"
+    )]
+    pub fn pop(&mut self, context: &mut Context, registry: &Registry) -> AnkhaOption {
+        if self.items.is_empty() {
+            return AnkhaOption::None;
+        }
+        let result = self.items.swap_remove(0);
+        if !self.items.is_empty() {
+            self.sift_down(context, registry, 0);
+        }
+        result
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        library::{
+            reflection,
+            tests::{TestHost, i32s, literal, literals, math_function},
+        },
+        script::AnkhaLiteral,
+    };
+    use intuicio_core::prelude::*;
+    use intuicio_data::prelude::*;
+
+    #[test]
+    fn test_queues() {
+        let TestHost {
+            registry,
+            mut context,
+        } = TestHost::default();
+
+        let mut deque = Deque::default();
+        deque.push_back(literal(2));
+        deque.push_front(literal(1));
+        deque.push_back(literal(3));
+        assert_eq!(deque.size(), 3);
+        assert_eq!(deque.front().read_literal(), Some(AnkhaLiteral::I32(1)));
+        assert_eq!(deque.back().read_literal(), Some(AnkhaLiteral::I32(3)));
+        assert_eq!(deque.pop_front().read_literal(), Some(AnkhaLiteral::I32(1)));
+        assert_eq!(deque.pop_back().read_literal(), Some(AnkhaLiteral::I32(3)));
+        assert_eq!(deque.get(0).read_literal(), Some(AnkhaLiteral::I32(2)));
+        assert!(deque.pop_back().is_some());
+        assert!(deque.pop_front().is_none());
+
+        let mut ring = RingBuffer::with_capacity(3);
+        for value in 1..=3 {
+            assert!(ring.push(literal(value)).is_none());
+        }
+        assert!(ring.is_full());
+        assert_eq!(
+            ring.push(literal(4)).read_literal(),
+            Some(AnkhaLiteral::I32(1))
+        );
+        assert_eq!(
+            ring.script_iter()
+                .map(|item| item.read_literal().unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                AnkhaLiteral::I32(2),
+                AnkhaLiteral::I32(3),
+                AnkhaLiteral::I32(4),
+            ]
+        );
+        assert_eq!(
+            RingBuffer::default().push(literal(5)).read_literal(),
+            Some(AnkhaLiteral::I32(5))
+        );
+
+        let less = math_function(&registry, "lt");
+        let mut queue = PriorityQueue::new(Closure::new(less, Default::default()));
+        for value in [5, 1, 4, 2, 3, 1] {
+            queue.push(&mut context, &registry, literal(value));
+        }
+        assert_eq!(queue.size(), 6);
+        assert_eq!(queue.peek().read_literal(), Some(AnkhaLiteral::I32(1)));
+        let mut result = vec![];
+        while !queue.is_empty() {
+            result.push(queue.pop(&mut context, &registry).read_literal().unwrap());
+        }
+        assert_eq!(result, i32s(&[1, 1, 2, 3, 4, 5]));
+        assert!(queue.pop(&mut context, &registry).is_none());
+    }
+
+    #[test]
+    fn test_priority_queue_ties() {
+        let TestHost {
+            registry,
+            mut context,
+        } = TestHost::default();
+        // Orders values by their tens only, so values within same ten tie.
+        let by_tens = Function::new(
+            FunctionSignature::new("by_tens")
+                .with_input(FunctionParameter::new(
+                    "a",
+                    registry
+                        .find_type(TypeQuery::of::<DynamicManagedRef>())
+                        .unwrap(),
+                ))
+                .with_input(FunctionParameter::new(
+                    "b",
+                    registry
+                        .find_type(TypeQuery::of::<DynamicManagedRef>())
+                        .unwrap(),
+                ))
+                .with_output(FunctionParameter::new(
+                    "result",
+                    registry
+                        .find_type(TypeQuery::of::<DynamicManaged>())
+                        .unwrap(),
+                )),
+            FunctionBody::closure(|context, _| {
+                let a = context.stack().pop::<DynamicManagedRef>().unwrap();
+                let b = context.stack().pop::<DynamicManagedRef>().unwrap();
+                let result = *a.read::<i32>().unwrap() / 10 < *b.read::<i32>().unwrap() / 10;
+                context
+                    .stack()
+                    .push(DynamicManaged::new(result).ok().unwrap());
+            }),
+        );
+        let mut queue =
+            PriorityQueue::new(Closure::from_function(reflection::Function(by_tens.into())));
+        for value in [21, 10, 25, 12, 20, 11, 10] {
+            queue.push(&mut context, &registry, literal(value));
+        }
+
+        // Tied items pop in no particular order, yet all before next ten.
+        let mut result = vec![];
+        while !queue.is_empty() {
+            result.push(queue.pop(&mut context, &registry));
+        }
+        let mut result = literals(&result)
+            .into_iter()
+            .map(|item| match item {
+                AnkhaLiteral::I32(value) => value,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            result.iter().map(|value| value / 10).collect::<Vec<_>>(),
+            vec![1, 1, 1, 1, 2, 2, 2]
+        );
+        result[..4].sort();
+        result[4..].sort();
+        assert_eq!(result, vec![10, 10, 11, 12, 20, 21, 25]);
+        assert_eq!(context.stack().position(), 0);
+    }
+}
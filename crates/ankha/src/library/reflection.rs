@@ -1,6 +1,9 @@
-use crate::library::option::AnkhaOption;
+use crate::{
+    library::option::AnkhaOption,
+    script::{declared_type, type_handle_query},
+};
 use intuicio_core::{
-    function::{FunctionHandle, FunctionQuery},
+    function::{FunctionHandle, FunctionParameter, FunctionQuery},
     registry::Registry,
     transformer::{DynamicManagedValueTransformer, ValueTransformer},
     types::{TypeHandle, TypeQuery, struct_type::NativeStructBuilder},
@@ -26,6 +29,12 @@ pub fn install(registry: &mut Registry) {
     registry.add_function(Function::by_name__define_function(registry));
     registry.add_function(Function::name__define_function(registry));
     registry.add_function(Function::module_name__define_function(registry));
+    registry.add_function(Function::inputs_count__define_function(registry));
+    registry.add_function(Function::outputs_count__define_function(registry));
+    registry.add_function(Function::input_name__define_function(registry));
+    registry.add_function(Function::output_name__define_function(registry));
+    registry.add_function(Function::input_type__define_function(registry));
+    registry.add_function(Function::output_type__define_function(registry));
 }
 
 #[derive(Debug, Clone)]
@@ -63,10 +72,7 @@ impl Type {
         registry
             .find_function(FunctionQuery {
                 name: Some(name.into()),
-                type_query: Some(TypeQuery {
-                    type_hash: Some(self.0.type_hash()),
-                    ..Default::default()
-                }),
+                type_query: Some(type_handle_query(&self.0)),
                 ..Default::default()
             })
             .map(|value| DynamicManaged::new(Function(value)).ok().unwrap().into())
@@ -115,4 +121,55 @@ impl Function {
             .map(|value| AnkhaOption::Owned(DynamicManaged::new(Type(value.clone())).ok().unwrap()))
             .unwrap_or_default()
     }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn inputs_count(&self) -> usize {
+        self.0.signature().inputs.len()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn outputs_count(&self) -> usize {
+        self.0.signature().outputs.len()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn input_name(&self, index: usize) -> String {
+        Self::parameter(&self.0.signature().inputs, index)
+            .name
+            .to_owned()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn output_name(&self, index: usize) -> String {
+        Self::parameter(&self.0.signature().outputs, index)
+            .name
+            .to_owned()
+    }
+
+    #[intuicio_method(use_registry, transformer = "DynamicManagedValueTransformer")]
+    pub fn input_type(&self, registry: &Registry, index: usize) -> AnkhaOption {
+        Self::parameter_type(registry, Self::parameter(&self.0.signature().inputs, index))
+    }
+
+    #[intuicio_method(use_registry, transformer = "DynamicManagedValueTransformer")]
+    pub fn output_type(&self, registry: &Registry, index: usize) -> AnkhaOption {
+        Self::parameter_type(
+            registry,
+            Self::parameter(&self.0.signature().outputs, index),
+        )
+    }
+
+    fn parameter(parameters: &[FunctionParameter], index: usize) -> &FunctionParameter {
+        parameters
+            .get(index)
+            .unwrap_or_else(|| panic!("There is no function parameter at index: {}", index))
+    }
+
+    // Type declared by script function parameter.
+    fn parameter_type(registry: &Registry, parameter: &FunctionParameter) -> AnkhaOption {
+        declared_type(parameter.meta.as_ref())
+            .and_then(|query| registry.find_type(query.compile()))
+            .map(|value| AnkhaOption::Owned(DynamicManaged::new(Type(value)).ok().unwrap()))
+            .unwrap_or_default()
+    }
 }
@@ -0,0 +1,588 @@
+use crate::{
+    library::{
+        array::{Array, AsyncArray},
+        iter::AnkhaIterator,
+        option::{AnkhaAsyncOption, AnkhaOption},
+        ordered_dictionary::OrderedMap,
+    },
+    script::{AnkhaLiteral, AnkhaOrderedLiteral},
+};
+use intuicio_core::{
+    IntuicioStruct,
+    registry::Registry,
+    transformer::{DynamicManagedValueTransformer, ValueTransformer},
+};
+use intuicio_derive::{IntuicioStruct, intuicio_method, intuicio_methods};
+use std::collections::BTreeSet;
+
+pub fn install(registry: &mut Registry) {
+    registry.add_type(Set::define_struct(registry));
+    registry.add_function(Set::is_empty__define_function(registry));
+    registry.add_function(Set::size__define_function(registry));
+    registry.add_function(Set::contains__define_function(registry));
+    registry.add_function(Set::insert__define_function(registry));
+    registry.add_function(Set::remove__define_function(registry));
+    registry.add_function(Set::clear__define_function(registry));
+    registry.add_function(Set::script_values__define_function(registry));
+    registry.add_function(Set::iter__define_function(registry));
+    registry.add_function(Set::union__define_function(registry));
+    registry.add_function(Set::intersection__define_function(registry));
+    registry.add_function(Set::difference__define_function(registry));
+    registry.add_function(Set::is_subset__define_function(registry));
+    registry.add_type(AsyncSet::define_struct(registry));
+    registry.add_function(AsyncSet::is_empty__define_function(registry));
+    registry.add_function(AsyncSet::size__define_function(registry));
+    registry.add_function(AsyncSet::contains__define_function(registry));
+    registry.add_function(AsyncSet::insert__define_function(registry));
+    registry.add_function(AsyncSet::remove__define_function(registry));
+    registry.add_function(AsyncSet::clear__define_function(registry));
+    registry.add_function(AsyncSet::script_values__define_function(registry));
+    registry.add_function(AsyncSet::union__define_function(registry));
+    registry.add_function(AsyncSet::intersection__define_function(registry));
+    registry.add_function(AsyncSet::difference__define_function(registry));
+    registry.add_function(AsyncSet::is_subset__define_function(registry));
+    registry.add_type(SortedSet::define_struct(registry));
+    registry.add_function(SortedSet::is_empty__define_function(registry));
+    registry.add_function(SortedSet::size__define_function(registry));
+    registry.add_function(SortedSet::contains__define_function(registry));
+    registry.add_function(SortedSet::insert__define_function(registry));
+    registry.add_function(SortedSet::remove__define_function(registry));
+    registry.add_function(SortedSet::clear__define_function(registry));
+    registry.add_function(SortedSet::script_values__define_function(registry));
+    registry.add_function(SortedSet::iter__define_function(registry));
+    registry.add_function(SortedSet::union__define_function(registry));
+    registry.add_function(SortedSet::intersection__define_function(registry));
+    registry.add_function(SortedSet::difference__define_function(registry));
+    registry.add_function(SortedSet::is_subset__define_function(registry));
+    registry.add_type(AsyncSortedSet::define_struct(registry));
+    registry.add_function(AsyncSortedSet::is_empty__define_function(registry));
+    registry.add_function(AsyncSortedSet::size__define_function(registry));
+    registry.add_function(AsyncSortedSet::contains__define_function(registry));
+    registry.add_function(AsyncSortedSet::insert__define_function(registry));
+    registry.add_function(AsyncSortedSet::remove__define_function(registry));
+    registry.add_function(AsyncSortedSet::clear__define_function(registry));
+    registry.add_function(AsyncSortedSet::script_values__define_function(registry));
+    registry.add_function(AsyncSortedSet::union__define_function(registry));
+    registry.add_function(AsyncSortedSet::intersection__define_function(registry));
+    registry.add_function(AsyncSortedSet::difference__define_function(registry));
+    registry.add_function(AsyncSortedSet::is_subset__define_function(registry));
+}
+
+// Sets keep literals only, `Set` iterates in insertion order and `SortedSet`
+// in order of literals.
+#[derive(IntuicioStruct, Default)]
+#[intuicio(name = "Set", module_name = "set")]
+pub struct Set {
+    #[intuicio(ignore)]
+    items: OrderedMap<AnkhaLiteral, ()>,
+}
+
+impl Set {
+    pub fn inner(&self) -> &OrderedMap<AnkhaLiteral, ()> {
+        &self.items
+    }
+
+    pub fn inner_mut(&mut self) -> &mut OrderedMap<AnkhaLiteral, ()> {
+        &mut self.items
+    }
+
+    pub fn contains_literal(&self, value: &AnkhaLiteral) -> bool {
+        self.items.contains_key(value)
+    }
+
+    pub fn literals(&self) -> impl Iterator<Item = &AnkhaLiteral> {
+        self.items.keys()
+    }
+
+    pub fn into_literals(self) -> impl Iterator<Item = AnkhaLiteral> {
+        self.items.into_iter().map(|(value, _)| value)
+    }
+}
+
+#[intuicio_methods(module_name = "set")]
+impl Set {
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn size(&self) -> usize {
+        self.items.len()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn contains(&self, value: AnkhaOption) -> bool {
+        value
+            .into_literal()
+            .map(|value| self.contains_literal(&value))
+            .unwrap_or_default()
+    }
+
+    // Tells if value was not in set yet.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn insert(&mut self, value: AnkhaOption) -> bool {
+        value
+            .into_literal()
+            .map(|value| self.items.insert(value, ()).is_none())
+            .unwrap_or_default()
+    }
+
+    // Tells if value was in set.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn remove(&mut self, value: AnkhaOption) -> bool {
+        value
+            .into_literal()
+            .map(|value| self.items.remove(&value).is_some())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    #[intuicio_method(name = "values", transformer = "DynamicManagedValueTransformer")]
+    pub fn script_values(&self) -> Array {
+        self.literals()
+            .map(|value| AnkhaOption::from_literal(value.to_owned()))
+            .collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn iter(&self) -> AnkhaIterator {
+        let values = self
+            .literals()
+            .map(|value| AnkhaOption::from_literal(value.to_owned()))
+            .collect::<Vec<_>>();
+        AnkhaIterator::new(values.into_iter())
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn union(&self, other: &Self) -> Self {
+        self.literals().chain(other.literals()).cloned().collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.literals()
+            .filter(|value| other.contains_literal(value))
+            .cloned()
+            .collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn difference(&self, other: &Self) -> Self {
+        self.literals()
+            .filter(|value| !other.contains_literal(value))
+            .cloned()
+            .collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.literals().all(|value| other.contains_literal(value))
+    }
+}
+
+impl FromIterator<AnkhaLiteral> for Set {
+    fn from_iter<T: IntoIterator<Item = AnkhaLiteral>>(iter: T) -> Self {
+        Self {
+            items: iter.into_iter().map(|value| (value, ())).collect(),
+        }
+    }
+}
+
+#[derive(IntuicioStruct, Default)]
+#[intuicio(name = "AsyncSet", module_name = "set")]
+pub struct AsyncSet {
+    #[intuicio(ignore)]
+    items: OrderedMap<AnkhaLiteral, ()>,
+}
+
+impl AsyncSet {
+    pub fn inner(&self) -> &OrderedMap<AnkhaLiteral, ()> {
+        &self.items
+    }
+
+    pub fn inner_mut(&mut self) -> &mut OrderedMap<AnkhaLiteral, ()> {
+        &mut self.items
+    }
+
+    pub fn contains_literal(&self, value: &AnkhaLiteral) -> bool {
+        self.items.contains_key(value)
+    }
+
+    pub fn literals(&self) -> impl Iterator<Item = &AnkhaLiteral> {
+        self.items.keys()
+    }
+
+    pub fn into_literals(self) -> impl Iterator<Item = AnkhaLiteral> {
+        self.items.into_iter().map(|(value, _)| value)
+    }
+}
+
+#[intuicio_methods(module_name = "set")]
+impl AsyncSet {
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn size(&self) -> usize {
+        self.items.len()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn contains(&self, value: AnkhaAsyncOption) -> bool {
+        value
+            .into_literal()
+            .map(|value| self.contains_literal(&value))
+            .unwrap_or_default()
+    }
+
+    // Tells if value was not in set yet.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn insert(&mut self, value: AnkhaAsyncOption) -> bool {
+        value
+            .into_literal()
+            .map(|value| self.items.insert(value, ()).is_none())
+            .unwrap_or_default()
+    }
+
+    // Tells if value was in set.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn remove(&mut self, value: AnkhaAsyncOption) -> bool {
+        value
+            .into_literal()
+            .map(|value| self.items.remove(&value).is_some())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    #[intuicio_method(name = "values", transformer = "DynamicManagedValueTransformer")]
+    pub fn script_values(&self) -> AsyncArray {
+        self.literals()
+            .map(|value| AnkhaAsyncOption::from_literal(value.to_owned()))
+            .collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn union(&self, other: &Self) -> Self {
+        self.literals().chain(other.literals()).cloned().collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.literals()
+            .filter(|value| other.contains_literal(value))
+            .cloned()
+            .collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn difference(&self, other: &Self) -> Self {
+        self.literals()
+            .filter(|value| !other.contains_literal(value))
+            .cloned()
+            .collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.literals().all(|value| other.contains_literal(value))
+    }
+}
+
+impl FromIterator<AnkhaLiteral> for AsyncSet {
+    fn from_iter<T: IntoIterator<Item = AnkhaLiteral>>(iter: T) -> Self {
+        Self {
+            items: iter.into_iter().map(|value| (value, ())).collect(),
+        }
+    }
+}
+
+#[derive(IntuicioStruct, Default)]
+#[intuicio(name = "SortedSet", module_name = "set")]
+pub struct SortedSet {
+    #[intuicio(ignore)]
+    items: BTreeSet<AnkhaOrderedLiteral>,
+}
+
+impl SortedSet {
+    pub fn inner(&self) -> &BTreeSet<AnkhaOrderedLiteral> {
+        &self.items
+    }
+
+    pub fn inner_mut(&mut self) -> &mut BTreeSet<AnkhaOrderedLiteral> {
+        &mut self.items
+    }
+
+    pub fn contains_literal(&self, value: &AnkhaLiteral) -> bool {
+        self.items.contains(&AnkhaOrderedLiteral(value.to_owned()))
+    }
+
+    pub fn literals(&self) -> impl Iterator<Item = &AnkhaLiteral> {
+        self.items.iter().map(|value| &value.0)
+    }
+
+    pub fn into_literals(self) -> impl Iterator<Item = AnkhaLiteral> {
+        self.items.into_iter().map(|value| value.0)
+    }
+}
+
+#[intuicio_methods(module_name = "set")]
+impl SortedSet {
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn size(&self) -> usize {
+        self.items.len()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn contains(&self, value: AnkhaOption) -> bool {
+        value
+            .into_literal()
+            .map(|value| self.contains_literal(&value))
+            .unwrap_or_default()
+    }
+
+    // Tells if value was not in set yet.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn insert(&mut self, value: AnkhaOption) -> bool {
+        value
+            .into_literal()
+            .map(|value| self.items.insert(AnkhaOrderedLiteral(value)))
+            .unwrap_or_default()
+    }
+
+    // Tells if value was in set.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn remove(&mut self, value: AnkhaOption) -> bool {
+        value
+            .into_literal()
+            .map(|value| self.items.remove(&AnkhaOrderedLiteral(value)))
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    #[intuicio_method(name = "values", transformer = "DynamicManagedValueTransformer")]
+    pub fn script_values(&self) -> Array {
+        self.literals()
+            .map(|value| AnkhaOption::from_literal(value.to_owned()))
+            .collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn iter(&self) -> AnkhaIterator {
+        let values = self
+            .literals()
+            .map(|value| AnkhaOption::from_literal(value.to_owned()))
+            .collect::<Vec<_>>();
+        AnkhaIterator::new(values.into_iter())
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn union(&self, other: &Self) -> Self {
+        self.literals().chain(other.literals()).cloned().collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.literals()
+            .filter(|value| other.contains_literal(value))
+            .cloned()
+            .collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn difference(&self, other: &Self) -> Self {
+        self.literals()
+            .filter(|value| !other.contains_literal(value))
+            .cloned()
+            .collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.literals().all(|value| other.contains_literal(value))
+    }
+}
+
+impl FromIterator<AnkhaLiteral> for SortedSet {
+    fn from_iter<T: IntoIterator<Item = AnkhaLiteral>>(iter: T) -> Self {
+        Self {
+            items: iter.into_iter().map(AnkhaOrderedLiteral).collect(),
+        }
+    }
+}
+
+#[derive(IntuicioStruct, Default)]
+#[intuicio(name = "AsyncSortedSet", module_name = "set")]
+pub struct AsyncSortedSet {
+    #[intuicio(ignore)]
+    items: BTreeSet<AnkhaOrderedLiteral>,
+}
+
+impl AsyncSortedSet {
+    pub fn inner(&self) -> &BTreeSet<AnkhaOrderedLiteral> {
+        &self.items
+    }
+
+    pub fn inner_mut(&mut self) -> &mut BTreeSet<AnkhaOrderedLiteral> {
+        &mut self.items
+    }
+
+    pub fn contains_literal(&self, value: &AnkhaLiteral) -> bool {
+        self.items.contains(&AnkhaOrderedLiteral(value.to_owned()))
+    }
+
+    pub fn literals(&self) -> impl Iterator<Item = &AnkhaLiteral> {
+        self.items.iter().map(|value| &value.0)
+    }
+
+    pub fn into_literals(self) -> impl Iterator<Item = AnkhaLiteral> {
+        self.items.into_iter().map(|value| value.0)
+    }
+}
+
+#[intuicio_methods(module_name = "set")]
+impl AsyncSortedSet {
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn size(&self) -> usize {
+        self.items.len()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn contains(&self, value: AnkhaAsyncOption) -> bool {
+        value
+            .into_literal()
+            .map(|value| self.contains_literal(&value))
+            .unwrap_or_default()
+    }
+
+    // Tells if value was not in set yet.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn insert(&mut self, value: AnkhaAsyncOption) -> bool {
+        value
+            .into_literal()
+            .map(|value| self.items.insert(AnkhaOrderedLiteral(value)))
+            .unwrap_or_default()
+    }
+
+    // Tells if value was in set.
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn remove(&mut self, value: AnkhaAsyncOption) -> bool {
+        value
+            .into_literal()
+            .map(|value| self.items.remove(&AnkhaOrderedLiteral(value)))
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    #[intuicio_method(name = "values", transformer = "DynamicManagedValueTransformer")]
+    pub fn script_values(&self) -> AsyncArray {
+        self.literals()
+            .map(|value| AnkhaAsyncOption::from_literal(value.to_owned()))
+            .collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn union(&self, other: &Self) -> Self {
+        self.literals().chain(other.literals()).cloned().collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.literals()
+            .filter(|value| other.contains_literal(value))
+            .cloned()
+            .collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn difference(&self, other: &Self) -> Self {
+        self.literals()
+            .filter(|value| !other.contains_literal(value))
+            .cloned()
+            .collect()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.literals().all(|value| other.contains_literal(value))
+    }
+}
+
+impl FromIterator<AnkhaLiteral> for AsyncSortedSet {
+    fn from_iter<T: IntoIterator<Item = AnkhaLiteral>>(iter: T) -> Self {
+        Self {
+            items: iter.into_iter().map(AnkhaOrderedLiteral).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set() {
+        let literal = |value: AnkhaLiteral| AnkhaOption::from_literal(value);
+        let keys = |array: Array| {
+            array
+                .iter()
+                .map(|item| item.read_literal().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let a = [3, 1, 2]
+            .into_iter()
+            .map(AnkhaLiteral::I32)
+            .collect::<Set>();
+        let b = [2, 4, 3]
+            .into_iter()
+            .map(AnkhaLiteral::I32)
+            .collect::<Set>();
+        let i32s = |values: &[i32]| {
+            values
+                .iter()
+                .map(|value| AnkhaLiteral::I32(*value))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys(a.union(&b).script_values()), i32s(&[3, 1, 2, 4]));
+        assert_eq!(keys(a.intersection(&b).script_values()), i32s(&[3, 2]));
+        assert_eq!(keys(a.difference(&b).script_values()), i32s(&[1]));
+        assert!(a.intersection(&b).is_subset(&a));
+        assert!(!a.is_subset(&b));
+
+        let mut sorted = a.union(&b).into_literals().collect::<SortedSet>();
+        assert_eq!(keys(sorted.script_values()), i32s(&[1, 2, 3, 4]));
+        assert!(!sorted.insert(literal(AnkhaLiteral::I32(2))));
+        assert!(sorted.remove(literal(AnkhaLiteral::I32(2))));
+        assert!(sorted.contains(literal(AnkhaLiteral::I32(4))));
+        assert_eq!(keys(sorted.script_values()), i32s(&[1, 3, 4]));
+    }
+}
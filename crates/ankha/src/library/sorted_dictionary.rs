@@ -0,0 +1,90 @@
+use crate::{
+    library::{
+        array::{Array, AsyncArray},
+        closure::{AsyncClosure, Closure},
+        dictionary::literal_dictionary,
+        iter::AnkhaIterator,
+        option::{AnkhaAsyncOption, AnkhaOption},
+    },
+    script::{AnkhaLiteral, AnkhaOrderedLiteral},
+};
+use intuicio_core::{
+    IntuicioStruct,
+    context::Context,
+    registry::Registry,
+    transformer::{DynamicManagedValueTransformer, ValueTransformer},
+};
+use intuicio_data::managed::DynamicManaged;
+use intuicio_derive::{IntuicioStruct, intuicio_method, intuicio_methods};
+use std::collections::BTreeMap;
+
+pub fn install(registry: &mut Registry) {
+    SortedDictionary::install(registry);
+    SortedDictionary::install_iterators(registry);
+    AsyncSortedDictionary::install(registry);
+}
+
+literal_dictionary!(
+    SortedDictionary as "SortedDictionary" in "sorted_dictionary",
+    BTreeMap<AnkhaOrderedLiteral, AnkhaOption>,
+    Array,
+    Closure
+);
+literal_dictionary!(SortedDictionary in "sorted_dictionary", iterators);
+literal_dictionary!(
+    AsyncSortedDictionary as "AsyncSortedDictionary" in "sorted_dictionary",
+    BTreeMap<AnkhaOrderedLiteral, AnkhaAsyncOption>,
+    AsyncArray,
+    AsyncClosure
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted_dictionary() {
+        let literal = |value: AnkhaLiteral| AnkhaOption::from_literal(value);
+        let keys = |array: Array| {
+            array
+                .iter()
+                .map(|item| item.read_literal().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let mut items = SortedDictionary::default();
+        for key in [
+            AnkhaLiteral::F32(1.5),
+            AnkhaLiteral::I32(7),
+            AnkhaLiteral::F32(-2.0),
+            AnkhaLiteral::Bool(true),
+            AnkhaLiteral::I32(-3),
+        ] {
+            items.insert(literal(key), literal(AnkhaLiteral::Unit));
+        }
+        assert_eq!(
+            keys(items.script_keys()),
+            vec![
+                AnkhaLiteral::Bool(true),
+                AnkhaLiteral::I32(-3),
+                AnkhaLiteral::I32(7),
+                AnkhaLiteral::F32(-2.0),
+                AnkhaLiteral::F32(1.5),
+            ]
+        );
+
+        // Keys compare floats by bits, so NaN finds itself and zeros stay apart,
+        // while literals compare them by value.
+        let ordered = |value: f64| AnkhaOrderedLiteral(AnkhaLiteral::F64(value));
+        assert_eq!(ordered(f64::NAN), ordered(f64::NAN));
+        assert_ne!(ordered(0.0), ordered(-0.0));
+        assert_ne!(AnkhaLiteral::F64(f64::NAN), AnkhaLiteral::F64(f64::NAN));
+        assert_eq!(AnkhaLiteral::F64(0.0), AnkhaLiteral::F64(-0.0));
+        let mut items = SortedDictionary::default();
+        for key in [f64::NAN, 0.0, -0.0, f64::NAN] {
+            items.insert(literal(AnkhaLiteral::F64(key)), literal(AnkhaLiteral::Unit));
+        }
+        assert_eq!(items.script_keys().size(), 3);
+        assert!(items.get(literal(AnkhaLiteral::F64(f64::NAN))).is_some());
+    }
+}
@@ -0,0 +1,388 @@
+// `DynamicManagedValueTransformer` borrows managed strings as `String`, hence `&String` inputs.
+#![allow(clippy::ptr_arg)]
+
+use crate::library::{array::Array, option::AnkhaOption};
+use intuicio_core::{
+    IntuicioStruct,
+    registry::Registry,
+    transformer::{DynamicManagedValueTransformer, ValueTransformer},
+};
+use intuicio_data::managed::DynamicManaged;
+use intuicio_derive::{IntuicioStruct, intuicio_function, intuicio_method, intuicio_methods};
+
+pub fn install(registry: &mut Registry) {
+    registry.add_function(concat::define_function(registry));
+    registry.add_function(length::define_function(registry));
+    registry.add_function(is_empty::define_function(registry));
+    registry.add_function(char_at::define_function(registry));
+    registry.add_function(slice::define_function(registry));
+    registry.add_function(find::define_function(registry));
+    registry.add_function(contains::define_function(registry));
+    registry.add_function(starts_with::define_function(registry));
+    registry.add_function(ends_with::define_function(registry));
+    registry.add_function(replace::define_function(registry));
+    registry.add_function(split::define_function(registry));
+    registry.add_function(join::define_function(registry));
+    registry.add_function(trim::define_function(registry));
+    registry.add_function(trim_start::define_function(registry));
+    registry.add_function(trim_end::define_function(registry));
+    registry.add_function(to_uppercase::define_function(registry));
+    registry.add_function(to_lowercase::define_function(registry));
+    registry.add_function(chars::define_function(registry));
+    registry.add_type(StringBuilder::define_struct(registry));
+    registry.add_function(StringBuilder::new__define_function(registry));
+    registry.add_function(StringBuilder::with_capacity__define_function(registry));
+    registry.add_function(StringBuilder::length__define_function(registry));
+    registry.add_function(StringBuilder::is_empty__define_function(registry));
+    registry.add_function(StringBuilder::append__define_function(registry));
+    registry.add_function(StringBuilder::append_char__define_function(registry));
+    registry.add_function(StringBuilder::append_value__define_function(registry));
+    registry.add_function(StringBuilder::append_line__define_function(registry));
+    registry.add_function(StringBuilder::clear__define_function(registry));
+    registry.add_function(StringBuilder::build__define_function(registry));
+}
+
+// Indices and lengths of string functions are counted in chars, not bytes.
+fn byte_index(value: &str, index: usize) -> usize {
+    value
+        .char_indices()
+        .nth(index)
+        .map(|(index, _)| index)
+        .unwrap_or(value.len())
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn concat(a: &String, b: &String) -> String {
+    format!("{}{}", a, b)
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn length(value: &String) -> usize {
+    value.chars().count()
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn is_empty(value: &String) -> bool {
+    value.is_empty()
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn char_at(value: &String, index: usize) -> AnkhaOption {
+    value
+        .chars()
+        .nth(index)
+        .map(|value| DynamicManaged::new(value).ok().unwrap().into())
+        .unwrap_or_default()
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn slice(value: &String, from: usize, to: usize) -> String {
+    let from = byte_index(value, from);
+    let to = byte_index(value, to).max(from);
+    value[from..to].to_owned()
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn find(value: &String, pattern: &String) -> AnkhaOption {
+    value
+        .find(pattern.as_str())
+        .map(|index| {
+            DynamicManaged::new(value[..index].chars().count())
+                .ok()
+                .unwrap()
+                .into()
+        })
+        .unwrap_or_default()
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn contains(value: &String, pattern: &String) -> bool {
+    value.contains(pattern.as_str())
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn starts_with(value: &String, pattern: &String) -> bool {
+    value.starts_with(pattern.as_str())
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn ends_with(value: &String, pattern: &String) -> bool {
+    value.ends_with(pattern.as_str())
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn replace(value: &String, from: &String, to: &String) -> String {
+    value.replace(from.as_str(), to)
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn split(value: &String, separator: &String) -> Array {
+    value
+        .split(separator.as_str())
+        .map(|item| DynamicManaged::new(item.to_owned()).ok().unwrap().into())
+        .collect()
+}
+
+#[intuicio_function(module_name = "string", transformer = "DynamicManagedValueTransformer")]
+pub fn join(items: &Array, separator: &String) -> String {
+    items
+        .iter()
+        .map(|item| {
+            item.read_literal()
+                .map(|item| item.format())
+                .expect("Could not join non-primitive value into string!")
+        })
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn trim(value: &String) -> String {
+    value.trim().to_owned()
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn trim_start(value: &String) -> String {
+    value.trim_start().to_owned()
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn trim_end(value: &String) -> String {
+    value.trim_end().to_owned()
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn to_uppercase(value: &String) -> String {
+    value.to_uppercase()
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn to_lowercase(value: &String) -> String {
+    value.to_lowercase()
+}
+
+#[intuicio_function(
+    module_name = "string",
+    type_path = "String",
+    transformer = "DynamicManagedValueTransformer"
+)]
+pub fn chars(value: &String) -> Array {
+    value
+        .chars()
+        .map(|item| DynamicManaged::new(item).ok().unwrap().into())
+        .collect()
+}
+
+#[derive(IntuicioStruct, Default)]
+#[intuicio(name = "StringBuilder", module_name = "string")]
+pub struct StringBuilder {
+    #[intuicio(ignore)]
+    buffer: String,
+}
+
+impl StringBuilder {
+    pub fn as_str(&self) -> &str {
+        &self.buffer
+    }
+
+    pub fn into_inner(self) -> String {
+        self.buffer
+    }
+}
+
+#[intuicio_methods(module_name = "string")]
+impl StringBuilder {
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffer: String::with_capacity(capacity),
+        }
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn length(&self) -> usize {
+        self.buffer.chars().count()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn append(&mut self, value: &String) {
+        self.buffer.push_str(value);
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn append_char(&mut self, value: char) {
+        self.buffer.push(value);
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn append_value(&mut self, value: AnkhaOption) {
+        let value = value
+            .read_literal()
+            .expect("Could not append non-primitive value to string builder!");
+        self.buffer.push_str(&value.format());
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn append_line(&mut self, value: &String) {
+        self.buffer.push_str(value);
+        self.buffer.push('\n');
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    #[intuicio_method(transformer = "DynamicManagedValueTransformer")]
+    pub fn build(&self) -> String {
+        self.buffer.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        library::{array::Array, tests::TestHost},
+        script::AnkhaLiteral,
+    };
+
+    #[test]
+    fn test_string() {
+        let mut host = TestHost::default();
+        host.install(
+            r#"(file
+                (mod "test"
+                    (fn "words"
+                        (out "result" (kind owned))
+                        (body
+                            (lit string " Hello, World ")
+                            (borrow)
+                            (make_register "text" (kind owned))
+                            (pop_to_register "text")
+                            (call_method (fn "trim" "string"))
+                            (borrow)
+                            (make_register "trimmed" (kind owned))
+                            (pop_to_register "trimmed")
+                            (lit string ", ")
+                            (borrow)
+                            (make_register "separator" (kind owned))
+                            (pop_to_register "separator")
+                            (swap)
+                            (call_method (fn "split" "string"))
+                        )
+                    )
+                )
+            )"#,
+        );
+        host.invoke("words").unwrap();
+        let result = host.context.stack().pop::<DynamicManaged>().unwrap();
+        let words = result
+            .consume::<Array>()
+            .ok()
+            .unwrap()
+            .into_iter()
+            .map(|item| item.into_literal().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            words,
+            vec![
+                AnkhaLiteral::String("Hello".to_owned()),
+                AnkhaLiteral::String("World".to_owned()),
+            ]
+        );
+        assert_eq!(host.context.stack().position(), 0);
+
+        let text = "zażółć".to_owned();
+        assert_eq!(length(&text), 6);
+        assert_eq!(slice(&text, 2, 4), "żó");
+        assert_eq!(slice(&text, 4, 100), "łć");
+        assert_eq!(
+            find(&text, &"ół".to_owned()).into_literal().unwrap(),
+            AnkhaLiteral::Usize(3)
+        );
+        assert!(find(&text, &"x".to_owned()).into_literal().is_none());
+
+        let mut builder = StringBuilder::new();
+        builder.append(&"HP: ".to_owned());
+        builder.append_value(AnkhaOption::from_literal(AnkhaLiteral::I32(42)));
+        builder.append_char('/');
+        builder.append_value(AnkhaOption::from_literal(AnkhaLiteral::F32(1.5)));
+        assert_eq!(builder.length(), 10);
+        assert_eq!(builder.build(), "HP: 42/1.5");
+    }
+}
@@ -8,7 +8,7 @@ use intuicio_parser::{
         opt, prefix, regex, seq, seq_del, string, suffix, zom,
     },
 };
-use std::{error::Error, str::FromStr};
+use std::{error::Error, str::FromStr, sync::LazyLock};
 
 pub struct AnkhaContentParser(ParserRegistry);
 
@@ -69,6 +69,8 @@ pub fn install(registry: &mut ParserRegistry) {
     registry.add_parser("ankha/input", param("in", true));
     registry.add_parser("ankha/output", param("out", false));
     registry.add_parser("ankha/fn", function());
+    registry.add_parser("ankha/const", constant());
+    registry.add_parser("ankha/global", global());
     registry.add_parser("ankha/operation", operation());
     registry.add_parser("ankha/body", body());
     registry
@@ -83,12 +85,18 @@ fn comment() -> ParserHandle {
     )
 }
 
+// Separators are used by every sentence and their regexes are costly to compile,
+// so they are built once and shared.
 fn ws() -> ParserHandle {
-    alt([comment(), intuicio_parser::shorthand::ws()])
+    static WS: LazyLock<ParserHandle> =
+        LazyLock::new(|| alt([comment(), intuicio_parser::shorthand::ws()]));
+    WS.clone()
 }
 
 fn ows() -> ParserHandle {
-    alt([comment(), intuicio_parser::shorthand::ows()])
+    static OWS: LazyLock<ParserHandle> =
+        LazyLock::new(|| alt([comment(), intuicio_parser::shorthand::ows()]));
+    OWS.clone()
 }
 
 fn sentence(inner: ParserHandle) -> ParserHandle {
@@ -185,11 +193,19 @@ fn module() -> ParserHandle {
                     inject("ankha/struct"),
                     inject("ankha/enum"),
                     inject("ankha/fn"),
+                    inject("ankha/const"),
+                    inject("ankha/global"),
+                    module_hook("init", true),
+                    module_hook("shutdown", false),
                     lit_string(),
                 ]),
             ),
             |values: Vec<ParserOutput>| {
                 let mut name = None;
+                let mut init = None;
+                let mut shutdown = None;
+                let mut constants = vec![];
+                let mut globals = vec![];
                 let mut structs = vec![];
                 let mut enums = vec![];
                 let mut functions = vec![];
@@ -202,12 +218,27 @@ fn module() -> ParserHandle {
                         enums.push(value.consume::<AnkhaEnum>().ok().unwrap());
                     } else if value.is::<AnkhaFunction>() {
                         functions.push(value.consume::<AnkhaFunction>().ok().unwrap());
+                    } else if value.is::<AnkhaConstant>() {
+                        constants.push(value.consume::<AnkhaConstant>().ok().unwrap());
+                    } else if value.is::<AnkhaGlobal>() {
+                        globals.push(value.consume::<AnkhaGlobal>().ok().unwrap());
+                    } else if value.is::<(String, bool)>() {
+                        let (hook, is_init) = value.consume::<(String, bool)>().ok().unwrap();
+                        if is_init {
+                            init = Some(hook);
+                        } else {
+                            shutdown = Some(hook);
+                        }
                     } else {
                         unreachable!();
                     }
                 }
                 AnkhaModule {
                     name: name.expect("Missing module name"),
+                    init,
+                    shutdown,
+                    constants,
+                    globals,
                     structs,
                     enums,
                     functions,
@@ -218,6 +249,16 @@ fn module() -> ParserHandle {
     )
 }
 
+fn module_hook(header: &'static str, init: bool) -> ParserHandle {
+    map_err(
+        map(
+            sentence(prefix(lit_string(), suffix(lit(header), ws()))),
+            move |name: String| (name, init),
+        ),
+        move |error| format!("Expected `{}` module hook | {}", header, error).into(),
+    )
+}
+
 fn meta() -> ParserHandle {
     map_err(
         sentence(prefix(meta_inner(), suffix(lit("meta"), ws()))),
@@ -377,6 +418,8 @@ fn field() -> ParserHandle {
                     inject("ankha/meta"),
                     inject("ankha/vis"),
                     inject("ankha/kind"),
+                    type_query(),
+                    field_default(),
                 ]),
             ),
             |values: Vec<ParserOutput>| {
@@ -384,6 +427,8 @@ fn field() -> ParserHandle {
                 let mut name = None;
                 let mut visibility = Visibility::Public;
                 let mut kind = AnkhaValueKind::Any;
+                let mut type_query = None;
+                let mut default = None;
                 for value in values {
                     if value.is::<String>() {
                         name = Some(value.consume::<String>().ok().unwrap());
@@ -393,6 +438,10 @@ fn field() -> ParserHandle {
                         visibility = value.consume::<Visibility>().ok().unwrap();
                     } else if value.is::<AnkhaValueKind>() {
                         kind = value.consume::<AnkhaValueKind>().ok().unwrap();
+                    } else if value.is::<AnkhaTypeQuery>() {
+                        type_query = Some(value.consume::<AnkhaTypeQuery>().ok().unwrap());
+                    } else if value.is::<AnkhaScript>() {
+                        default = Some(value.consume::<AnkhaScript>().ok().unwrap());
                     } else {
                         unreachable!();
                     }
@@ -405,6 +454,8 @@ fn field() -> ParserHandle {
                     name: name.expect("Missing field name"),
                     visibility,
                     kind,
+                    type_query,
+                    default,
                 }
             },
         ),
@@ -412,6 +463,21 @@ fn field() -> ParserHandle {
     )
 }
 
+fn field_default() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(lit("default"), inject("ankha/operation")),
+            |values: Vec<ParserOutput>| {
+                values
+                    .into_iter()
+                    .map(|value| value.consume::<AnkhaOperation>().ok().unwrap())
+                    .collect::<AnkhaScript>()
+            },
+        ),
+        |error| format!("Expected field default | {}", error).into(),
+    )
+}
+
 fn struct_type() -> ParserHandle {
     map_err(
         map(
@@ -573,12 +639,18 @@ fn param(header: &'static str, input: bool) -> ParserHandle {
         map(
             sentence_list(
                 lit(header),
-                alt([lit_string(), inject("ankha/meta"), inject("ankha/kind")]),
+                alt([
+                    lit_string(),
+                    inject("ankha/meta"),
+                    inject("ankha/kind"),
+                    type_query(),
+                ]),
             ),
             move |values: Vec<ParserOutput>| {
                 let mut meta = None;
                 let mut name = None;
                 let mut kind = AnkhaValueKind::Any;
+                let mut type_query = None;
                 for value in values {
                     if value.is::<String>() {
                         name = Some(value.consume::<String>().ok().unwrap());
@@ -586,6 +658,8 @@ fn param(header: &'static str, input: bool) -> ParserHandle {
                         meta = Some(value.consume::<Meta>().ok().unwrap())
                     } else if value.is::<AnkhaValueKind>() {
                         kind = value.consume::<AnkhaValueKind>().ok().unwrap();
+                    } else if value.is::<AnkhaTypeQuery>() {
+                        type_query = Some(value.consume::<AnkhaTypeQuery>().ok().unwrap());
                     } else {
                         unreachable!();
                     }
@@ -598,6 +672,7 @@ fn param(header: &'static str, input: bool) -> ParserHandle {
                         meta,
                         name: name.expect("Missing parameter name"),
                         kind,
+                        type_query,
                     },
                     input,
                 )
@@ -676,6 +751,84 @@ fn function() -> ParserHandle {
     )
 }
 
+fn constant() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(
+                lit("const"),
+                alt([lit_string(), inject("ankha/vis"), literal()]),
+            ),
+            |values: Vec<ParserOutput>| {
+                let mut name = None;
+                let mut visibility = Visibility::Public;
+                let mut value = None;
+                for item in values {
+                    if item.is::<String>() {
+                        name = Some(item.consume::<String>().ok().unwrap());
+                    } else if item.is::<Visibility>() {
+                        visibility = item.consume::<Visibility>().ok().unwrap();
+                    } else if item.is::<AnkhaLiteral>() {
+                        value = Some(item.consume::<AnkhaLiteral>().ok().unwrap());
+                    } else {
+                        unreachable!();
+                    }
+                }
+                AnkhaConstant {
+                    name: name.expect("Missing constant name"),
+                    visibility,
+                    value: value.expect("Missing constant value"),
+                }
+            },
+        ),
+        |error| format!("Expected `ankha/const` | {}", error).into(),
+    )
+}
+
+fn global() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(
+                lit("global"),
+                alt([
+                    lit_string(),
+                    inject("ankha/vis"),
+                    inject("ankha/kind"),
+                    inject("ankha/operation"),
+                ]),
+            ),
+            |values: Vec<ParserOutput>| {
+                let mut name = None;
+                let mut visibility = Visibility::Public;
+                let mut kind = AnkhaValueKind::Any;
+                let mut script = vec![];
+                for value in values {
+                    if value.is::<String>() {
+                        name = Some(value.consume::<String>().ok().unwrap());
+                    } else if value.is::<Visibility>() {
+                        visibility = value.consume::<Visibility>().ok().unwrap();
+                    } else if value.is::<AnkhaValueKind>() {
+                        kind = value.consume::<AnkhaValueKind>().ok().unwrap();
+                    } else if value.is::<AnkhaOperation>() {
+                        script.push(value.consume::<AnkhaOperation>().ok().unwrap());
+                    } else {
+                        unreachable!();
+                    }
+                }
+                if kind == AnkhaValueKind::Any {
+                    panic!("Missing value kind");
+                }
+                AnkhaGlobal {
+                    name: name.expect("Missing global name"),
+                    visibility,
+                    kind,
+                    script,
+                }
+            },
+        ),
+        |error| format!("Expected `ankha/global` | {}", error).into(),
+    )
+}
+
 fn body() -> ParserHandle {
     map_err(
         map(
@@ -708,6 +861,12 @@ fn operation() -> ParserHandle {
             loop_scope(),
             push_scope(),
             pop_scope(),
+            break_loop(),
+            continue_loop(),
+            return_function(),
+            throw(),
+            try_scope(),
+            match_scope(),
             ensure_register_type_op(),
             ensure_register_kind_op(),
         ]),
@@ -734,6 +893,8 @@ fn expression() -> ParserHandle {
         swap_in(),
         destructure(),
         structure(),
+        make_variant(),
+        cast(),
         box_(),
         manage(),
         unmanage(),
@@ -746,6 +907,8 @@ fn expression() -> ParserHandle {
         ensure_register_kind(),
         call_method(),
         call_indirect(),
+        get_constant(),
+        get_global(),
     ])
 }
 
@@ -1079,6 +1242,73 @@ fn structure() -> ParserHandle {
     )
 }
 
+fn make_variant() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(lit("variant"), alt([type_query(), lit_string()])),
+            |values: Vec<ParserOutput>| {
+                let mut type_query = None;
+                let mut variant = None;
+                let mut fields = vec![];
+                for value in values {
+                    if value.is::<AnkhaTypeQuery>() {
+                        type_query = Some(value.consume::<AnkhaTypeQuery>().ok().unwrap());
+                    } else if value.is::<String>() {
+                        let value = value.consume::<String>().ok().unwrap();
+                        if variant.is_none() {
+                            variant = Some(value);
+                        } else {
+                            fields.push(value);
+                        }
+                    } else {
+                        unreachable!()
+                    }
+                }
+                AnkhaExpression::Variant {
+                    type_query: type_query.expect("Expected type query"),
+                    variant: variant.expect("Expected variant name"),
+                    fields,
+                }
+            },
+        ),
+        |error| format!("Expected variant | {}", error).into(),
+    )
+}
+
+fn cast_mode() -> ParserHandle {
+    alt([
+        map(lit("as"), |_: String| AnkhaCastMode::As),
+        map(lit("checked"), |_: String| AnkhaCastMode::Checked),
+        map(lit("saturating"), |_: String| AnkhaCastMode::Saturating),
+    ])
+}
+
+fn cast() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(lit("cast"), alt([type_query(), cast_mode()])),
+            |values: Vec<ParserOutput>| {
+                let mut type_query = None;
+                let mut mode = AnkhaCastMode::default();
+                for value in values {
+                    if value.is::<AnkhaTypeQuery>() {
+                        type_query = Some(value.consume::<AnkhaTypeQuery>().ok().unwrap());
+                    } else if value.is::<AnkhaCastMode>() {
+                        mode = value.consume::<AnkhaCastMode>().ok().unwrap();
+                    } else {
+                        unreachable!()
+                    }
+                }
+                AnkhaExpression::Cast {
+                    type_query: type_query.expect("Expected type query"),
+                    mode,
+                }
+            },
+        ),
+        |error| format!("Expected cast | {}", error).into(),
+    )
+}
+
 fn box_() -> ParserHandle {
     map_err(
         map(sentence(lit("box")), |_: String| AnkhaExpression::Box),
@@ -1333,6 +1563,55 @@ fn call_indirect() -> ParserHandle {
     )
 }
 
+fn get_constant() -> ParserHandle {
+    map_err(
+        map(
+            sentence(prefix(
+                function_query_name_module(),
+                suffix(lit("get_const"), ws()),
+            )),
+            |(name, module_name): (String, Option<String>)| AnkhaExpression::GetConstant {
+                name,
+                module_name,
+            },
+        ),
+        |error| format!("Expected get constant | {}", error).into(),
+    )
+}
+
+fn get_global() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(
+                lit("get_global"),
+                alt([function_query_name_module(), inject("ankha/kind")]),
+            ),
+            |values: Vec<ParserOutput>| {
+                let mut name = None;
+                let mut module_name = None;
+                let mut kind = None;
+                for value in values {
+                    if value.is::<(String, Option<String>)>() {
+                        let (n, m) = value.consume::<(String, Option<String>)>().ok().unwrap();
+                        name = Some(n);
+                        module_name = m;
+                    } else if value.is::<AnkhaValueKind>() {
+                        kind = Some(value.consume::<AnkhaValueKind>().ok().unwrap());
+                    } else {
+                        unreachable!()
+                    }
+                }
+                AnkhaExpression::GetGlobal {
+                    name: name.expect("Missing global name"),
+                    module_name,
+                    kind: kind.expect("Expected value kind"),
+                }
+            },
+        ),
+        |error| format!("Expected get global | {}", error).into(),
+    )
+}
+
 fn group() -> ParserHandle {
     map_err(
         map(
@@ -1565,15 +1844,137 @@ fn branch_scope() -> ParserHandle {
     )
 }
 
-fn loop_scope() -> ParserHandle {
+fn try_scope_body() -> ParserHandle {
     map_err(
         map(
-            sentence_list(lit("loop"), inject("ankha/operation")),
-            |values: Vec<ParserOutput>| AnkhaOperation::LoopScope {
-                script: values
+            sentence_list(lit("body"), inject("ankha/operation")),
+            |values: Vec<ParserOutput>| {
+                values
                     .into_iter()
                     .map(|value| value.consume::<AnkhaOperation>().ok().unwrap())
-                    .collect(),
+                    .collect::<AnkhaScript>()
+            },
+        ),
+        |error| format!("Expected try scope body | {}", error).into(),
+    )
+}
+
+fn try_scope_catch() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(lit("catch"), alt([lit_string(), inject("ankha/operation")])),
+            |values: Vec<ParserOutput>| {
+                let mut name = None;
+                let mut script = vec![];
+                for value in values {
+                    if value.is::<String>() {
+                        name = Some(value.consume::<String>().ok().unwrap());
+                    } else if value.is::<AnkhaOperation>() {
+                        script.push(value.consume::<AnkhaOperation>().ok().unwrap());
+                    } else {
+                        unreachable!();
+                    }
+                }
+                (name, script)
+            },
+        ),
+        |error| format!("Expected try scope catch | {}", error).into(),
+    )
+}
+
+fn try_scope() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(lit("try"), alt([try_scope_body(), try_scope_catch()])),
+            |values: Vec<ParserOutput>| {
+                let mut script = vec![];
+                let mut catch_name = None;
+                let mut script_catch = vec![];
+                for value in values {
+                    if value.is::<AnkhaScript>() {
+                        script.extend(value.consume::<AnkhaScript>().ok().unwrap());
+                    } else if value.is::<(Option<String>, AnkhaScript)>() {
+                        let (name, catch) = value
+                            .consume::<(Option<String>, AnkhaScript)>()
+                            .ok()
+                            .unwrap();
+                        catch_name = name;
+                        script_catch.extend(catch);
+                    } else {
+                        unreachable!();
+                    }
+                }
+                AnkhaOperation::TryScope {
+                    script,
+                    catch_name,
+                    script_catch,
+                }
+            },
+        ),
+        |error| format!("Expected try scope | {}", error).into(),
+    )
+}
+
+fn match_case() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(lit("case"), alt([lit_string(), inject("ankha/operation")])),
+            |values: Vec<ParserOutput>| {
+                let mut variant = None;
+                let mut script = vec![];
+                for value in values {
+                    if value.is::<String>() {
+                        variant = Some(value.consume::<String>().ok().unwrap());
+                    } else if value.is::<AnkhaOperation>() {
+                        script.push(value.consume::<AnkhaOperation>().ok().unwrap());
+                    } else {
+                        unreachable!();
+                    }
+                }
+                AnkhaMatchCase {
+                    variant: variant.expect("Expected variant name"),
+                    script,
+                }
+            },
+        ),
+        |error| format!("Expected match case | {}", error).into(),
+    )
+}
+
+fn match_scope() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(lit("match"), match_case()),
+            |values: Vec<ParserOutput>| {
+                AnkhaOperation::Match(
+                    values
+                        .into_iter()
+                        .map(|value| value.consume::<AnkhaMatchCase>().ok().unwrap())
+                        .collect(),
+                )
+            },
+        ),
+        |error| format!("Expected match | {}", error).into(),
+    )
+}
+
+fn loop_scope() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(lit("loop"), alt([lit_string(), inject("ankha/operation")])),
+            |values: Vec<ParserOutput>| {
+                let mut script = vec![];
+                let mut label = None;
+                for value in values {
+                    if value.is::<String>() {
+                        label = Some(value.consume::<String>().ok().unwrap());
+                    } else if value.is::<AnkhaOperation>() {
+                        script.push(value.consume::<AnkhaOperation>().ok().unwrap());
+                    } else {
+                        unreachable!();
+                    }
+                }
+                AnkhaOperation::LoopScope { script, label }
             },
         ),
         |error| format!("Expected loop scope | {}", error).into(),
@@ -1602,6 +2003,54 @@ fn pop_scope() -> ParserHandle {
     )
 }
 
+fn break_loop() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(lit("break"), lit_string()),
+            |values: Vec<ParserOutput>| {
+                AnkhaOperation::Break(
+                    values
+                        .into_iter()
+                        .next()
+                        .map(|value| value.consume::<String>().ok().unwrap()),
+                )
+            },
+        ),
+        |error| format!("Expected break | {}", error).into(),
+    )
+}
+
+fn continue_loop() -> ParserHandle {
+    map_err(
+        map(
+            sentence_list(lit("continue"), lit_string()),
+            |values: Vec<ParserOutput>| {
+                AnkhaOperation::Continue(
+                    values
+                        .into_iter()
+                        .next()
+                        .map(|value| value.consume::<String>().ok().unwrap()),
+                )
+            },
+        ),
+        |error| format!("Expected continue | {}", error).into(),
+    )
+}
+
+fn return_function() -> ParserHandle {
+    map_err(
+        map(sentence(lit("return")), |_: String| AnkhaOperation::Return),
+        |error| format!("Expected return | {}", error).into(),
+    )
+}
+
+fn throw() -> ParserHandle {
+    map_err(
+        map(sentence(lit("throw")), |_: String| AnkhaOperation::Throw),
+        |error| format!("Expected throw | {}", error).into(),
+    )
+}
+
 fn ensure_register_type_op() -> ParserHandle {
     map_err(
         map(
@@ -1782,6 +2231,10 @@ mod tests {
         let input = "(file
             (use \"std\")
             (mod \"main\"
+                (init \"setup\")
+                (shutdown \"teardown\")
+                (const \"MAX_HP\" (lit i32 100))
+                (global \"score\" (kind box) (lit i32 0) (box))
                 (struct \"Foo\"
                     (meta (map
                         (\"a\" (id \"identifier\"))
@@ -1850,6 +2303,8 @@ mod tests {
                                 (out \"result\")
                             ))
                             (call_indirect)
+                            (get_const \"MAX_HP\" \"main\")
+                            (get_global \"score\" (kind refmut))
                         )
                         (call_function (fn \"add\" \"intrinsics\"
                             (type \"Bar\")
@@ -1919,7 +2374,45 @@ mod tests {
                 meta: None,
                 name: "a".to_owned(),
                 visibility: Visibility::Private,
-                kind: AnkhaValueKind::Box
+                kind: AnkhaValueKind::Box,
+                type_query: None,
+                default: None
+            }
+        );
+
+        let input = "(field \"a\" (kind owned) (type \"Foo\" \"main\"))";
+        let (rest, result) = registry.parse("ankha/field", input).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaStructField>().ok().unwrap(),
+            AnkhaStructField {
+                meta: None,
+                name: "a".to_owned(),
+                visibility: Visibility::Public,
+                kind: AnkhaValueKind::Owned,
+                type_query: Some(AnkhaTypeQuery {
+                    name: Some("Foo".to_owned()),
+                    module_name: Some("main".to_owned()),
+                    ..Default::default()
+                }),
+                default: None
+            }
+        );
+
+        let input = "(field \"a\" (kind owned) (default (lit unit)))";
+        let (rest, result) = registry.parse("ankha/field", input).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaStructField>().ok().unwrap(),
+            AnkhaStructField {
+                meta: None,
+                name: "a".to_owned(),
+                visibility: Visibility::Public,
+                kind: AnkhaValueKind::Owned,
+                type_query: None,
+                default: Some(vec![AnkhaOperation::Expression(AnkhaExpression::Literal(
+                    AnkhaLiteral::Unit
+                ))])
             }
         );
     }
@@ -1974,31 +2467,41 @@ mod tests {
                         meta: None,
                         name: "a".to_owned(),
                         visibility: Visibility::Public,
-                        kind: AnkhaValueKind::Owned
+                        kind: AnkhaValueKind::Owned,
+                        type_query: None,
+                        default: None
                     },
                     AnkhaStructField {
                         meta: None,
                         name: "b".to_owned(),
                         visibility: Visibility::Public,
-                        kind: AnkhaValueKind::Ref
+                        kind: AnkhaValueKind::Ref,
+                        type_query: None,
+                        default: None
                     },
                     AnkhaStructField {
                         meta: None,
                         name: "c".to_owned(),
                         visibility: Visibility::Public,
-                        kind: AnkhaValueKind::RefMut
+                        kind: AnkhaValueKind::RefMut,
+                        type_query: None,
+                        default: None
                     },
                     AnkhaStructField {
                         meta: None,
                         name: "d".to_owned(),
                         visibility: Visibility::Public,
-                        kind: AnkhaValueKind::Lazy
+                        kind: AnkhaValueKind::Lazy,
+                        type_query: None,
+                        default: None
                     },
                     AnkhaStructField {
                         meta: None,
                         name: "e".to_owned(),
                         visibility: Visibility::Public,
-                        kind: AnkhaValueKind::Box
+                        kind: AnkhaValueKind::Box,
+                        type_query: None,
+                        default: None
                     },
                 ]
             }
@@ -2022,7 +2525,9 @@ mod tests {
                     meta: None,
                     name: "a".to_owned(),
                     visibility: Visibility::Public,
-                    kind: AnkhaValueKind::Owned
+                    kind: AnkhaValueKind::Owned,
+                    type_query: None,
+                    default: None
                 }],
                 discriminant: None
             }
@@ -2061,7 +2566,9 @@ mod tests {
                             meta: None,
                             name: "a".to_owned(),
                             visibility: Visibility::Public,
-                            kind: AnkhaValueKind::Owned
+                            kind: AnkhaValueKind::Owned,
+                            type_query: None,
+                            default: None
                         }],
                         discriminant: None
                     },
@@ -2088,7 +2595,30 @@ mod tests {
                 AnkhaFunctionParameter {
                     meta: None,
                     name: "a".to_owned(),
-                    kind: AnkhaValueKind::Owned
+                    kind: AnkhaValueKind::Owned,
+                    type_query: None
+                },
+                true
+            )
+        );
+
+        let input = "(in \"a\" (type \"Foo\") (kind owned))";
+        let (rest, result) = registry.parse("ankha/input", input).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result
+                .consume::<(AnkhaFunctionParameter, bool)>()
+                .ok()
+                .unwrap(),
+            (
+                AnkhaFunctionParameter {
+                    meta: None,
+                    name: "a".to_owned(),
+                    kind: AnkhaValueKind::Owned,
+                    type_query: Some(AnkhaTypeQuery {
+                        name: Some("Foo".to_owned()),
+                        ..Default::default()
+                    })
                 },
                 true
             )
@@ -2106,7 +2636,8 @@ mod tests {
                 AnkhaFunctionParameter {
                     meta: None,
                     name: "result".to_owned(),
-                    kind: AnkhaValueKind::Owned
+                    kind: AnkhaValueKind::Owned,
+                    type_query: None
                 },
                 false
             )
@@ -2143,18 +2674,21 @@ mod tests {
                     AnkhaFunctionParameter {
                         meta: None,
                         name: "a".to_owned(),
-                        kind: AnkhaValueKind::Owned
+                        kind: AnkhaValueKind::Owned,
+                        type_query: None
                     },
                     AnkhaFunctionParameter {
                         meta: None,
                         name: "b".to_owned(),
-                        kind: AnkhaValueKind::Owned
+                        kind: AnkhaValueKind::Owned,
+                        type_query: None
                     }
                 ],
                 outputs: vec![AnkhaFunctionParameter {
                     meta: None,
                     name: "result".to_owned(),
-                    kind: AnkhaValueKind::Owned
+                    kind: AnkhaValueKind::Owned,
+                    type_query: None
                 }],
                 script: vec![
                     AnkhaOperation::Expression(AnkhaExpression::Literal(AnkhaLiteral::I32(40))),
@@ -2168,6 +2702,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_constant() {
+        let mut registry = ParserRegistry::default();
+        install(&mut registry);
+
+        let input = "(const \"MAX_HP\" (vis module) (lit i32 100))";
+        let (rest, result) = registry.parse("ankha/const", input).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaConstant>().ok().unwrap(),
+            AnkhaConstant {
+                name: "MAX_HP".to_owned(),
+                visibility: Visibility::Module,
+                value: AnkhaLiteral::I32(100),
+            }
+        );
+    }
+
+    #[test]
+    fn test_global() {
+        let mut registry = ParserRegistry::default();
+        install(&mut registry);
+
+        let input = "(global \"score\" (kind box)
+            (lit i32 0)
+            (box)
+        )";
+        let (rest, result) = registry.parse("ankha/global", input).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaGlobal>().ok().unwrap(),
+            AnkhaGlobal {
+                name: "score".to_owned(),
+                visibility: Visibility::Public,
+                kind: AnkhaValueKind::Box,
+                script: vec![
+                    AnkhaOperation::Expression(AnkhaExpression::Literal(AnkhaLiteral::I32(0))),
+                    AnkhaOperation::Expression(AnkhaExpression::Box),
+                ],
+            }
+        );
+    }
+
     #[test]
     fn test_literal() {
         let mut registry = ParserRegistry::default();
@@ -2387,6 +2964,52 @@ mod tests {
             }
         );
 
+        let (rest, result) = expression()
+            .parse(&registry, "(variant (type \"Bar\") \"B\" \"a\")")
+            .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaExpression>().ok().unwrap(),
+            AnkhaExpression::Variant {
+                type_query: AnkhaTypeQuery {
+                    name: Some("Bar".to_owned()),
+                    ..Default::default()
+                },
+                variant: "B".to_owned(),
+                fields: vec!["a".to_owned()]
+            }
+        );
+
+        let (rest, result) = expression()
+            .parse(&registry, "(cast (type \"f64\"))")
+            .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaExpression>().ok().unwrap(),
+            AnkhaExpression::Cast {
+                type_query: AnkhaTypeQuery {
+                    name: Some("f64".to_owned()),
+                    ..Default::default()
+                },
+                mode: AnkhaCastMode::As
+            }
+        );
+
+        let (rest, result) = expression()
+            .parse(&registry, "(cast saturating (type \"u8\"))")
+            .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaExpression>().ok().unwrap(),
+            AnkhaExpression::Cast {
+                type_query: AnkhaTypeQuery {
+                    name: Some("u8".to_owned()),
+                    ..Default::default()
+                },
+                mode: AnkhaCastMode::Saturating
+            }
+        );
+
         let (rest, result) = expression().parse(&registry, "(box)").unwrap();
         assert_eq!(rest, "");
         assert_eq!(
@@ -2495,6 +3118,29 @@ mod tests {
             result.consume::<AnkhaExpression>().ok().unwrap(),
             AnkhaExpression::CallIndirect
         );
+
+        let input = "(get_const \"MAX_HP\" \"main\")";
+        let (rest, result) = expression().parse(&registry, input).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaExpression>().ok().unwrap(),
+            AnkhaExpression::GetConstant {
+                name: "MAX_HP".to_owned(),
+                module_name: Some("main".to_owned()),
+            }
+        );
+
+        let input = "(get_global \"score\" (kind ref))";
+        let (rest, result) = expression().parse(&registry, input).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaExpression>().ok().unwrap(),
+            AnkhaExpression::GetGlobal {
+                name: "score".to_owned(),
+                module_name: None,
+                kind: AnkhaValueKind::Ref,
+            }
+        );
     }
 
     #[test]
@@ -2630,7 +3276,23 @@ mod tests {
             AnkhaOperation::LoopScope {
                 script: vec![AnkhaOperation::Expression(AnkhaExpression::Literal(
                     AnkhaLiteral::Unit
-                ))]
+                ))],
+                label: None,
+            }
+        );
+
+        let (rest, result) = operation()
+            .parse(&registry, "(loop \"outer\" (break) (continue \"outer\"))")
+            .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaOperation>().ok().unwrap(),
+            AnkhaOperation::LoopScope {
+                script: vec![
+                    AnkhaOperation::Break(None),
+                    AnkhaOperation::Continue(Some("outer".to_owned())),
+                ],
+                label: Some("outer".to_owned()),
             }
         );
 
@@ -2652,6 +3314,57 @@ mod tests {
             AnkhaOperation::PopScope
         );
 
+        let (rest, result) = operation().parse(&registry, "(return)").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaOperation>().ok().unwrap(),
+            AnkhaOperation::Return
+        );
+
+        let (rest, result) = operation()
+            .parse(
+                &registry,
+                "(try (body (lit unit) (throw)) (catch \"err\" (drop_register \"err\")))",
+            )
+            .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaOperation>().ok().unwrap(),
+            AnkhaOperation::TryScope {
+                script: vec![
+                    AnkhaOperation::Expression(AnkhaExpression::Literal(AnkhaLiteral::Unit)),
+                    AnkhaOperation::Throw,
+                ],
+                catch_name: Some("err".to_owned()),
+                script_catch: vec![AnkhaOperation::DropRegister(AnkhaRegisterAddress::Name(
+                    "err".to_owned()
+                ))],
+            }
+        );
+
+        let (rest, result) = operation()
+            .parse(
+                &registry,
+                "(match (case \"A\") (case \"B\" (pop_to_register \"a\")))",
+            )
+            .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result.consume::<AnkhaOperation>().ok().unwrap(),
+            AnkhaOperation::Match(vec![
+                AnkhaMatchCase {
+                    variant: "A".to_owned(),
+                    script: vec![],
+                },
+                AnkhaMatchCase {
+                    variant: "B".to_owned(),
+                    script: vec![AnkhaOperation::PopToRegister(AnkhaRegisterAddress::Name(
+                        "a".to_owned()
+                    ))],
+                },
+            ])
+        );
+
         let (rest, result) = operation()
             .parse(&registry, "(ensure_register_type (type \"Foo\") \"a\")")
             .unwrap();
@@ -1,18 +1,29 @@
-use crate::library::reflection::{Function, Type};
+use crate::library::{
+    option::AnkhaOption,
+    reflection::{Function, Type},
+};
+use intuicio_backend_vm::{debugger::VmDebuggerHandle, scope::VmScope};
 use intuicio_core::{
     IntuicioVersion, Visibility,
     context::Context,
     crate_version,
-    function::{FunctionQuery, FunctionQueryParameter},
-    meta::Meta,
+    function::{
+        Function as CoreFunction, FunctionBody, FunctionHandle, FunctionQuery,
+        FunctionQueryParameter,
+    },
+    meta::{Meta, MetaValue},
     object::Object,
     registry::Registry,
     script::{
         ScriptContentProvider, ScriptEnum, ScriptEnumVariant, ScriptExpression, ScriptFunction,
-        ScriptFunctionParameter, ScriptFunctionSignature, ScriptHandle, ScriptModule,
-        ScriptOperation, ScriptPackage, ScriptStruct, ScriptStructField,
+        ScriptFunctionGenerator, ScriptFunctionParameter, ScriptFunctionSignature, ScriptHandle,
+        ScriptModule, ScriptOperation, ScriptPackage, ScriptStruct, ScriptStructField,
+    },
+    types::{
+        EnumVariantQuery, StructFieldQuery, Type as CoreType, TypeHandle, TypeQuery,
+        enum_type::{Enum, EnumVariant},
+        struct_type::StructField,
     },
-    types::{StructFieldQuery, TypeQuery},
 };
 use intuicio_data::{
     lifetime::Lifetime,
@@ -23,9 +34,19 @@ use intuicio_data::{
 use serde::{Deserialize, Serialize};
 use std::{
     alloc::dealloc,
-    collections::HashMap,
+    any::Any,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
     error::Error,
     hash::{Hash, Hasher},
+    ops::Deref,
+    panic::{AssertUnwindSafe, catch_unwind, resume_unwind},
+    path::Path,
+    sync::{
+        Arc, Mutex, OnceLock, PoisonError, RwLock, Weak,
+        atomic::{AtomicUsize, Ordering as AtomicOrdering},
+    },
+    thread::ThreadId,
 };
 
 pub type AnkhaScript = Vec<AnkhaOperation>;
@@ -34,6 +55,62 @@ pub fn frontend_ankha_version() -> IntuicioVersion {
     crate_version!()
 }
 
+/// Hash carried by managed values of given type. Script structs and enums are runtime
+/// types that share type hash of `RuntimeObject`, so their values carry hash of type
+/// name qualified by its module instead.
+pub fn type_identity(handle: &TypeHandle) -> TypeHash {
+    if handle.is_runtime() {
+        unsafe {
+            TypeHash::raw(&format!(
+                "{}::{}",
+                handle.module_name().unwrap_or_default(),
+                handle.name()
+            ))
+        }
+    } else {
+        handle.type_hash()
+    }
+}
+
+/// Finds type of managed value by hash it carries.
+pub fn find_value_type(registry: &Registry, type_hash: TypeHash) -> Option<TypeHandle> {
+    registry
+        .find_type(TypeQuery {
+            type_hash: Some(type_hash),
+            ..Default::default()
+        })
+        .or_else(|| {
+            registry
+                .types()
+                .filter(|handle| handle.is_runtime())
+                .find(|handle| type_identity(handle) == type_hash)
+                .cloned()
+        })
+}
+
+/// Query matching only given type - type hash alone does not tell script types apart.
+pub fn type_handle_query<'a>(handle: &TypeHandle) -> TypeQuery<'a> {
+    TypeQuery {
+        name: Some(handle.name().to_owned().into()),
+        module_name: handle.module_name().map(|name| name.to_owned().into()),
+        type_hash: Some(handle.type_hash()),
+        ..Default::default()
+    }
+}
+
+/// Finds variant of enum value through its type. Only runtime enums have layout known
+/// from their type, with discriminant in first byte as `EnumBuilder` lays it out, so
+/// values of native enums give no variant.
+///
+/// # Safety
+/// Pointer must point to value of given enum type.
+pub unsafe fn enum_variant(enum_type: &Enum, pointer: *const u8) -> Option<&EnumVariant> {
+    if !enum_type.is_runtime() {
+        return None;
+    }
+    enum_type.find_variant_by_discriminant(unsafe { pointer.read() })
+}
+
 pub(crate) fn stack_managed_variant<R>(
     context: &mut Context,
     owned_variant: impl FnOnce(&mut Context, DynamicManaged) -> R,
@@ -524,8 +601,338 @@ impl Hash for AnkhaLiteral {
 
 impl Eq for AnkhaLiteral {}
 
+/// Literal that has total order, used as key of sorted collections. Literals of
+/// different types are ordered by type, while floats are ordered and compared by
+/// their bits the way `total_cmp` does, so NaN finds itself and zeros of different
+/// sign stay apart.
+#[derive(Debug, Clone)]
+pub struct AnkhaOrderedLiteral(pub AnkhaLiteral);
+
+impl Deref for AnkhaOrderedLiteral {
+    type Target = AnkhaLiteral;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<AnkhaLiteral> for AnkhaOrderedLiteral {
+    fn from(value: AnkhaLiteral) -> Self {
+        Self(value)
+    }
+}
+
+impl From<AnkhaOrderedLiteral> for AnkhaLiteral {
+    fn from(value: AnkhaOrderedLiteral) -> Self {
+        value.0
+    }
+}
+
+impl PartialEq for AnkhaOrderedLiteral {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for AnkhaOrderedLiteral {}
+
+impl Ord for AnkhaOrderedLiteral {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.0, &other.0) {
+            (AnkhaLiteral::Unit, AnkhaLiteral::Unit) => Ordering::Equal,
+            (AnkhaLiteral::Bool(a), AnkhaLiteral::Bool(b)) => a.cmp(b),
+            (AnkhaLiteral::I8(a), AnkhaLiteral::I8(b)) => a.cmp(b),
+            (AnkhaLiteral::I16(a), AnkhaLiteral::I16(b)) => a.cmp(b),
+            (AnkhaLiteral::I32(a), AnkhaLiteral::I32(b)) => a.cmp(b),
+            (AnkhaLiteral::I64(a), AnkhaLiteral::I64(b)) => a.cmp(b),
+            (AnkhaLiteral::I128(a), AnkhaLiteral::I128(b)) => a.cmp(b),
+            (AnkhaLiteral::Isize(a), AnkhaLiteral::Isize(b)) => a.cmp(b),
+            (AnkhaLiteral::U8(a), AnkhaLiteral::U8(b)) => a.cmp(b),
+            (AnkhaLiteral::U16(a), AnkhaLiteral::U16(b)) => a.cmp(b),
+            (AnkhaLiteral::U32(a), AnkhaLiteral::U32(b)) => a.cmp(b),
+            (AnkhaLiteral::U64(a), AnkhaLiteral::U64(b)) => a.cmp(b),
+            (AnkhaLiteral::U128(a), AnkhaLiteral::U128(b)) => a.cmp(b),
+            (AnkhaLiteral::Usize(a), AnkhaLiteral::Usize(b)) => a.cmp(b),
+            (AnkhaLiteral::F32(a), AnkhaLiteral::F32(b)) => a.total_cmp(b),
+            (AnkhaLiteral::F64(a), AnkhaLiteral::F64(b)) => a.total_cmp(b),
+            (AnkhaLiteral::Char(a), AnkhaLiteral::Char(b)) => a.cmp(b),
+            (AnkhaLiteral::String(a), AnkhaLiteral::String(b)) => a.cmp(b),
+            (a, b) => a.type_order().cmp(&b.type_order()),
+        }
+    }
+}
+
+impl PartialOrd for AnkhaOrderedLiteral {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl AnkhaLiteral {
+    fn type_order(&self) -> u8 {
+        match self {
+            Self::Unit => 0,
+            Self::Bool(_) => 1,
+            Self::I8(_) => 2,
+            Self::I16(_) => 3,
+            Self::I32(_) => 4,
+            Self::I64(_) => 5,
+            Self::I128(_) => 6,
+            Self::Isize(_) => 7,
+            Self::U8(_) => 8,
+            Self::U16(_) => 9,
+            Self::U32(_) => 10,
+            Self::U64(_) => 11,
+            Self::U128(_) => 12,
+            Self::Usize(_) => 13,
+            Self::F32(_) => 14,
+            Self::F64(_) => 15,
+            Self::Char(_) => 16,
+            Self::String(_) => 17,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnkhaCastMode {
+    // Converts numbers the way Rust `as` operator does: integers wrap, while floats
+    // saturate when converted to integers and NaN becomes zero.
+    #[default]
+    As,
+    // Produces `option::Option` that is none when value does not fit target type.
+    Checked,
+    // Clamps numbers to range of target type.
+    Saturating,
+}
+
+impl AnkhaCastMode {
+    pub fn is_as(&self) -> bool {
+        matches!(self, Self::As)
+    }
+}
+
+// Intermediate form of numeric literal being cast.
+#[derive(Clone, Copy)]
+enum AnkhaNumber {
+    Signed(i128),
+    Unsigned(u128),
+    Float(f64),
+}
+
+macro_rules! cast_integer {
+    ($number:expr, $mode:expr, $type:ty) => {
+        match ($number, $mode) {
+            (AnkhaNumber::Signed(value), AnkhaCastMode::As) => Some(value as $type),
+            (AnkhaNumber::Signed(value), AnkhaCastMode::Checked) => <$type>::try_from(value).ok(),
+            (AnkhaNumber::Signed(value), AnkhaCastMode::Saturating) => {
+                Some(<$type>::try_from(value).unwrap_or(if value < 0 {
+                    <$type>::MIN
+                } else {
+                    <$type>::MAX
+                }))
+            }
+            (AnkhaNumber::Unsigned(value), AnkhaCastMode::As) => Some(value as $type),
+            (AnkhaNumber::Unsigned(value), AnkhaCastMode::Checked) => <$type>::try_from(value).ok(),
+            (AnkhaNumber::Unsigned(value), AnkhaCastMode::Saturating) => {
+                Some(<$type>::try_from(value).unwrap_or(<$type>::MAX))
+            }
+            (AnkhaNumber::Float(value), AnkhaCastMode::Checked) => {
+                let value = value.trunc();
+                (value.is_finite()
+                    && value >= <$type>::MIN as f64
+                    && value < <$type>::MAX as f64 + 1.0)
+                    .then_some(value as $type)
+            }
+            (AnkhaNumber::Float(value), _) => Some(value as $type),
+        }
+    };
+}
+
+macro_rules! parse_literal {
+    ($value:expr, $mode:expr, $type:ty, $variant:ident) => {
+        match $value.trim().parse::<$type>() {
+            Ok(value) => Some(Self::$variant(value)),
+            Err(_) if $mode == AnkhaCastMode::Checked => None,
+            Err(_) => panic!(
+                "Could not parse `{}` as `{}`!",
+                $value,
+                std::any::type_name::<$type>()
+            ),
+        }
+    };
+}
+
+impl AnkhaLiteral {
+    fn number(&self) -> Option<AnkhaNumber> {
+        match self {
+            Self::Bool(value) => Some(AnkhaNumber::Unsigned(*value as u128)),
+            Self::I8(value) => Some(AnkhaNumber::Signed(*value as i128)),
+            Self::I16(value) => Some(AnkhaNumber::Signed(*value as i128)),
+            Self::I32(value) => Some(AnkhaNumber::Signed(*value as i128)),
+            Self::I64(value) => Some(AnkhaNumber::Signed(*value as i128)),
+            Self::I128(value) => Some(AnkhaNumber::Signed(*value)),
+            Self::Isize(value) => Some(AnkhaNumber::Signed(*value as i128)),
+            Self::U8(value) => Some(AnkhaNumber::Unsigned(*value as u128)),
+            Self::U16(value) => Some(AnkhaNumber::Unsigned(*value as u128)),
+            Self::U32(value) => Some(AnkhaNumber::Unsigned(*value as u128)),
+            Self::U64(value) => Some(AnkhaNumber::Unsigned(*value as u128)),
+            Self::U128(value) => Some(AnkhaNumber::Unsigned(*value)),
+            Self::Usize(value) => Some(AnkhaNumber::Unsigned(*value as u128)),
+            Self::F32(value) => Some(AnkhaNumber::Float(*value as f64)),
+            Self::F64(value) => Some(AnkhaNumber::Float(*value)),
+            Self::Char(value) => Some(AnkhaNumber::Unsigned(*value as u128)),
+            Self::Unit | Self::String(_) => None,
+        }
+    }
+
+    pub fn format(&self) -> String {
+        match self {
+            Self::Unit => "()".to_owned(),
+            Self::Bool(value) => value.to_string(),
+            Self::I8(value) => value.to_string(),
+            Self::I16(value) => value.to_string(),
+            Self::I32(value) => value.to_string(),
+            Self::I64(value) => value.to_string(),
+            Self::I128(value) => value.to_string(),
+            Self::Isize(value) => value.to_string(),
+            Self::U8(value) => value.to_string(),
+            Self::U16(value) => value.to_string(),
+            Self::U32(value) => value.to_string(),
+            Self::U64(value) => value.to_string(),
+            Self::U128(value) => value.to_string(),
+            Self::Usize(value) => value.to_string(),
+            Self::F32(value) => value.to_string(),
+            Self::F64(value) => value.to_string(),
+            Self::Char(value) => value.to_string(),
+            Self::String(value) => value.to_owned(),
+        }
+    }
+
+    fn parse(value: &str, type_hash: TypeHash, mode: AnkhaCastMode) -> Option<Self> {
+        if type_hash == TypeHash::of::<bool>() {
+            parse_literal!(value, mode, bool, Bool)
+        } else if type_hash == TypeHash::of::<i8>() {
+            parse_literal!(value, mode, i8, I8)
+        } else if type_hash == TypeHash::of::<i16>() {
+            parse_literal!(value, mode, i16, I16)
+        } else if type_hash == TypeHash::of::<i32>() {
+            parse_literal!(value, mode, i32, I32)
+        } else if type_hash == TypeHash::of::<i64>() {
+            parse_literal!(value, mode, i64, I64)
+        } else if type_hash == TypeHash::of::<i128>() {
+            parse_literal!(value, mode, i128, I128)
+        } else if type_hash == TypeHash::of::<isize>() {
+            parse_literal!(value, mode, isize, Isize)
+        } else if type_hash == TypeHash::of::<u8>() {
+            parse_literal!(value, mode, u8, U8)
+        } else if type_hash == TypeHash::of::<u16>() {
+            parse_literal!(value, mode, u16, U16)
+        } else if type_hash == TypeHash::of::<u32>() {
+            parse_literal!(value, mode, u32, U32)
+        } else if type_hash == TypeHash::of::<u64>() {
+            parse_literal!(value, mode, u64, U64)
+        } else if type_hash == TypeHash::of::<u128>() {
+            parse_literal!(value, mode, u128, U128)
+        } else if type_hash == TypeHash::of::<usize>() {
+            parse_literal!(value, mode, usize, Usize)
+        } else if type_hash == TypeHash::of::<f32>() {
+            parse_literal!(value, mode, f32, F32)
+        } else if type_hash == TypeHash::of::<f64>() {
+            parse_literal!(value, mode, f64, F64)
+        } else if type_hash == TypeHash::of::<char>() {
+            parse_literal!(value, mode, char, Char)
+        } else if type_hash == TypeHash::of::<String>() {
+            Some(Self::String(value.to_owned()))
+        } else {
+            panic!("Cannot parse String into non-primitive type!");
+        }
+    }
+
+    // Converts literal into primitive type, none means checked cast failed.
+    pub fn cast(self, type_hash: TypeHash, mode: AnkhaCastMode) -> Option<Self> {
+        if type_hash == TypeHash::of::<String>() {
+            return Some(Self::String(self.format()));
+        }
+        if let Self::String(value) = &self {
+            return Self::parse(value, type_hash, mode);
+        }
+        let number = self
+            .number()
+            .unwrap_or_else(|| panic!("Cannot cast {:?} into non-String type!", self));
+        if type_hash == TypeHash::of::<bool>() {
+            let value = match number {
+                AnkhaNumber::Signed(value) => value as f64,
+                AnkhaNumber::Unsigned(value) => value as f64,
+                AnkhaNumber::Float(value) => value,
+            };
+            if mode == AnkhaCastMode::Checked && value != 0.0 && value != 1.0 {
+                None
+            } else {
+                Some(Self::Bool(value != 0.0))
+            }
+        } else if type_hash == TypeHash::of::<i8>() {
+            cast_integer!(number, mode, i8).map(Self::I8)
+        } else if type_hash == TypeHash::of::<i16>() {
+            cast_integer!(number, mode, i16).map(Self::I16)
+        } else if type_hash == TypeHash::of::<i32>() {
+            cast_integer!(number, mode, i32).map(Self::I32)
+        } else if type_hash == TypeHash::of::<i64>() {
+            cast_integer!(number, mode, i64).map(Self::I64)
+        } else if type_hash == TypeHash::of::<i128>() {
+            cast_integer!(number, mode, i128).map(Self::I128)
+        } else if type_hash == TypeHash::of::<isize>() {
+            cast_integer!(number, mode, isize).map(Self::Isize)
+        } else if type_hash == TypeHash::of::<u8>() {
+            cast_integer!(number, mode, u8).map(Self::U8)
+        } else if type_hash == TypeHash::of::<u16>() {
+            cast_integer!(number, mode, u16).map(Self::U16)
+        } else if type_hash == TypeHash::of::<u32>() {
+            cast_integer!(number, mode, u32).map(Self::U32)
+        } else if type_hash == TypeHash::of::<u64>() {
+            cast_integer!(number, mode, u64).map(Self::U64)
+        } else if type_hash == TypeHash::of::<u128>() {
+            cast_integer!(number, mode, u128).map(Self::U128)
+        } else if type_hash == TypeHash::of::<usize>() {
+            cast_integer!(number, mode, usize).map(Self::Usize)
+        } else if type_hash == TypeHash::of::<f32>() {
+            match (number, mode) {
+                (AnkhaNumber::Signed(value), _) => Some(Self::F32(value as f32)),
+                (AnkhaNumber::Unsigned(value), _) => Some(Self::F32(value as f32)),
+                (AnkhaNumber::Float(value), AnkhaCastMode::As) => Some(Self::F32(value as f32)),
+                (AnkhaNumber::Float(value), AnkhaCastMode::Checked) => (!value.is_finite()
+                    || (value as f32).is_finite())
+                .then_some(Self::F32(value as f32)),
+                (AnkhaNumber::Float(value), AnkhaCastMode::Saturating) => {
+                    Some(Self::F32(if value.is_finite() {
+                        value.clamp(f32::MIN as f64, f32::MAX as f64)
+                    } else {
+                        value
+                    } as f32))
+                }
+            }
+        } else if type_hash == TypeHash::of::<f64>() {
+            Some(Self::F64(match number {
+                AnkhaNumber::Signed(value) => value as f64,
+                AnkhaNumber::Unsigned(value) => value as f64,
+                AnkhaNumber::Float(value) => value,
+            }))
+        } else if type_hash == TypeHash::of::<char>() {
+            let code = cast_integer!(number, AnkhaCastMode::Checked, u32);
+            match code.and_then(char::from_u32) {
+                Some(value) => Some(Self::Char(value)),
+                None if mode == AnkhaCastMode::Checked => None,
+                None => Some(Self::Char(char::REPLACEMENT_CHARACTER)),
+            }
+        } else {
+            panic!("Cannot cast {:?} into non-primitive type!", self);
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AnkhaExpression {
+    // Expressions marked hidden are made only by lowering of operations - they are not
+    // part of serialized scripts and modules using them are rejected by validation.
     // push owned value on stack.
     Literal(AnkhaLiteral),
     // drop top value from stack.
@@ -579,6 +986,28 @@ pub enum AnkhaExpression {
         #[serde(default, skip_serializing_if = "Vec::is_empty")]
         fields: Vec<String>,
     },
+    // Consume top stack managed values into new enum value of variant fields.
+    Variant {
+        type_query: AnkhaTypeQuery,
+        variant: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        fields: Vec<String>,
+    },
+    // Consume and unpack top stack owned enum value variant managed fields followed by
+    // unmanaged true if it holds that variant, otherwise pushes only unmanaged false.
+    MatchVariant {
+        variant: String,
+    },
+    // Panics with variant of top stack owned enum value that was not matched.
+    #[doc(hidden)]
+    #[serde(skip)]
+    MatchFailure,
+    // Converts top stack owned primitive value into another primitive type or String.
+    Cast {
+        type_query: AnkhaTypeQuery,
+        #[serde(default, skip_serializing_if = "AnkhaCastMode::is_as")]
+        mode: AnkhaCastMode,
+    },
     // Turns top stack owned value into boxed value.
     Box,
     // Turns top stack owned unmanaged value into managed value.
@@ -623,9 +1052,110 @@ pub enum AnkhaExpression {
     GetFunction {
         query: AnkhaFunctionQuery,
     },
+    // Pushes owned value of module constant.
+    GetConstant {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        module_name: Option<String>,
+    },
+    // Pushes ref, ref mut or lazy value of module global.
+    GetGlobal {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        module_name: Option<String>,
+        kind: AnkhaValueKind,
+    },
+    // Checks if function inputs on stack have their declared types and panics if don't.
+    #[doc(hidden)]
+    #[serde(skip)]
+    EnsureInputTypes {
+        function: String,
+        inputs: Vec<AnkhaFunctionParameter>,
+    },
+    // Pushes frame of entered script function onto call stack.
+    #[doc(hidden)]
+    #[serde(skip)]
+    PushCallFrame {
+        name: Arc<str>,
+    },
+    // Pops frame of script function that returns from call stack.
+    #[doc(hidden)]
+    #[serde(skip)]
+    PopCallFrame,
+    // Pushes condition of tail call loop, repeating drops registers of current call.
+    #[doc(hidden)]
+    #[serde(skip)]
+    TailCallLoop {
+        repeat: bool,
+    },
+    // Starts unwinding scopes towards target of jump.
+    #[doc(hidden)]
+    #[serde(skip)]
+    Jump(AnkhaJump),
+    // Finishes pending return of function.
+    #[doc(hidden)]
+    #[serde(skip)]
+    FinishReturn,
+    // Pops owned value and throws it to nearest catch scope.
+    #[doc(hidden)]
+    #[serde(skip)]
+    Throw,
+    // Calls function like `CallFunction` operation does, used where call has to be guarded.
+    CallFunction {
+        function_query: AnkhaFunctionQuery,
+    },
+    // Turns error raised by expression into pending throw that unwinds scopes
+    // to nearest try scope of the same function.
+    #[doc(hidden)]
+    #[serde(skip)]
+    Guarded(Box<AnkhaExpression>),
+    // Starts try scope and pushes unmanaged true.
+    #[doc(hidden)]
+    #[serde(skip)]
+    TryEnter,
+    // Ends try scope and pushes caught error followed by unmanaged true,
+    // or only unmanaged false when nothing was caught.
+    #[doc(hidden)]
+    #[serde(skip)]
+    TryExit,
+    // Pushes scope continuation condition, false when jump is pending.
+    #[doc(hidden)]
+    #[serde(skip)]
+    JumpCheck {
+        restore_registers: bool,
+    },
+    // Pushes condition of loop and its body continuation, finishing pending jump
+    // that targets this loop.
+    #[doc(hidden)]
+    #[serde(skip)]
+    LoopJumpCheck {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+        restore_registers: bool,
+    },
 }
 
 impl AnkhaExpression {
+    // Expressions made only by lowering of operations.
+    pub fn is_internal(&self) -> bool {
+        matches!(
+            self,
+            Self::MatchFailure
+                | Self::EnsureInputTypes { .. }
+                | Self::PushCallFrame { .. }
+                | Self::PopCallFrame
+                | Self::TailCallLoop { .. }
+                | Self::Jump(_)
+                | Self::FinishReturn
+                | Self::Throw
+                | Self::Guarded(_)
+                | Self::TryEnter
+                | Self::TryExit
+                | Self::JumpCheck { .. }
+                | Self::LoopJumpCheck { .. }
+        )
+    }
+
     fn stack_unwrap_boolean(context: &mut Context) {
         stack_managed_variant(
             context,
@@ -773,11 +1303,7 @@ impl AnkhaExpression {
                 panic!("Cannot borrow field from owned stack value!");
             },
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of ref stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_
@@ -876,11 +1402,7 @@ impl AnkhaExpression {
                 context.stack().push(value_ref);
             },
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of ref mut stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_
@@ -967,11 +1489,7 @@ impl AnkhaExpression {
                 context.stack().push(value_ref);
             },
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of lazy stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_
@@ -1058,11 +1576,9 @@ impl AnkhaExpression {
                 context.stack().push(value_ref);
             },
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: value.type_hash(),
-                        ..Default::default()
-                    })
+                let type_ = value
+                    .type_hash()
+                    .and_then(|type_hash| find_value_type(registry, type_hash))
                     .expect("Could not find type of box stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_
@@ -1167,11 +1683,7 @@ impl AnkhaExpression {
                 panic!("Cannot borrow field mutably from ref stack value!");
             },
             |context, mut value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of ref mut stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_
@@ -1258,11 +1770,7 @@ impl AnkhaExpression {
                 context.stack().push(value_ref);
             },
             |context, mut value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of lazy stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_
@@ -1349,11 +1857,9 @@ impl AnkhaExpression {
                 context.stack().push(value_ref);
             },
             |context, mut value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: value.type_hash(),
-                        ..Default::default()
-                    })
+                let type_ = value
+                    .type_hash()
+                    .and_then(|type_hash| find_value_type(registry, type_hash))
                     .expect("Could not find type of box stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_
@@ -1449,11 +1955,7 @@ impl AnkhaExpression {
                 panic!("Cannot borrow field from owned stack value!");
             },
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of ref stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_.find_field(query.compile()).unwrap_or_else(|| {
@@ -1493,11 +1995,7 @@ impl AnkhaExpression {
                 });
             },
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of ref mut stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_.find_field(query.compile()).unwrap_or_else(|| {
@@ -1537,11 +2035,7 @@ impl AnkhaExpression {
                 });
             },
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of lazy stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_.find_field(query.compile()).unwrap_or_else(|| {
@@ -1581,11 +2075,9 @@ impl AnkhaExpression {
                 });
             },
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: value.type_hash(),
-                        ..Default::default()
-                    })
+                let type_ = value
+                    .type_hash()
+                    .and_then(|type_hash| find_value_type(registry, type_hash))
                     .expect("Could not find type of box stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_.find_field(query.compile()).unwrap_or_else(|| {
@@ -1641,11 +2133,7 @@ impl AnkhaExpression {
                 panic!("Cannot borrow mutably field from ref stack value!");
             },
             |context, mut value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of ref mut stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_.find_field(query.compile()).unwrap_or_else(|| {
@@ -1685,11 +2173,7 @@ impl AnkhaExpression {
                 });
             },
             |context, mut value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of lazy stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_.find_field(query.compile()).unwrap_or_else(|| {
@@ -1729,11 +2213,9 @@ impl AnkhaExpression {
                 });
             },
             |context, mut value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: value.type_hash(),
-                        ..Default::default()
-                    })
+                let type_ = value
+                    .type_hash()
+                    .and_then(|type_hash| find_value_type(registry, type_hash))
                     .expect("Could not find type of box stack value!");
                 let field = if let Some(type_) = type_.as_struct() {
                     type_.find_field(query.compile()).unwrap_or_else(|| {
@@ -1782,11 +2264,7 @@ impl AnkhaExpression {
                 panic!("Cannot copy owned value!");
             },
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of ref stack value!");
                 if type_.is_copy() {
                     unsafe {
@@ -1822,11 +2300,7 @@ impl AnkhaExpression {
                 }
             },
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of ref mut stack value!");
                 if type_.is_copy() {
                     unsafe {
@@ -1862,11 +2336,7 @@ impl AnkhaExpression {
                 }
             },
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of lazy stack value!");
                 if type_.is_copy() {
                     unsafe {
@@ -1902,11 +2372,9 @@ impl AnkhaExpression {
                 }
             },
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: value.type_hash(),
-                        ..Default::default()
-                    })
+                let type_ = value
+                    .type_hash()
+                    .and_then(|type_hash| find_value_type(registry, type_hash))
                     .expect("Could not find type of box stack value!");
                 if type_.is_copy() {
                     unsafe {
@@ -1944,7 +2412,7 @@ impl AnkhaExpression {
         );
     }
 
-    fn move_into(context: &mut Context) {
+    fn move_into(context: &mut Context, registry: &Registry) {
         let value = context
             .stack()
             .pop::<DynamicManaged>()
@@ -1953,11 +2421,21 @@ impl AnkhaExpression {
             .stack()
             .peek()
             .expect("Could not pop value from stack to borrow!");
+        let ensure_type = |target: TypeHash| {
+            if *value.type_hash() != target {
+                panic!(
+                    "Cannot move value of `{}` type into target of `{}` type!",
+                    Self::type_name(registry, Some(*value.type_hash())),
+                    Self::type_name(registry, Some(target))
+                );
+            }
+        };
         if type_hash == TypeHash::of::<DynamicManagedRefMut>() {
             let target = context
                 .stack()
                 .pop::<DynamicManagedRefMut>()
                 .expect("Could not pop ref mut value from stack!");
+            ensure_type(*target.type_hash());
             value
                 .move_into_ref(target)
                 .ok()
@@ -1967,6 +2445,7 @@ impl AnkhaExpression {
                 .stack()
                 .pop::<DynamicManagedLazy>()
                 .expect("Could not pop lazy value from stack!");
+            ensure_type(*target.type_hash());
             value
                 .move_into_lazy(target)
                 .ok()
@@ -2045,18 +2524,158 @@ impl AnkhaExpression {
         }
     }
 
+    // Moves managed field value onto stack, leaving empty value in its place.
+    unsafe fn push_managed_field(
+        context: &mut Context,
+        type_hash: TypeHash,
+        owner_type_hash: TypeHash,
+        pointer: *mut u8,
+    ) {
+        unsafe {
+            if type_hash == TypeHash::of::<DynamicManaged>() {
+                context.stack().push(
+                    pointer.cast::<DynamicManaged>().replace(
+                        DynamicManaged::new(()).expect("Could not create managed object!"),
+                    ),
+                );
+            } else if type_hash == TypeHash::of::<DynamicManagedRef>() {
+                let lifetime = Lifetime::default();
+                context.stack().push(
+                    pointer.cast::<DynamicManagedRef>().replace(
+                        DynamicManagedRef::new_raw(
+                            owner_type_hash,
+                            lifetime.borrow().unwrap(),
+                            std::ptr::null(),
+                        )
+                        .expect("Could not create managed object ref!"),
+                    ),
+                );
+            } else if type_hash == TypeHash::of::<DynamicManagedRefMut>() {
+                let lifetime = Lifetime::default();
+                context.stack().push(
+                    pointer.cast::<DynamicManagedRefMut>().replace(
+                        DynamicManagedRefMut::new_raw(
+                            owner_type_hash,
+                            lifetime.borrow_mut().unwrap(),
+                            std::ptr::null_mut(),
+                        )
+                        .expect("Could not create managed object ref mut!"),
+                    ),
+                );
+            } else if type_hash == TypeHash::of::<DynamicManagedLazy>() {
+                let lifetime = Lifetime::default();
+                context.stack().push(
+                    pointer.cast::<DynamicManagedLazy>().replace(
+                        DynamicManagedLazy::new_raw(
+                            owner_type_hash,
+                            lifetime.lazy(),
+                            std::ptr::null_mut(),
+                        )
+                        .expect("Could not create managed object ref mut!"),
+                    ),
+                );
+            } else if type_hash == TypeHash::of::<DynamicManagedBox>() {
+                context.stack().push(
+                    pointer
+                        .cast::<DynamicManagedBox>()
+                        .replace(DynamicManagedBox::new(())),
+                );
+            }
+        }
+    }
+
+    // Moves managed value out of field memory, leaving that memory uninitialized.
+    unsafe fn take_managed_field(context: &mut Context, type_hash: TypeHash, pointer: *mut u8) {
+        unsafe {
+            if type_hash == TypeHash::of::<DynamicManaged>() {
+                context
+                    .stack()
+                    .push(pointer.cast::<DynamicManaged>().read());
+            } else if type_hash == TypeHash::of::<DynamicManagedRef>() {
+                context
+                    .stack()
+                    .push(pointer.cast::<DynamicManagedRef>().read());
+            } else if type_hash == TypeHash::of::<DynamicManagedRefMut>() {
+                context
+                    .stack()
+                    .push(pointer.cast::<DynamicManagedRefMut>().read());
+            } else if type_hash == TypeHash::of::<DynamicManagedLazy>() {
+                context
+                    .stack()
+                    .push(pointer.cast::<DynamicManagedLazy>().read());
+            } else if type_hash == TypeHash::of::<DynamicManagedBox>() {
+                context
+                    .stack()
+                    .push(pointer.cast::<DynamicManagedBox>().read());
+            }
+        }
+    }
+
+    // Moves top stack managed value into field memory, dropping its previous value
+    // only when memory is initialized.
+    unsafe fn pop_managed_field(
+        context: &mut Context,
+        type_hash: TypeHash,
+        pointer: *mut u8,
+        initialized: bool,
+    ) {
+        unsafe fn write<T: 'static>(context: &mut Context, pointer: *mut u8, initialized: bool) {
+            let value = context
+                .stack()
+                .pop::<T>()
+                .unwrap_or_else(|| panic!("Value on stack is not {}!", std::any::type_name::<T>()));
+            let pointer = pointer.cast::<T>();
+            unsafe {
+                if initialized {
+                    pointer.replace(value);
+                } else {
+                    pointer.write(value);
+                }
+            }
+        }
+
+        unsafe {
+            if type_hash == TypeHash::of::<DynamicManaged>() {
+                write::<DynamicManaged>(context, pointer, initialized);
+            } else if type_hash == TypeHash::of::<DynamicManagedRef>() {
+                write::<DynamicManagedRef>(context, pointer, initialized);
+            } else if type_hash == TypeHash::of::<DynamicManagedRefMut>() {
+                write::<DynamicManagedRefMut>(context, pointer, initialized);
+            } else if type_hash == TypeHash::of::<DynamicManagedLazy>() {
+                write::<DynamicManagedLazy>(context, pointer, initialized);
+            } else if type_hash == TypeHash::of::<DynamicManagedBox>() {
+                write::<DynamicManagedBox>(context, pointer, initialized);
+            }
+        }
+    }
+
+    fn ensure_field_type(
+        context: &mut Context,
+        registry: &Registry,
+        field: &StructField,
+        owner: &str,
+    ) {
+        if let Some(type_query) = declared_type(field.meta.as_ref()) {
+            let value = Self::pop_managed(context);
+            Self::ensure_declared_type(registry, value.type_hash(), &type_query, || {
+                format!("Field `{}` of `{}`", field.name, owner)
+            });
+            Self::push_managed(context, value);
+        }
+    }
+
     fn destructure(context: &mut Context, registry: &Registry, fields: &[String]) {
         stack_managed_variant(
             context,
             |context, value| {
-                let type_ = registry
-                    .find_type(TypeQuery {
-                        type_hash: Some(*value.type_hash()),
-                        ..Default::default()
-                    })
+                let type_ = find_value_type(registry, *value.type_hash())
                     .expect("Could not find type of owned value!");
                 if let Some(struct_type) = type_.as_struct() {
                     let pointer = unsafe { value.as_ptr_raw().cast_mut() };
+                    // Runtime struct finalizer does not drop its fields, so fields are
+                    // moved out of it and the ones left out of destructure are dropped
+                    // here. Native struct drops all fields, so they get placeholders.
+                    let is_runtime = struct_type.is_runtime();
                     for field in fields.iter().rev() {
                         let field = struct_type
                             .find_field(StructFieldQuery {
@@ -2069,61 +2688,32 @@ impl AnkhaExpression {
                                     field, struct_type.name
                                 )
                             });
-                        let pointer = unsafe { pointer.add(field.address_offset()) };
                         let type_hash = field.type_handle().type_hash();
+                        let pointer = unsafe { pointer.add(field.address_offset()) };
                         unsafe {
-                            if type_hash == TypeHash::of::<DynamicManaged>() {
-                                context.stack().push(
-                                    pointer.cast::<DynamicManaged>().replace(
-                                        DynamicManaged::new(())
-                                            .expect("Could not create managed object!"),
-                                    ),
-                                );
-                            } else if type_hash == TypeHash::of::<DynamicManagedRef>() {
-                                let lifetime = Lifetime::default();
-                                context.stack().push(
-                                    pointer.cast::<DynamicManagedRef>().replace(
-                                        DynamicManagedRef::new_raw(
-                                            *value.type_hash(),
-                                            lifetime.borrow().unwrap(),
-                                            std::ptr::null(),
-                                        )
-                                        .expect("Could not create managed object ref!"),
-                                    ),
-                                );
-                            } else if type_hash == TypeHash::of::<DynamicManagedRefMut>() {
-                                let lifetime = Lifetime::default();
-                                context.stack().push(
-                                    pointer.cast::<DynamicManagedRefMut>().replace(
-                                        DynamicManagedRefMut::new_raw(
-                                            *value.type_hash(),
-                                            lifetime.borrow_mut().unwrap(),
-                                            std::ptr::null_mut(),
-                                        )
-                                        .expect("Could not create managed object ref mut!"),
-                                    ),
-                                );
-                            } else if type_hash == TypeHash::of::<DynamicManagedLazy>() {
-                                let lifetime = Lifetime::default();
-                                context.stack().push(
-                                    pointer.cast::<DynamicManagedLazy>().replace(
-                                        DynamicManagedLazy::new_raw(
-                                            *value.type_hash(),
-                                            lifetime.lazy(),
-                                            std::ptr::null_mut(),
-                                        )
-                                        .expect("Could not create managed object ref mut!"),
-                                    ),
-                                );
-                            } else if type_hash == TypeHash::of::<DynamicManagedBox>() {
-                                context.stack().push(
-                                    pointer
-                                        .cast::<DynamicManagedBox>()
-                                        .replace(DynamicManagedBox::new(())),
+                            if is_runtime {
+                                Self::take_managed_field(context, type_hash, pointer);
+                            } else {
+                                Self::push_managed_field(
+                                    context,
+                                    type_hash,
+                                    *value.type_hash(),
+                                    pointer,
                                 );
                             }
                         }
                     }
+                    if is_runtime {
+                        for field in struct_type.fields() {
+                            if !fields.contains(&field.name) {
+                                unsafe {
+                                    field
+                                        .type_handle()
+                                        .finalize(pointer.add(field.address_offset()).cast::<()>());
+                                }
+                            }
+                        }
+                    }
                 } else {
                     panic!("`{}` is not a struct!", type_.name());
                 }
@@ -2153,13 +2743,25 @@ impl AnkhaExpression {
             .find_type(type_query.compile())
             .unwrap_or_else(|| panic!("Could not find `{}` type!", type_query));
         if let Some(struct_type) = type_.as_struct() {
+            let mut defaults = vec![];
             if !struct_type.can_initialize() || struct_type.is_runtime() {
                 for field in struct_type.fields() {
                     if !fields.contains(&field.name) {
-                        panic!(
-                            "Field `{}` of `{}` type must be initialized!",
-                            field.name, struct_type.name
-                        );
+                        let name = default_function_name(&field.name);
+                        let default = registry
+                            .find_function(FunctionQuery {
+                                name: Some(name.as_str().into()),
+                                type_query: Some(type_handle_query(&type_)),
+                                meta: Some(is_default_meta),
+                                ..Default::default()
+                            })
+                            .unwrap_or_else(|| {
+                                panic!(
+                                    "Field `{}` of `{}` type must be initialized!",
+                                    field.name, struct_type.name
+                                )
+                            });
+                        defaults.push((field, default));
                     }
                 }
             }
@@ -2185,74 +2787,26 @@ impl AnkhaExpression {
                                 field, struct_type.name
                             )
                         });
-                    let type_hash = field.type_handle().type_hash();
-                    if type_hash == TypeHash::of::<DynamicManaged>() {
-                        let value = context
-                            .stack()
-                            .pop::<DynamicManaged>()
-                            .expect("Value on stack is not owned managed!");
-                        let pointer = memory.add(field.address_offset()).cast::<DynamicManaged>();
-                        if field.type_handle().can_initialize() {
-                            pointer.replace(value);
-                        } else {
-                            pointer.write(value);
-                        }
-                    } else if type_hash == TypeHash::of::<DynamicManagedRef>() {
-                        let value = context
-                            .stack()
-                            .pop::<DynamicManagedRef>()
-                            .expect("Value on stack is not ref managed!");
-                        let pointer = memory
-                            .add(field.address_offset())
-                            .cast::<DynamicManagedRef>();
-                        if field.type_handle().can_initialize() {
-                            pointer.replace(value);
-                        } else {
-                            pointer.write(value);
-                        }
-                    } else if type_hash == TypeHash::of::<DynamicManagedRefMut>() {
-                        let value = context
-                            .stack()
-                            .pop::<DynamicManagedRefMut>()
-                            .expect("Value on stack is not ref mut managed!");
-                        let pointer = memory
-                            .add(field.address_offset())
-                            .cast::<DynamicManagedRefMut>();
-                        if field.type_handle().can_initialize() {
-                            pointer.replace(value);
-                        } else {
-                            pointer.write(value);
-                        }
-                    } else if type_hash == TypeHash::of::<DynamicManagedLazy>() {
-                        let value = context
-                            .stack()
-                            .pop::<DynamicManagedLazy>()
-                            .expect("Value on stack is not lazy managed!");
-                        let pointer = memory
-                            .add(field.address_offset())
-                            .cast::<DynamicManagedLazy>();
-                        if field.type_handle().can_initialize() {
-                            pointer.replace(value);
-                        } else {
-                            pointer.write(value);
-                        }
-                    } else if type_hash == TypeHash::of::<DynamicManagedBox>() {
-                        let value = context
-                            .stack()
-                            .pop::<DynamicManagedBox>()
-                            .expect("Value on stack is not box managed!");
-                        let pointer = memory
-                            .add(field.address_offset())
-                            .cast::<DynamicManagedBox>();
-                        if field.type_handle().can_initialize() {
-                            pointer.replace(value);
-                        } else {
-                            pointer.write(value);
-                        }
-                    }
+                    Self::ensure_field_type(context, registry, field, &struct_type.name);
+                    Self::pop_managed_field(
+                        context,
+                        field.type_handle().type_hash(),
+                        memory.add(field.address_offset()),
+                        field.type_handle().can_initialize(),
+                    );
+                }
+                for (field, default) in defaults {
+                    default.invoke(context, registry);
+                    Self::ensure_field_type(context, registry, field, &struct_type.name);
+                    Self::pop_managed_field(
+                        context,
+                        field.type_handle().type_hash(),
+                        memory.add(field.address_offset()),
+                        field.type_handle().can_initialize(),
+                    );
                 }
                 let result = DynamicManaged::new_raw(
-                    struct_type.type_hash(),
+                    type_identity(&type_),
                     Lifetime::default(),
                     memory,
                     *struct_type.layout(),
@@ -2268,6 +2822,175 @@ impl AnkhaExpression {
         }
     }
 
+    fn variant(
+        context: &mut Context,
+        registry: &Registry,
+        type_query: &AnkhaTypeQuery,
+        variant: &str,
+        fields: &[String],
+    ) {
+        let type_ = registry
+            .find_type(type_query.compile())
+            .unwrap_or_else(|| panic!("Could not find `{}` type!", type_query));
+        let Some(enum_type) = type_.as_enum() else {
+            panic!("Type `{}` is not an enum!", type_query);
+        };
+        let variant = enum_type
+            .find_variant(EnumVariantQuery {
+                name: Some(variant.into()),
+                ..Default::default()
+            })
+            .unwrap_or_else(|| {
+                panic!(
+                    "Could not find `{}` variant in `{}` type!",
+                    variant, enum_type.name
+                )
+            });
+        for field in &variant.fields {
+            if !fields.contains(&field.name) {
+                panic!(
+                    "Field `{}` of `{}::{}` variant must be initialized!",
+                    field.name, enum_type.name, variant.name
+                );
+            }
+        }
+        unsafe {
+            let memory = Object::new_uninitialized(type_.clone())
+                .unwrap_or_else(|| panic!("Could not create object of `{}` type!", enum_type.name))
+                .into_inner()
+                .1;
+            memory.write(variant.discriminant());
+            for field in fields.iter() {
+                let field = variant
+                    .find_field(StructFieldQuery {
+                        name: Some(field.into()),
+                        ..Default::default()
+                    })
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "Could not find `{}` field in `{}::{}` variant!",
+                            field, enum_type.name, variant.name
+                        )
+                    });
+                Self::ensure_field_type(
+                    context,
+                    registry,
+                    field,
+                    &format!("{}::{}", enum_type.name, variant.name),
+                );
+                Self::pop_managed_field(
+                    context,
+                    field.type_handle().type_hash(),
+                    memory.add(field.address_offset()),
+                    false,
+                );
+            }
+            let result = DynamicManaged::new_raw(
+                type_identity(&type_),
+                Lifetime::default(),
+                memory,
+                *enum_type.layout(),
+                enum_type.finalizer(),
+            )
+            .unwrap_or_else(|| panic!("Could not create managed object of `{}` type!", type_query));
+            context.stack().push(result);
+        }
+    }
+
+    fn match_variant(context: &mut Context, registry: &Registry, variant: &str) {
+        let value = context
+            .stack()
+            .pop::<DynamicManaged>()
+            .expect("Could not pop owned value to match!");
+        let type_ = find_value_type(registry, *value.type_hash())
+            .expect("Could not find type of owned value!");
+        let Some(enum_type) = type_.as_enum() else {
+            panic!("`{}` is not an enum!", type_.name());
+        };
+        let found = enum_type
+            .find_variant(EnumVariantQuery {
+                name: Some(variant.into()),
+                ..Default::default()
+            })
+            .unwrap_or_else(|| {
+                panic!(
+                    "Could not find `{}` variant in `{}` type!",
+                    variant, enum_type.name
+                )
+            });
+        let pointer = unsafe { value.as_ptr_raw().cast_mut() };
+        if unsafe { pointer.read() } == found.discriminant() {
+            // Runtime enum finalizer does not drop fields, so they are moved out of it.
+            for field in found.fields.iter().rev() {
+                let type_hash = field.type_handle().type_hash();
+                unsafe {
+                    let pointer = pointer.add(field.address_offset());
+                    if enum_type.is_runtime() {
+                        Self::take_managed_field(context, type_hash, pointer);
+                    } else {
+                        Self::push_managed_field(context, type_hash, *value.type_hash(), pointer);
+                    }
+                }
+            }
+            context.stack().push(true);
+        } else {
+            context.stack().push(value);
+            context.stack().push(false);
+        }
+    }
+
+    fn match_failure(context: &mut Context, registry: &Registry) {
+        let value = context
+            .stack()
+            .pop::<DynamicManaged>()
+            .expect("Could not pop owned value to match!");
+        let type_ = find_value_type(registry, *value.type_hash())
+            .expect("Could not find type of owned value!");
+        let discriminant = unsafe { value.as_ptr_raw().read() };
+        let variant = type_
+            .as_enum()
+            .and_then(|enum_type| enum_type.find_variant_by_discriminant(discriminant))
+            .map(|variant| variant.name.as_str())
+            .unwrap_or("?");
+        panic!(
+            "There is no case matching `{}` variant of `{}` type!",
+            variant,
+            type_.name()
+        );
+    }
+
+    fn cast(
+        context: &mut Context,
+        registry: &Registry,
+        type_query: &AnkhaTypeQuery,
+        mode: AnkhaCastMode,
+    ) {
+        let type_hash = registry
+            .find_type(type_query.compile())
+            .unwrap_or_else(|| panic!("Could not find `{}` type!", type_query))
+            .type_hash();
+        let value = context
+            .stack()
+            .pop::<DynamicManaged>()
+            .expect("Value on stack is not managed owned value!");
+        let literal = AnkhaOption::Owned(value)
+            .into_literal()
+            .expect("Only primitive and String values can be cast!")
+            .cast(type_hash, mode);
+        if mode == AnkhaCastMode::Checked {
+            let result = literal.map(AnkhaOption::from_literal).unwrap_or_default();
+            context.stack().push(
+                DynamicManaged::new(result)
+                    .ok()
+                    .expect("Could not create managed option value!"),
+            );
+        } else {
+            literal
+                .expect("Unchecked cast cannot fail!")
+                .evaluate(context);
+        }
+    }
+
     fn box_(context: &mut Context) {
         let value = context
             .stack()
@@ -2324,11 +3047,7 @@ impl AnkhaExpression {
             .stack()
             .pop::<DynamicManaged>()
             .expect("Could not pop owned value from stack!");
-        let type_ = registry
-            .find_type(TypeQuery {
-                type_hash: Some(*value.type_hash()),
-                ..Default::default()
-            })
+        let type_ = find_value_type(registry, *value.type_hash())
             .expect("Could not find type of owned stack value!");
         if type_.is_copy() {
             unsafe {
@@ -2389,10 +3108,11 @@ impl AnkhaExpression {
     }
 
     fn ensure_stack_type(context: &mut Context, registry: &Registry, type_query: &AnkhaTypeQuery) {
-        let expected = registry
-            .find_type(type_query.compile())
-            .unwrap_or_else(|| panic!("Could not find `{}` type!", type_query))
-            .type_hash();
+        let expected = type_identity(
+            &registry
+                .find_type(type_query.compile())
+                .unwrap_or_else(|| panic!("Could not find `{}` type!", type_query)),
+        );
         stack_managed_variant(
             context,
             |context, value| {
@@ -2428,16 +3148,98 @@ impl AnkhaExpression {
         );
     }
 
+    fn pop_managed(context: &mut Context) -> AnkhaOption {
+        stack_managed_variant(
+            context,
+            |_, value| AnkhaOption::Owned(value),
+            |_, value| AnkhaOption::Ref(value),
+            |_, value| AnkhaOption::RefMut(value),
+            |_, value| AnkhaOption::Lazy(value),
+            |_, value| AnkhaOption::Box(value),
+        )
+    }
+
+    fn push_managed(context: &mut Context, value: AnkhaOption) {
+        match value {
+            AnkhaOption::None => {}
+            AnkhaOption::Owned(value) => {
+                context.stack().push(value);
+            }
+            AnkhaOption::Ref(value) => {
+                context.stack().push(value);
+            }
+            AnkhaOption::RefMut(value) => {
+                context.stack().push(value);
+            }
+            AnkhaOption::Lazy(value) => {
+                context.stack().push(value);
+            }
+            AnkhaOption::Box(value) => {
+                context.stack().push(value);
+            }
+        }
+    }
+
+    fn type_name(registry: &Registry, type_hash: Option<TypeHash>) -> String {
+        type_hash
+            .and_then(|type_hash| find_value_type(registry, type_hash))
+            .map(|type_| type_.name().to_owned())
+            .unwrap_or_else(|| "?".to_owned())
+    }
+
+    fn ensure_declared_type(
+        registry: &Registry,
+        type_hash: Option<TypeHash>,
+        type_query: &AnkhaTypeQuery,
+        subject: impl FnOnce() -> String,
+    ) {
+        let expected = registry
+            .find_type(type_query.compile())
+            .unwrap_or_else(|| panic!("Could not find `{}` type!", type_query));
+        if type_hash != Some(type_identity(&expected)) {
+            panic!(
+                "{} expects value of `{}` type, but got `{}`!",
+                subject(),
+                expected.name(),
+                Self::type_name(registry, type_hash)
+            );
+        }
+    }
+
+    fn ensure_input_types(
+        context: &mut Context,
+        registry: &Registry,
+        function: &str,
+        inputs: &[AnkhaFunctionParameter],
+    ) {
+        let values = inputs
+            .iter()
+            .map(|input| {
+                let value = Self::pop_managed(context);
+                if let Some(type_query) = input.type_query.as_ref() {
+                    Self::ensure_declared_type(registry, value.type_hash(), type_query, || {
+                        format!("Input `{}` of `{}` function", input.name, function)
+                    });
+                }
+                value
+            })
+            .collect::<Vec<_>>();
+        for value in values.into_iter().rev() {
+            Self::push_managed(context, value);
+        }
+    }
+
     fn ensure_register_type(
         context: &mut Context,
         registry: &Registry,
         type_query: &AnkhaTypeQuery,
         index: usize,
     ) {
-        let expected = registry
-            .find_type(type_query.compile())
-            .unwrap_or_else(|| panic!("Could not find `{}` type!", type_query))
-            .type_hash();
+        let expected = type_identity(
+            &registry
+                .find_type(type_query.compile())
+                .unwrap_or_else(|| panic!("Could not find `{}` type!", type_query)),
+        );
         register_managed_variant(
             context,
             index,
@@ -2518,6 +3320,7 @@ impl AnkhaExpression {
         context: &mut Context,
         registry: &Registry,
         function_query: &AnkhaFunctionQuery,
+        direct: bool,
     ) {
         let type_hash = stack_managed_variant(
             context,
@@ -2547,19 +3350,23 @@ impl AnkhaExpression {
                 result
             },
         );
-        let mut query = function_query.compile();
-        query.type_query = Some(TypeQuery {
-            type_hash: Some(type_hash),
-            ..Default::default()
+        let type_ = find_value_type(registry, type_hash).unwrap_or_else(|| {
+            panic!(
+                "Could not find type of method `{}` receiver!",
+                function_query
+            )
         });
+        let mut query = function_query.compile();
+        query.type_query = Some(type_handle_query(&type_));
         let handle = registry
             .functions()
             .find(|handle| query.is_valid(handle.signature()))
             .unwrap_or_else(|| panic!("Could not call non-existent function: {:#?}", query));
-        handle.invoke(context, registry);
+        AnkhaCapabilities::ensure_function_allowed(context, handle);
+        AnkhaTaskCall::invoke(handle, direct, context, registry);
     }
 
-    fn call_indirect(context: &mut Context, registry: &Registry) {
+    fn call_indirect(context: &mut Context, registry: &Registry, direct: bool) {
         let handle = stack_managed_variant(
             context,
             |_, value| {
@@ -2598,7 +3405,8 @@ impl AnkhaExpression {
                     .clone()
             },
         );
-        handle.invoke(context, registry);
+        AnkhaCapabilities::ensure_function_allowed(context, &handle);
+        AnkhaTaskCall::invoke(&handle, direct, context, registry);
     }
 
     fn get_type(context: &mut Context, registry: &Registry, query: &AnkhaTypeQuery) {
@@ -2612,12 +3420,106 @@ impl AnkhaExpression {
         let handle = registry
             .find_function(query.compile())
             .unwrap_or_else(|| panic!("Could not find `{}` type!", query));
+        AnkhaCapabilities::ensure_function_allowed(context, &handle);
         context.stack().push(Function(handle));
     }
+
+    fn get_constant(
+        context: &mut Context,
+        registry: &Registry,
+        name: &str,
+        module_name: Option<&str>,
+    ) {
+        let handle = registry
+            .find_function(accessor_query(
+                is_constant_meta,
+                constant_function_name(name),
+                module_name,
+            ))
+            .unwrap_or_else(|| panic!("Could not find `{}` constant!", name));
+        handle.invoke(context, registry);
+    }
+
+    fn get_global(
+        context: &mut Context,
+        registry: &Registry,
+        name: &str,
+        module_name: Option<&str>,
+        kind: AnkhaValueKind,
+    ) {
+        let handle = registry
+            .find_function(accessor_query(
+                is_global_meta,
+                global_function_name(name),
+                module_name,
+            ))
+            .unwrap_or_else(|| panic!("Could not find `{}` global!", name));
+        handle.invoke(context, registry);
+        let mut value = context
+            .stack()
+            .pop::<DynamicManagedLazy>()
+            .expect("Global value on stack is not lazy!");
+        match kind {
+            AnkhaValueKind::Ref => {
+                let value = value
+                    .borrow()
+                    .unwrap_or_else(|| panic!("Global `{}` cannot be borrowed!", name));
+                context.stack().push(value);
+            }
+            AnkhaValueKind::RefMut => {
+                let value = value
+                    .borrow_mut()
+                    .unwrap_or_else(|| panic!("Global `{}` cannot be borrowed mutably!", name));
+                context.stack().push(value);
+            }
+            AnkhaValueKind::Lazy => {
+                context.stack().push(value);
+            }
+            _ => panic!(
+                "Global `{}` can be accessed only as ref, ref mut or lazy!",
+                name
+            ),
+        }
+    }
+}
+
+impl AnkhaExpression {
+    fn loop_jump_check(context: &mut Context, label: Option<&str>, restore_registers: bool) {
+        let Some(jump) = AnkhaJump::pending(context) else {
+            context.stack().push(true);
+            return;
+        };
+        let repeat = match jump {
+            AnkhaJump::Continue(target) if AnkhaJump::targets(target, label) => true,
+            AnkhaJump::Break(target) if AnkhaJump::targets(target, label) => false,
+            _ => {
+                if restore_registers {
+                    context.restore_registers();
+                }
+                context.stack().push(false);
+                context.stack().push(false);
+                return;
+            }
+        };
+        AnkhaJump::finish(context);
+        if restore_registers {
+            context.restore_registers();
+        }
+        context.stack().push(repeat);
+        context.stack().push(false);
+    }
 }
 
 impl ScriptExpression for AnkhaExpression {
     fn evaluate(&self, context: &mut Context, registry: &Registry) {
+        // Task steps run only with budget, so without one there is nothing to track.
+        let direct = AnkhaFuel::is_metered(context) && {
+            // Guarded expression is paid for by the one it wraps.
+            if !matches!(self, Self::Guarded(_)) {
+                AnkhaFuel::consume(context);
+            }
+            AnkhaTaskState::take_step(context)
+        };
         match self {
             Self::Literal(literal) => {
                 literal.evaluate(context);
@@ -2661,7 +3563,7 @@ impl ScriptExpression for AnkhaExpression {
                 Self::copy_from(context, registry);
             }
             Self::MoveInto => {
-                Self::move_into(context);
+                Self::move_into(context, registry);
             }
             Self::SwapIn => {
                 Self::swap_in(context);
@@ -2672,6 +3574,22 @@ impl ScriptExpression for AnkhaExpression {
             Self::Structure { type_query, fields } => {
                 Self::structure(context, registry, type_query, fields);
             }
+            Self::Variant {
+                type_query,
+                variant,
+                fields,
+            } => {
+                Self::variant(context, registry, type_query, variant, fields);
+            }
+            Self::MatchVariant { variant } => {
+                Self::match_variant(context, registry, variant);
+            }
+            Self::MatchFailure => {
+                Self::match_failure(context, registry);
+            }
+            Self::Cast { type_query, mode } => {
+                Self::cast(context, registry, type_query, *mode);
+            }
             Self::Box => {
                 Self::box_(context);
             }
@@ -2703,10 +3621,10 @@ impl ScriptExpression for AnkhaExpression {
                 Self::ensure_register_kind(context, *kind, *index);
             }
             Self::CallMethod { function_query } => {
-                Self::call_method(context, registry, function_query);
+                Self::call_method(context, registry, function_query, direct);
             }
             Self::CallIndirect => {
-                Self::call_indirect(context, registry);
+                Self::call_indirect(context, registry, direct);
             }
             Self::GetType { query } => {
                 Self::get_type(context, registry, query);
@@ -2714,6 +3632,105 @@ impl ScriptExpression for AnkhaExpression {
             Self::GetFunction { query } => {
                 Self::get_function(context, registry, query);
             }
+            Self::GetConstant { name, module_name } => {
+                Self::get_constant(context, registry, name, module_name.as_deref());
+            }
+            Self::GetGlobal {
+                name,
+                module_name,
+                kind,
+            } => {
+                Self::get_global(context, registry, name, module_name.as_deref(), *kind);
+            }
+            Self::EnsureInputTypes { function, inputs } => {
+                Self::ensure_input_types(context, registry, function, inputs);
+            }
+            Self::PushCallFrame { name } => {
+                AnkhaCallStack::push(context, name);
+            }
+            Self::PopCallFrame => {
+                AnkhaCallStack::pop(context);
+            }
+            Self::TailCallLoop { repeat } => {
+                if *repeat {
+                    context.restore_registers();
+                    context.store_registers();
+                }
+                context.stack().push(*repeat);
+            }
+            Self::Jump(jump) => {
+                jump.to_owned().start(context);
+            }
+            Self::Throw => {
+                let value = context
+                    .stack()
+                    .pop::<DynamicManaged>()
+                    .expect("Could not pop owned value to throw!");
+                resume_unwind(Box::new(AnkhaError::Thrown {
+                    value,
+                    backtrace: vec![],
+                }));
+            }
+            Self::CallFunction { function_query } => {
+                let query = function_query.compile();
+                let handle = registry
+                    .functions()
+                    .find(|handle| query.is_valid(handle.signature()))
+                    .unwrap_or_else(|| {
+                        panic!("Could not call non-existent function: {:#?}", query)
+                    });
+                AnkhaCapabilities::ensure_function_allowed(context, handle);
+                AnkhaTaskCall::invoke(handle, direct, context, registry);
+            }
+            Self::Guarded(expression) => {
+                let tries = AnkhaTry::count(context);
+                AnkhaTaskState::set_step(context, direct);
+                let result = catch_panic(|| {
+                    AnkhaCallStack::guard(context, |context| expression.evaluate(context, registry))
+                });
+                match result {
+                    // Errors of call deferred to task are caught by task for this try scope.
+                    Ok(()) => AnkhaTaskCall::guard(context, tries),
+                    Err(payload) => {
+                        // Exhausted budget has to reach task that runs the script.
+                        if AnkhaFuel::exhausted(context) {
+                            resume_unwind(payload);
+                        }
+                        AnkhaTry::throw(context, tries, AnkhaError::from_payload(payload, vec![]));
+                    }
+                }
+            }
+            Self::TryEnter => {
+                AnkhaTry::enter(context);
+                context.stack().push(true);
+            }
+            Self::TryExit => match AnkhaTry::exit(context) {
+                Some(error) => {
+                    context.stack().push(error.into_value());
+                    context.stack().push(true);
+                }
+                None => {
+                    context.stack().push(false);
+                }
+            },
+            Self::FinishReturn => {
+                if AnkhaJump::pending(context) == Some(&AnkhaJump::Return) {
+                    AnkhaJump::finish(context);
+                }
+            }
+            Self::JumpCheck { restore_registers } => {
+                let pending = AnkhaJump::pending(context).is_some();
+                if pending && *restore_registers {
+                    context.restore_registers();
+                }
+                context.stack().push(!pending);
+            }
+            Self::LoopJumpCheck {
+                label,
+                restore_registers,
+            } => {
+                Self::loop_jump_check(context, label.as_deref(), *restore_registers);
+            }
         }
     }
 }
@@ -2744,11 +3761,33 @@ pub enum AnkhaOperation {
     },
     LoopScope {
         script: AnkhaScript,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
     },
     PushScope {
         script: AnkhaScript,
     },
     PopScope,
+    // Leaves innermost or labeled loop.
+    Break(Option<String>),
+    // Starts next iteration of innermost or labeled loop.
+    Continue(Option<String>),
+    // Leaves function with its outputs already on stack.
+    Return,
+    // Pops owned value and throws it to nearest catch scope.
+    Throw,
+    // Runs catch script with caught error when try script fails.
+    TryScope {
+        script: AnkhaScript,
+        // Name of register that catch script gets error in, otherwise it is on stack.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        catch_name: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        script_catch: AnkhaScript,
+    },
+    // Runs script of case matching variant of top stack owned enum value,
+    // with that variant fields unpacked on stack.
+    Match(Vec<AnkhaMatchCase>),
     EnsureRegisterType {
         type_query: AnkhaTypeQuery,
         address: AnkhaRegisterAddress,
@@ -2759,25 +3798,49 @@ pub enum AnkhaOperation {
     },
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnkhaMatchCase {
+    pub variant: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub script: AnkhaScript,
+}
+
+impl AnkhaMatchCase {
+    // Chains cases into branches that try to match variants one after another.
+    fn branches(cases: &[Self]) -> AnkhaScript {
+        match cases.split_first() {
+            Some((case, rest)) => vec![
+                AnkhaOperation::Expression(AnkhaExpression::MatchVariant {
+                    variant: case.variant.to_owned(),
+                }),
+                AnkhaOperation::BranchScope {
+                    script_success: case.script.to_owned(),
+                    script_failure: Some(Self::branches(rest)),
+                },
+            ],
+            None => vec![AnkhaOperation::Expression(AnkhaExpression::MatchFailure)],
+        }
+    }
+}
+
 fn write_operation(
     operation: &AnkhaOperation,
     registers: &mut Vec<Option<String>>,
+    jumps: &AnkhaScopeJumps,
     result: &mut Vec<ScriptOperation<'static, AnkhaExpression>>,
 ) {
     match operation {
         AnkhaOperation::Expression(expression) => {
-            result.push(ScriptOperation::Expression {
-                expression: expression.to_owned(),
-            });
+            write_expression(expression.to_owned(), jumps, result);
         }
         AnkhaOperation::Group(operations) => {
             for operation in operations {
-                write_operation(operation, registers, result);
+                write_operation(operation, registers, jumps, result);
             }
         }
         AnkhaOperation::GroupReversed(operations) => {
             for operation in operations.iter().rev() {
-                write_operation(operation, registers, result);
+                write_operation(operation, registers, jumps, result);
             }
         }
         AnkhaOperation::MakeRegister { kind, name } => {
@@ -2856,47 +3919,122 @@ fn write_operation(
                 }
             });
         }
+        // Calls are expressions, so task can run called script function as its frame.
         AnkhaOperation::CallFunction(function_query) => {
-            result.push(ScriptOperation::CallFunction {
-                query: function_query.compile().to_static(),
-            });
+            write_expression(
+                AnkhaExpression::CallFunction {
+                    function_query: function_query.to_owned(),
+                },
+                jumps,
+                result,
+            );
         }
         AnkhaOperation::BranchScope {
             script_success: operations_success,
             script_failure: operations_failure,
         } => {
+            let nested = jumps.nested();
             result.push(ScriptOperation::BranchScope {
-                scope_success: build_script(operations_success),
-                scope_failure: operations_failure.as_ref().map(build_script),
+                scope_success: build_scope(operations_success, &nested),
+                scope_failure: operations_failure
+                    .as_ref()
+                    .map(|operations| build_scope(operations, &nested)),
             });
+            jumps.write_jump_check(false, result);
         }
-        AnkhaOperation::LoopScope { script: operations } => {
+        AnkhaOperation::LoopScope {
+            script: operations,
+            label,
+        } => {
             result.push(ScriptOperation::LoopScope {
-                scope: build_script(operations),
+                scope: build_scope(operations, &jumps.enter(label.to_owned())),
             });
+            jumps.write_jump_check(false, result);
         }
         AnkhaOperation::PushScope { script: operations } => {
             result.push(ScriptOperation::PushScope {
-                scope: build_script(operations),
+                scope: build_scope(operations, &jumps.nested()),
             });
+            jumps.write_jump_check(true, result);
         }
         AnkhaOperation::PopScope => {
             result.push(ScriptOperation::PopScope);
         }
-        AnkhaOperation::EnsureRegisterType {
-            type_query,
-            address,
+        AnkhaOperation::Break(label) => {
+            jumps.validate("Break", label.as_deref());
+            result.push(ScriptOperation::Expression {
+                expression: AnkhaExpression::Jump(AnkhaJump::Break(label.to_owned())),
+            });
+            jumps.write_jump_check(false, result);
+        }
+        AnkhaOperation::Continue(label) => {
+            jumps.validate("Continue", label.as_deref());
+            result.push(ScriptOperation::Expression {
+                expression: AnkhaExpression::Jump(AnkhaJump::Continue(label.to_owned())),
+            });
+            jumps.write_jump_check(false, result);
+        }
+        AnkhaOperation::Return => {
+            if !jumps.returns {
+                panic!("Return can be used only inside function!");
+            }
+            result.push(ScriptOperation::Expression {
+                expression: AnkhaExpression::Jump(AnkhaJump::Return),
+            });
+            jumps.write_jump_check(false, result);
+        }
+        AnkhaOperation::Throw => {
+            write_expression(AnkhaExpression::Throw, jumps, result);
+        }
+        AnkhaOperation::TryScope {
+            script,
+            catch_name,
+            script_catch,
         } => {
-            result.push(match address {
-                AnkhaRegisterAddress::Index(index) => ScriptOperation::Expression {
-                    expression: AnkhaExpression::EnsureRegisterType {
-                        type_query: type_query.to_owned(),
-                        index: *index,
-                    },
-                },
-                AnkhaRegisterAddress::Name(name) => {
-                    let index = registers
-                        .iter()
+            let mut operations = vec![];
+            if let Some(name) = catch_name {
+                operations.push(AnkhaOperation::MakeRegister {
+                    kind: AnkhaValueKind::Owned,
+                    name: Some(name.to_owned()),
+                });
+                operations.push(AnkhaOperation::PopToRegister(AnkhaRegisterAddress::Name(
+                    name.to_owned(),
+                )));
+            }
+            operations.extend(script_catch.iter().cloned());
+            result.push(ScriptOperation::Expression {
+                expression: AnkhaExpression::TryEnter,
+            });
+            result.push(ScriptOperation::BranchScope {
+                scope_success: build_scope(script, &jumps.guarded()),
+                scope_failure: None,
+            });
+            result.push(ScriptOperation::Expression {
+                expression: AnkhaExpression::TryExit,
+            });
+            result.push(ScriptOperation::BranchScope {
+                scope_success: build_scope(&operations, &jumps.nested()),
+                scope_failure: None,
+            });
+            jumps.write_jump_check(false, result);
+        }
+        AnkhaOperation::Match(cases) => {
+            for operation in AnkhaMatchCase::branches(cases) {
+                write_operation(&operation, registers, jumps, result);
+            }
+        }
+        AnkhaOperation::EnsureRegisterType {
+            type_query,
+            address,
+        } => {
+            let expression = match address {
+                AnkhaRegisterAddress::Index(index) => AnkhaExpression::EnsureRegisterType {
+                    type_query: type_query.to_owned(),
+                    index: *index,
+                },
+                AnkhaRegisterAddress::Name(name) => {
+                    let index = registers
+                        .iter()
                         .position(|register| {
                             register
                                 .as_deref()
@@ -2906,22 +4044,19 @@ fn write_operation(
                         .unwrap_or_else(|| {
                             panic!("There is no register with `{}` name in the scope!", name)
                         });
-                    ScriptOperation::Expression {
-                        expression: AnkhaExpression::EnsureRegisterType {
-                            type_query: type_query.to_owned(),
-                            index,
-                        },
+                    AnkhaExpression::EnsureRegisterType {
+                        type_query: type_query.to_owned(),
+                        index,
                     }
                 }
-            });
+            };
+            write_expression(expression, jumps, result);
         }
         AnkhaOperation::EnsureRegisterKind { kind, address } => {
-            result.push(match address {
-                AnkhaRegisterAddress::Index(index) => ScriptOperation::Expression {
-                    expression: AnkhaExpression::EnsureRegisterKind {
-                        kind: *kind,
-                        index: *index,
-                    },
+            let expression = match address {
+                AnkhaRegisterAddress::Index(index) => AnkhaExpression::EnsureRegisterKind {
+                    kind: *kind,
+                    index: *index,
                 },
                 AnkhaRegisterAddress::Name(name) => {
                     let index = registers
@@ -2935,24 +4070,539 @@ fn write_operation(
                         .unwrap_or_else(|| {
                             panic!("There is no register with `{}` name in the scope!", name)
                         });
-                    ScriptOperation::Expression {
-                        expression: AnkhaExpression::EnsureRegisterKind { kind: *kind, index },
-                    }
+                    AnkhaExpression::EnsureRegisterKind { kind: *kind, index }
                 }
-            });
+            };
+            write_expression(expression, jumps, result);
         }
     }
 }
 
+// Expressions that can fail inside try scope get guarded and followed by jump check,
+// so raised error skips rest of try body. Operations run by VM itself, like register
+// access, are not guarded.
+fn write_expression(
+    expression: AnkhaExpression,
+    jumps: &AnkhaScopeJumps,
+    result: &mut Vec<ScriptOperation<'static, AnkhaExpression>>,
+) {
+    let fallible = !matches!(
+        expression,
+        AnkhaExpression::Guarded(_)
+            | AnkhaExpression::TryEnter
+            | AnkhaExpression::TryExit
+            | AnkhaExpression::Jump(_)
+            | AnkhaExpression::FinishReturn
+            | AnkhaExpression::JumpCheck { .. }
+            | AnkhaExpression::LoopJumpCheck { .. }
+            | AnkhaExpression::TailCallLoop { .. }
+            | AnkhaExpression::PopCallFrame
+    );
+    if jumps.in_try && fallible {
+        result.push(ScriptOperation::Expression {
+            expression: AnkhaExpression::Guarded(Box::new(expression)),
+        });
+        jumps.write_jump_check(false, result);
+    } else {
+        result.push(ScriptOperation::Expression { expression });
+    }
+}
+
+// Constants and globals referenced without module name belong to module of the script,
+// so lookup does not depend on what other modules are installed.
+fn qualify_accessors(script: &AnkhaScript, module_name: &str) -> AnkhaScript {
+    let qualify = |script: &AnkhaScript| qualify_accessors(script, module_name);
+    script
+        .iter()
+        .map(|operation| match operation {
+            AnkhaOperation::Expression(AnkhaExpression::GetConstant {
+                name,
+                module_name: None,
+            }) => AnkhaOperation::Expression(AnkhaExpression::GetConstant {
+                name: name.to_owned(),
+                module_name: Some(module_name.to_owned()),
+            }),
+            AnkhaOperation::Expression(AnkhaExpression::GetGlobal {
+                name,
+                module_name: None,
+                kind,
+            }) => AnkhaOperation::Expression(AnkhaExpression::GetGlobal {
+                name: name.to_owned(),
+                module_name: Some(module_name.to_owned()),
+                kind: *kind,
+            }),
+            AnkhaOperation::Group(script) => AnkhaOperation::Group(qualify(script)),
+            AnkhaOperation::GroupReversed(script) => AnkhaOperation::GroupReversed(qualify(script)),
+            AnkhaOperation::BranchScope {
+                script_success,
+                script_failure,
+            } => AnkhaOperation::BranchScope {
+                script_success: qualify(script_success),
+                script_failure: script_failure.as_ref().map(qualify),
+            },
+            AnkhaOperation::LoopScope { script, label } => AnkhaOperation::LoopScope {
+                script: qualify(script),
+                label: label.to_owned(),
+            },
+            AnkhaOperation::PushScope { script } => AnkhaOperation::PushScope {
+                script: qualify(script),
+            },
+            AnkhaOperation::TryScope {
+                script,
+                catch_name,
+                script_catch,
+            } => AnkhaOperation::TryScope {
+                script: qualify(script),
+                catch_name: catch_name.to_owned(),
+                script_catch: qualify(script_catch),
+            },
+            AnkhaOperation::Match(cases) => AnkhaOperation::Match(
+                cases
+                    .iter()
+                    .map(|case| AnkhaMatchCase {
+                        variant: case.variant.to_owned(),
+                        script: qualify(&case.script),
+                    })
+                    .collect(),
+            ),
+            operation => operation.to_owned(),
+        })
+        .collect()
+}
+
 fn build_script(script: &AnkhaScript) -> ScriptHandle<'static, AnkhaExpression> {
+    build_scope(script, &Default::default())
+}
+
+fn build_scope(
+    script: &AnkhaScript,
+    jumps: &AnkhaScopeJumps,
+) -> ScriptHandle<'static, AnkhaExpression> {
     let mut registers = vec![];
     let mut result = vec![];
     for operation in script {
-        write_operation(operation, &mut registers, &mut result);
+        write_operation(operation, &mut registers, jumps, &mut result);
+    }
+    ScriptHandle::new(result)
+}
+
+// Jump targets of compiled scope.
+#[derive(Default, Clone)]
+struct AnkhaScopeJumps {
+    // Loops enclosing compiled scope, innermost goes last.
+    loops: Vec<Option<String>>,
+    // Compiled scope is body of innermost loop.
+    is_loop_body: bool,
+    // Compiled scope is part of function that returns early.
+    returns: bool,
+    // Compiled scope is part of try body.
+    in_try: bool,
+}
+
+impl AnkhaScopeJumps {
+    fn nested(&self) -> Self {
+        Self {
+            loops: self.loops.to_owned(),
+            is_loop_body: false,
+            returns: self.returns,
+            in_try: self.in_try,
+        }
+    }
+
+    fn enter(&self, label: Option<String>) -> Self {
+        let mut loops = self.loops.to_owned();
+        loops.push(label);
+        Self {
+            loops,
+            is_loop_body: true,
+            returns: self.returns,
+            in_try: self.in_try,
+        }
+    }
+
+    fn guarded(&self) -> Self {
+        Self {
+            in_try: true,
+            ..self.nested()
+        }
+    }
+
+    fn validate(&self, operation: &str, label: Option<&str>) {
+        match label {
+            Some(label) => {
+                if !self.loops.iter().any(|item| item.as_deref() == Some(label)) {
+                    panic!("There is no loop with `{}` label in the scope!", label);
+                }
+            }
+            None => {
+                if self.loops.is_empty() {
+                    panic!("{} can be used only inside loop!", operation);
+                }
+            }
+        }
+    }
+
+    // Leaves compiled scope when jump is pending - only needed when there is
+    // something to jump to.
+    fn write_jump_check(
+        &self,
+        restore_registers: bool,
+        result: &mut Vec<ScriptOperation<'static, AnkhaExpression>>,
+    ) {
+        if self.loops.is_empty() && !self.returns && !self.in_try {
+            return;
+        }
+        result.push(ScriptOperation::Expression {
+            expression: if self.is_loop_body {
+                AnkhaExpression::LoopJumpCheck {
+                    label: self.loops.last().cloned().flatten(),
+                    restore_registers,
+                }
+            } else {
+                AnkhaExpression::JumpCheck { restore_registers }
+            },
+        });
+        result.push(ScriptOperation::ContinueScopeConditionally);
+    }
+}
+
+fn find_internal_expression(script: &AnkhaScript) -> Option<&AnkhaExpression> {
+    script.iter().find_map(|operation| match operation {
+        AnkhaOperation::Expression(expression) => {
+            expression.is_internal().then_some(expression)
+        }
+        AnkhaOperation::TryScope {
+            script,
+            script_catch,
+            ..
+        } => find_internal_expression(script).or_else(|| find_internal_expression(script_catch)),
+        AnkhaOperation::Match(cases) => cases
+            .iter()
+            .find_map(|case| find_internal_expression(&case.script)),
+        AnkhaOperation::Group(script)
+        | AnkhaOperation::GroupReversed(script)
+        | AnkhaOperation::LoopScope { script, .. }
+        | AnkhaOperation::PushScope { script } => find_internal_expression(script),
+        AnkhaOperation::BranchScope {
+            script_success,
+            script_failure,
+        } => find_internal_expression(script_success)
+            .or_else(|| script_failure.as_ref().and_then(find_internal_expression)),
+        _ => None,
+    })
+}
+
+fn has_return(script: &AnkhaScript) -> bool {
+    script.iter().any(|operation| match operation {
+        AnkhaOperation::Return => true,
+        AnkhaOperation::TryScope {
+            script,
+            script_catch,
+            ..
+        } => has_return(script) || has_return(script_catch),
+        AnkhaOperation::Match(cases) => cases.iter().any(|case| has_return(&case.script)),
+        AnkhaOperation::Group(script)
+        | AnkhaOperation::GroupReversed(script)
+        | AnkhaOperation::LoopScope { script, .. }
+        | AnkhaOperation::PushScope { script } => has_return(script),
+        AnkhaOperation::BranchScope {
+            script_success,
+            script_failure,
+        } => {
+            has_return(script_success)
+                || script_failure.as_ref().map(has_return).unwrap_or_default()
+        }
+        _ => false,
+    })
+}
+
+fn tail_operations(script: &AnkhaScript) -> Vec<&AnkhaOperation> {
+    let mut result = vec![];
+    for operation in script {
+        push_tail_operation(operation, &mut result);
+    }
+    result
+}
+
+// Flattens groups in the same order `write_operation` writes them.
+fn push_tail_operation<'a>(operation: &'a AnkhaOperation, result: &mut Vec<&'a AnkhaOperation>) {
+    match operation {
+        AnkhaOperation::Group(script) => {
+            for operation in script {
+                push_tail_operation(operation, result);
+            }
+        }
+        AnkhaOperation::GroupReversed(script) => {
+            for operation in script.iter().rev() {
+                push_tail_operation(operation, result);
+            }
+        }
+        operation => result.push(operation),
+    }
+}
+
+fn has_tail_call(script: &AnkhaScript, is_self: &impl Fn(&AnkhaFunctionQuery) -> bool) -> bool {
+    match tail_operations(script).last() {
+        Some(AnkhaOperation::CallFunction(query)) => is_self(query),
+        Some(AnkhaOperation::BranchScope {
+            script_success,
+            script_failure,
+        }) => {
+            has_tail_call(script_success, is_self)
+                || script_failure
+                    .as_ref()
+                    .map(|script| has_tail_call(script, is_self))
+                    .unwrap_or_default()
+        }
+        _ => false,
+    }
+}
+
+// Builds body of function loop - every path either re-enters or leaves the loop.
+fn build_tail_script(
+    script: &AnkhaScript,
+    jumps: &AnkhaScopeJumps,
+    is_self: &impl Fn(&AnkhaFunctionQuery) -> bool,
+) -> ScriptHandle<'static, AnkhaExpression> {
+    let mut registers = vec![];
+    let mut result = vec![];
+    let mut operations = tail_operations(script);
+    let last = operations.pop();
+    for operation in operations {
+        write_operation(operation, &mut registers, jumps, &mut result);
+    }
+    match last {
+        Some(AnkhaOperation::CallFunction(query)) if is_self(query) => {
+            result.push(ScriptOperation::Expression {
+                expression: AnkhaExpression::TailCallLoop { repeat: true },
+            });
+        }
+        Some(AnkhaOperation::BranchScope {
+            script_success,
+            script_failure,
+        }) => {
+            let nested = jumps.nested();
+            result.push(ScriptOperation::BranchScope {
+                scope_success: build_tail_script(script_success, &nested, is_self),
+                scope_failure: Some(build_tail_script(
+                    script_failure.as_ref().unwrap_or(&vec![]),
+                    &nested,
+                    is_self,
+                )),
+            });
+            jumps.write_jump_check(false, &mut result);
+        }
+        last => {
+            if let Some(operation) = last {
+                write_operation(operation, &mut registers, jumps, &mut result);
+            }
+            result.push(ScriptOperation::Expression {
+                expression: AnkhaExpression::TailCallLoop { repeat: false },
+            });
+        }
     }
     ScriptHandle::new(result)
 }
 
+enum AnkhaReference {
+    Type(AnkhaTypeQuery),
+    Function(AnkhaFunctionQuery),
+    Constant(String, Option<String>),
+    Global(String, Option<String>),
+}
+
+impl AnkhaReference {
+    fn collect_module(module: &AnkhaModule, result: &mut Vec<Self>) {
+        let scripts = module
+            .functions
+            .iter()
+            .map(|function| &function.script)
+            .chain(module.globals.iter().map(|global| &global.script))
+            .chain(
+                module
+                    .structs
+                    .iter()
+                    .flat_map(|struct_type| &struct_type.fields)
+                    .filter_map(|field| field.default.as_ref()),
+            );
+        for script in scripts {
+            Self::collect(&qualify_accessors(script, &module.name), result);
+        }
+    }
+
+    fn collect(script: &AnkhaScript, result: &mut Vec<Self>) {
+        for operation in script {
+            match operation {
+                AnkhaOperation::Expression(expression) => match expression {
+                    AnkhaExpression::Structure { type_query, .. }
+                    | AnkhaExpression::Variant { type_query, .. }
+                    | AnkhaExpression::Cast { type_query, .. }
+                    | AnkhaExpression::EnsureStackType { type_query }
+                    | AnkhaExpression::EnsureRegisterType { type_query, .. }
+                    | AnkhaExpression::GetType { query: type_query } => {
+                        result.push(Self::Type(type_query.to_owned()));
+                    }
+                    AnkhaExpression::GetFunction { query }
+                    | AnkhaExpression::CallMethod {
+                        function_query: query,
+                    }
+                    | AnkhaExpression::CallFunction {
+                        function_query: query,
+                    } => {
+                        result.push(Self::Function(query.to_owned()));
+                    }
+                    AnkhaExpression::Guarded(expression) => {
+                        Self::collect(
+                            &vec![AnkhaOperation::Expression(*expression.clone())],
+                            result,
+                        );
+                    }
+                    AnkhaExpression::GetConstant { name, module_name } => {
+                        result.push(Self::Constant(name.to_owned(), module_name.to_owned()));
+                    }
+                    AnkhaExpression::GetGlobal {
+                        name, module_name, ..
+                    } => {
+                        result.push(Self::Global(name.to_owned(), module_name.to_owned()));
+                    }
+                    _ => {}
+                },
+                AnkhaOperation::Group(script) | AnkhaOperation::GroupReversed(script) => {
+                    Self::collect(script, result);
+                }
+                AnkhaOperation::CallFunction(query) => {
+                    result.push(Self::Function(query.to_owned()));
+                }
+                AnkhaOperation::BranchScope {
+                    script_success,
+                    script_failure,
+                } => {
+                    Self::collect(script_success, result);
+                    if let Some(script_failure) = script_failure {
+                        Self::collect(script_failure, result);
+                    }
+                }
+                AnkhaOperation::LoopScope { script, .. } | AnkhaOperation::PushScope { script } => {
+                    Self::collect(script, result);
+                }
+                AnkhaOperation::TryScope {
+                    script,
+                    script_catch,
+                    ..
+                } => {
+                    Self::collect(script, result);
+                    Self::collect(script_catch, result);
+                }
+                AnkhaOperation::Match(cases) => {
+                    for case in cases {
+                        Self::collect(&case.script, result);
+                    }
+                }
+                AnkhaOperation::EnsureRegisterType { type_query, .. } => {
+                    result.push(Self::Type(type_query.to_owned()));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn resolves(&self, registry: &Registry) -> bool {
+        self.resolve_module_name(registry).is_some()
+    }
+
+    fn resolve_module_name(&self, registry: &Registry) -> Option<Option<String>> {
+        match self {
+            Self::Type(query) => registry
+                .find_type(query.compile())
+                .map(|handle| handle.module_name().map(|name| name.to_owned())),
+            Self::Function(query) => registry
+                .find_function(query.compile())
+                .map(|handle| handle.signature().module_name.to_owned()),
+            Self::Constant(name, module_name) => registry
+                .find_function(accessor_query(
+                    is_constant_meta,
+                    constant_function_name(name),
+                    module_name.as_deref(),
+                ))
+                .map(|handle| handle.signature().module_name.to_owned()),
+            Self::Global(name, module_name) => registry
+                .find_function(accessor_query(
+                    is_global_meta,
+                    global_function_name(name),
+                    module_name.as_deref(),
+                ))
+                .map(|handle| handle.signature().module_name.to_owned()),
+        }
+    }
+}
+
+impl std::fmt::Display for AnkhaReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Type(query) => write!(f, "`{}`", query),
+            Self::Function(query) => write!(f, "`{}`", query),
+            Self::Constant(name, module_name) => match module_name {
+                Some(module_name) => write!(f, "constant `{}::{}`", module_name, name),
+                None => write!(f, "constant `{}`", name),
+            },
+            Self::Global(name, module_name) => match module_name {
+                Some(module_name) => write!(f, "global `{}::{}`", module_name, name),
+                None => write!(f, "global `{}`", name),
+            },
+        }
+    }
+}
+
+const TYPE_META: &str = "ankha/type";
+
+// Declared type of parameter or field travels in its meta, next to user meta.
+fn declared_type_meta(meta: Option<&Meta>, type_query: Option<&AnkhaTypeQuery>) -> Option<Meta> {
+    let Some(type_query) = type_query else {
+        return meta.cloned();
+    };
+    let map = [
+        ("name", type_query.name.as_ref()),
+        ("module_name", type_query.module_name.as_ref()),
+        ("type_name", type_query.type_name.as_ref()),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| {
+        Some((
+            key.to_owned(),
+            Meta::Value(MetaValue::String(value?.to_owned())),
+        ))
+    })
+    .collect();
+    let result = Meta::Named(TYPE_META.to_owned(), Box::new(Meta::Map(map)));
+    match meta {
+        Some(meta) => Some(Meta::Array(vec![meta.to_owned(), result])),
+        None => Some(result),
+    }
+}
+
+/// Reads type declared for script function parameter or struct field from its meta.
+pub fn declared_type(meta: Option<&Meta>) -> Option<AnkhaTypeQuery> {
+    match meta? {
+        Meta::Named(name, meta) if name == TYPE_META => {
+            let Meta::Map(map) = &**meta else {
+                return None;
+            };
+            let read = |key: &str| match map.get(key) {
+                Some(Meta::Value(MetaValue::String(value))) => Some(value.to_owned()),
+                _ => None,
+            };
+            Some(AnkhaTypeQuery {
+                name: read("name"),
+                module_name: read("module_name"),
+                type_name: read("type_name"),
+                ..Default::default()
+            })
+        }
+        Meta::Array(items) => items.iter().find_map(|meta| declared_type(Some(meta))),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AnkhaFunctionParameter {
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -2960,12 +4610,14 @@ pub struct AnkhaFunctionParameter {
     pub name: String,
     #[serde(default, skip_serializing_if = "AnkhaValueKind::is_any")]
     pub kind: AnkhaValueKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_query: Option<AnkhaTypeQuery>,
 }
 
 impl AnkhaFunctionParameter {
     pub fn compile(&self) -> ScriptFunctionParameter<'static> {
         ScriptFunctionParameter {
-            meta: self.meta.to_owned(),
+            meta: declared_type_meta(self.meta.as_ref(), self.type_query.as_ref()),
             name: self.name.to_owned(),
             type_query: TypeQuery {
                 type_hash: self.kind.type_hash(),
@@ -3018,8 +4670,74 @@ impl AnkhaFunction {
                     .map(|parameter| parameter.compile())
                     .collect(),
             },
-            script: build_script(&self.script),
+            script: self.build_script(module_name),
+        }
+    }
+
+    fn build_script(&self, module_name: &str) -> ScriptHandle<'static, AnkhaExpression> {
+        let name = match &self.type_name_module {
+            Some((type_name, _)) => format!("{}::{}::{}", module_name, type_name, self.name),
+            None => format!("{}::{}", module_name, self.name),
+        };
+        let mut script = vec![];
+        // Declared input types are checked on every entry, including tail calls.
+        if self.inputs.iter().any(|input| input.type_query.is_some()) {
+            script.push(AnkhaOperation::Expression(
+                AnkhaExpression::EnsureInputTypes {
+                    function: name.to_owned(),
+                    inputs: self.inputs.to_owned(),
+                },
+            ));
+        }
+        script.extend(qualify_accessors(&self.script, module_name));
+        let mut registers = vec![];
+        let mut result = vec![ScriptOperation::Expression {
+            expression: AnkhaExpression::PushCallFrame { name: name.into() },
+        }];
+        let is_self = |query: &AnkhaFunctionQuery| self.is_self_call(query, module_name);
+        let returns = has_return(&script);
+        if returns || has_tail_call(&script, &is_self) {
+            // Calls to itself in tail position re-enter function body in place,
+            // and early return leaves the loop.
+            let jumps = AnkhaScopeJumps {
+                is_loop_body: true,
+                returns,
+                ..Default::default()
+            };
+            result.push(ScriptOperation::Expression {
+                expression: AnkhaExpression::TailCallLoop { repeat: true },
+            });
+            result.push(ScriptOperation::LoopScope {
+                scope: build_tail_script(&script, &jumps, &is_self),
+            });
+            if returns {
+                result.push(ScriptOperation::Expression {
+                    expression: AnkhaExpression::FinishReturn,
+                });
+            }
+        } else {
+            for operation in &script {
+                write_operation(operation, &mut registers, &Default::default(), &mut result);
+            }
         }
+        result.push(ScriptOperation::Expression {
+            expression: AnkhaExpression::PopCallFrame,
+        });
+        ScriptHandle::new(result)
+    }
+
+    fn is_self_call(&self, query: &AnkhaFunctionQuery, module_name: &str) -> bool {
+        self.type_name_module.is_none()
+            && query.name.as_deref() == Some(self.name.as_str())
+            && query
+                .module_name
+                .as_deref()
+                .map(|name| name == module_name)
+                .unwrap_or(true)
+            && query.type_query.is_none()
+            && query.visibility.is_none()
+            && query.inputs.is_empty()
+            && query.outputs.is_empty()
     }
 }
 
@@ -3032,12 +4750,17 @@ pub struct AnkhaStructField {
     pub visibility: Visibility,
     #[serde(default, skip_serializing_if = "AnkhaValueKind::is_any")]
     pub kind: AnkhaValueKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_query: Option<AnkhaTypeQuery>,
+    // Script producing value of field omitted in `Structure`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<AnkhaScript>,
 }
 
 impl AnkhaStructField {
     pub fn compile(&self) -> ScriptStructField<'static> {
         ScriptStructField {
-            meta: self.meta.to_owned(),
+            meta: declared_type_meta(self.meta.as_ref(), self.type_query.as_ref()),
             name: self.name.to_owned(),
             visibility: self.visibility,
             type_query: TypeQuery {
@@ -3060,6 +4783,40 @@ pub struct AnkhaStruct {
 }
 
 impl AnkhaStruct {
+    pub fn has_same_layout(&self, other: &Self) -> bool {
+        self.fields.len() == other.fields.len()
+            && self
+                .fields
+                .iter()
+                .zip(other.fields.iter())
+                .all(|(a, b)| a.name == b.name && a.kind == b.kind && a.type_query == b.type_query)
+    }
+
+    // Functions producing default values of struct fields.
+    pub fn default_functions(&self, module_name: &str) -> Vec<AnkhaFunction> {
+        let mut result = vec![];
+        for field in &self.fields {
+            let Some(script) = field.default.to_owned() else {
+                continue;
+            };
+            result.push(AnkhaFunction {
+                meta: Some(Meta::Identifier(DEFAULT_META.to_owned())),
+                name: default_function_name(&field.name),
+                type_name_module: Some((self.name.to_owned(), Some(module_name.to_owned()))),
+                visibility: Visibility::Public,
+                inputs: vec![],
+                outputs: vec![AnkhaFunctionParameter {
+                    meta: None,
+                    name: "value".to_owned(),
+                    kind: field.kind,
+                    type_query: None,
+                }],
+                script,
+            });
+        }
+        result
+    }
+
     pub fn compile(&self, module_name: &str) -> ScriptStruct<'static> {
         ScriptStruct {
             meta: self.meta.to_owned(),
@@ -3107,6 +4864,23 @@ pub struct AnkhaEnum {
 }
 
 impl AnkhaEnum {
+    pub fn has_same_layout(&self, other: &Self) -> bool {
+        self.default_variant == other.default_variant
+            && self.variants.len() == other.variants.len()
+            && self
+                .variants
+                .iter()
+                .zip(other.variants.iter())
+                .all(|(a, b)| {
+                    a.name == b.name
+                        && a.discriminant == b.discriminant
+                        && a.fields.len() == b.fields.len()
+                        && a.fields.iter().zip(b.fields.iter()).all(|(a, b)| {
+                            a.name == b.name && a.kind == b.kind && a.type_query == b.type_query
+                        })
+                })
+    }
+
     pub fn compile(&self, module_name: &str) -> ScriptEnum<'static> {
         ScriptEnum {
             meta: self.meta.to_owned(),
@@ -3124,524 +4898,4746 @@ impl AnkhaEnum {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct AnkhaModule {
+pub struct AnkhaConstant {
     pub name: String,
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub structs: Vec<AnkhaStruct>,
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub enums: Vec<AnkhaEnum>,
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub functions: Vec<AnkhaFunction>,
+    #[serde(default, skip_serializing_if = "Visibility::is_public")]
+    pub visibility: Visibility,
+    pub value: AnkhaLiteral,
 }
 
-impl AnkhaModule {
-    pub fn compile(&self) -> ScriptModule<'static, AnkhaExpression> {
-        ScriptModule {
-            name: self.name.to_owned(),
-            structs: self
-                .structs
-                .iter()
-                .map(|struct_type| struct_type.compile(&self.name))
-                .collect(),
-            enums: self
-                .enums
-                .iter()
-                .map(|enum_type| enum_type.compile(&self.name))
-                .collect(),
-            functions: self
-                .functions
-                .iter()
-                .map(|function| function.compile(&self.name))
-                .collect(),
-        }
+impl AnkhaConstant {
+    pub fn install(&self, module_name: &str, registry: &mut Registry) {
+        let signature = accessor_signature(
+            CONSTANT_META,
+            constant_function_name(&self.name),
+            module_name,
+            self.visibility,
+            AnkhaValueKind::Owned,
+        )
+        .build(registry);
+        let value = self.value.to_owned();
+        registry.add_function(CoreFunction::new(
+            signature,
+            FunctionBody::closure(move |context, _| value.evaluate(context)),
+        ));
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
-pub struct AnkhaFile {
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub dependencies: Vec<String>,
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub modules: Vec<AnkhaModule>,
+// Box values live in managed storage of thread that made them, so box global keeps
+// its box together with that thread, other threads reach it through lazy value.
+struct AnkhaBoxGlobal {
+    value: DynamicManagedLazy,
+    thread: ThreadId,
+    owned: Mutex<Option<DynamicManagedBox>>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
-pub struct AnkhaPackage {
-    pub files: HashMap<String, AnkhaFile>,
-}
+// Box is dropped only on thread that made it.
+unsafe impl Send for AnkhaBoxGlobal {}
+unsafe impl Sync for AnkhaBoxGlobal {}
 
-impl AnkhaPackage {
-    pub fn new<CP>(path: &str, content_provider: &mut CP) -> Result<Self, Box<dyn Error>>
-    where
-        CP: ScriptContentProvider<AnkhaFile>,
-    {
-        let mut result = Self::default();
-        result.load(path, content_provider)?;
-        Ok(result)
+impl AnkhaBoxGlobal {
+    fn new(value: DynamicManagedBox) -> Option<Self> {
+        Some(Self {
+            value: value.lazy()?,
+            thread: std::thread::current().id(),
+            owned: Mutex::new(Some(value)),
+        })
     }
 
-    pub fn load<CP>(&mut self, path: &str, content_provider: &mut CP) -> Result<(), Box<dyn Error>>
-    where
-        CP: ScriptContentProvider<AnkhaFile>,
-    {
-        let path = content_provider.sanitize_path(path)?;
-        if self.files.contains_key(&path) {
-            return Ok(());
+    fn release(&self, name: &str) {
+        if std::thread::current().id() != self.thread {
+            panic!(
+                "Global `{}` box can be released only by thread that made it!",
+                name
+            );
         }
-        for content in content_provider.unpack_load(&path)? {
-            if let Some(file) = content.data? {
-                let dependencies = file.dependencies.to_owned();
-                self.files.insert(content.name, file);
-                for relative in dependencies {
-                    let path = content_provider.join_paths(&content.path, &relative)?;
-                    self.load(&path, content_provider)?;
-                }
-            }
+        let value = self.owned.lock().unwrap().take();
+        drop(value);
+    }
+}
+
+impl Drop for AnkhaBoxGlobal {
+    // Global that was not released gets dropped with its box on thread that made it,
+    // other threads cannot reach storage of that thread, so box is leaked there.
+    fn drop(&mut self) {
+        let value = self
+            .owned
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if std::thread::current().id() != self.thread {
+            std::mem::forget(value);
         }
-        Ok(())
     }
+}
 
-    pub fn compile(&self) -> ScriptPackage<'static, AnkhaExpression> {
-        ScriptPackage {
-            modules: self
-                .files
-                .values()
-                .flat_map(|file| file.modules.iter())
-                .map(|module| module.compile())
-                .collect(),
+// Generated body of script function and script it runs, which tasks run as their
+// frame instead.
+#[derive(Clone)]
+struct AnkhaFunctionCode {
+    body: Arc<FunctionBody>,
+    script: ScriptHandle<'static, AnkhaExpression>,
+}
+
+// Script functions run their body through shared slot, so reload can replace code of
+// functions that keep their signature and handles held by hosts run new code.
+struct AnkhaFunctionBody {
+    name: String,
+    // Key of function handle that owns the slot.
+    key: OnceLock<usize>,
+    code: RwLock<Option<AnkhaFunctionCode>>,
+}
+
+impl AnkhaFunctionBody {
+    fn install(
+        function: &ScriptFunction<'static, AnkhaExpression>,
+        body: FunctionBody,
+        registry: &mut Registry,
+        bodies: &AnkhaFunctionBodies,
+    ) {
+        let slot = Arc::new(Self {
+            name: function.signature.name.to_owned(),
+            key: OnceLock::new(),
+            code: RwLock::new(Some(AnkhaFunctionCode {
+                body: Arc::new(body),
+                script: function.script.clone(),
+            })),
+        });
+        let handle = registry.add_function(CoreFunction::new(
+            function.signature.build(registry),
+            FunctionBody::closure({
+                let slot = slot.clone();
+                move |context, registry| slot.invoke(context, registry)
+            }),
+        ));
+        let _ = slot.key.set(Self::key(&handle));
+        bodies.insert(&handle, &slot);
+    }
+
+    // Moves code of other slot into this one.
+    fn replace(&self, other: &Self) {
+        let code = other.code.write().unwrap().take();
+        *self.code.write().unwrap() = code;
+    }
+
+    fn clear(&self) {
+        *self.code.write().unwrap() = None;
+    }
+
+    fn invoke(&self, context: &mut Context, registry: &Registry) {
+        let code = self
+            .code
+            .read()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| panic!("Function `{}` was removed!", self.name));
+        if AnkhaTaskCall::targets(context, self.key.get().copied()) {
+            AnkhaTaskCall::defer(context, code.script);
+            return;
         }
+        code.body.invoke(context, registry);
+    }
+
+    fn key(handle: &FunctionHandle) -> usize {
+        Arc::as_ptr(handle) as usize
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        library::{AnkhaScriptBuilder, AnkhaVmScope},
-        parser::AnkhaContentParser,
-    };
-    use intuicio_backend_vm::prelude::*;
-    use intuicio_core::prelude::*;
-    use intuicio_data::prelude::*;
-    use intuicio_derive::*;
+// Body slots of functions installed by package, found by address of function
+// handle that owns them. Slots are owned by their functions, so entries of
+// functions dropped from registry expire.
+#[derive(Default)]
+pub struct AnkhaFunctionBodies(Mutex<HashMap<usize, Weak<AnkhaFunctionBody>>>);
 
-    pub struct LexprContentParser;
+impl AnkhaFunctionBodies {
+    fn insert(&self, handle: &FunctionHandle, slot: &Arc<AnkhaFunctionBody>) {
+        let mut bodies = self.0.lock().unwrap();
+        bodies.retain(|_, slot| slot.strong_count() > 0);
+        // Function with same signature might be registered already.
+        bodies
+            .entry(AnkhaFunctionBody::key(handle))
+            .or_insert_with(|| Arc::downgrade(slot));
+    }
 
-    impl BytesContentParser<AnkhaFile> for LexprContentParser {
-        fn parse(&self, bytes: Vec<u8>) -> Result<AnkhaFile, Box<dyn Error>> {
-            let content = String::from_utf8(bytes)?;
-            Ok(serde_lexpr::from_str::<AnkhaFile>(&content)?)
+    fn find(&self, handle: &FunctionHandle) -> Option<Arc<AnkhaFunctionBody>> {
+        self.0
+            .lock()
+            .unwrap()
+            .get(&AnkhaFunctionBody::key(handle))?
+            .upgrade()
+    }
+
+    fn remove(&self, handle: &FunctionHandle) -> Option<Arc<AnkhaFunctionBody>> {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(&AnkhaFunctionBody::key(handle))?
+            .upgrade()
+    }
+
+    fn extend(&self, other: Self) {
+        self.0.lock().unwrap().extend(other.0.into_inner().unwrap());
+    }
+}
+
+impl Clone for AnkhaFunctionBodies {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.0.lock().unwrap().clone()))
+    }
+}
+
+impl std::fmt::Debug for AnkhaFunctionBodies {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("AnkhaFunctionBodies").finish_non_exhaustive()
+    }
+}
+
+// Functions and types added to registry by installed package, found by address of
+// their handles. Weak handles keep addresses from being reused by other items.
+#[derive(Default)]
+pub struct AnkhaInstalledItems {
+    functions: Mutex<HashMap<usize, Weak<CoreFunction>>>,
+    types: Mutex<HashMap<usize, Weak<CoreType>>>,
+}
+
+impl AnkhaInstalledItems {
+    // Records items registry got since snapshot of it was made.
+    fn record(&self, before: &Registry, registry: &Registry) {
+        let functions = before
+            .functions()
+            .map(AnkhaFunctionBody::key)
+            .collect::<HashSet<_>>();
+        for handle in registry.functions() {
+            if !functions.contains(&AnkhaFunctionBody::key(handle)) {
+                self.insert_function(handle);
+            }
+        }
+        let types = before
+            .types()
+            .map(|handle| Arc::as_ptr(handle) as usize)
+            .collect::<HashSet<_>>();
+        for handle in registry.types() {
+            if !types.contains(&(Arc::as_ptr(handle) as usize)) {
+                self.insert_type(handle);
+            }
         }
     }
 
-    #[intuicio_function(
-        transformer = "DynamicManagedValueTransformer",
-        module_name = "intrinsics"
-    )]
-    fn add(a: i32, b: i32) -> i32 {
-        a + b
+    fn insert_function(&self, handle: &FunctionHandle) {
+        self.functions
+            .lock()
+            .unwrap()
+            .insert(AnkhaFunctionBody::key(handle), Arc::downgrade(handle));
     }
 
-    #[intuicio_function(
-        transformer = "DynamicManagedValueTransformer",
-        module_name = "intrinsics"
-    )]
-    fn sub(a: i32, b: i32) -> i32 {
-        a - b
+    fn contains_function(&self, handle: &FunctionHandle) -> bool {
+        self.functions
+            .lock()
+            .unwrap()
+            .contains_key(&AnkhaFunctionBody::key(handle))
     }
 
-    #[intuicio_function(
-        transformer = "DynamicManagedValueTransformer",
-        module_name = "intrinsics"
-    )]
-    fn mul(a: i32, b: i32) -> i32 {
-        a * b
+    fn remove_function(&self, handle: &FunctionHandle) {
+        self.functions
+            .lock()
+            .unwrap()
+            .remove(&AnkhaFunctionBody::key(handle));
     }
 
-    #[intuicio_function(
-        transformer = "DynamicManagedValueTransformer",
-        module_name = "intrinsics"
-    )]
-    fn eq(a: i32, b: i32) -> bool {
-        a == b
+    fn insert_type(&self, handle: &TypeHandle) {
+        self.types
+            .lock()
+            .unwrap()
+            .insert(Arc::as_ptr(handle) as usize, Arc::downgrade(handle));
     }
 
-    #[intuicio_function(module_name = "intrinsics")]
-    fn unmanaged_add(a: i32, b: i32) -> i32 {
-        a + b
+    fn contains_type(&self, handle: &TypeHandle) -> bool {
+        self.types
+            .lock()
+            .unwrap()
+            .contains_key(&(Arc::as_ptr(handle) as usize))
     }
 
-    #[derive(IntuicioStruct)]
-    struct Foo {
-        pub a: DynamicManaged,
-        pub b: DynamicManagedBox,
+    fn remove_type(&self, handle: &TypeHandle) {
+        self.types
+            .lock()
+            .unwrap()
+            .remove(&(Arc::as_ptr(handle) as usize));
     }
+}
 
-    impl Default for Foo {
-        fn default() -> Self {
-            let a = DynamicManaged::new(40).unwrap();
-            let b = DynamicManagedBox::new(2);
-            Self { a, b }
+impl Clone for AnkhaInstalledItems {
+    fn clone(&self) -> Self {
+        Self {
+            functions: Mutex::new(self.functions.lock().unwrap().clone()),
+            types: Mutex::new(self.types.lock().unwrap().clone()),
         }
     }
+}
 
-    #[test]
-    fn test_script() {
-        let mut registry = Registry::default().with_basic_types();
-        crate::library::install(&mut registry);
-        registry.add_type(Foo::define_struct(&registry));
-        registry.add_function(add::define_function(&registry));
-        registry.add_function(unmanaged_add::define_function(&registry));
-        let mut context = Context::new(10240, 10240);
+impl std::fmt::Debug for AnkhaInstalledItems {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnkhaInstalledItems").finish_non_exhaustive()
+    }
+}
 
-        let script = AnkhaScriptBuilder::default()
-            .expression(AnkhaExpression::Literal(AnkhaLiteral::I32(40)))
-            .expression(AnkhaExpression::EnsureStackType {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<i32>()),
-                    ..Default::default()
-                },
-            })
-            .expression(AnkhaExpression::Literal(AnkhaLiteral::I32(2)))
-            .expression(AnkhaExpression::EnsureStackType {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<i32>()),
-                    ..Default::default()
-                },
-            })
-            .call_function(FunctionQuery {
-                name: Some("add".into()),
-                ..Default::default()
-            })
-            .expression(AnkhaExpression::EnsureStackType {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<i32>()),
-                    ..Default::default()
-                },
-            })
-            .build();
-        let mut vm = VmScope::new(script, Default::default());
-        vm.run(&mut context, &registry);
-        assert_eq!(
-            context
-                .stack()
-                .pop::<DynamicManaged>()
-                .unwrap()
-                .consume::<i32>()
-                .ok()
-                .unwrap(),
-            42
-        );
-        context.stack().restore(unsafe { DataStackToken::new(0) });
+fn install_script_functions<SFG>(
+    module: &ScriptModule<'static, AnkhaExpression>,
+    registry: &mut Registry,
+    input: SFG::Input,
+    bodies: &AnkhaFunctionBodies,
+) where
+    SFG: ScriptFunctionGenerator<AnkhaExpression>,
+    SFG::Input: Clone,
+{
+    for function in &module.functions {
+        if let Some((body, _)) = SFG::generate_function_body(function.script.clone(), input.clone())
+        {
+            AnkhaFunctionBody::install(function, body, registry, bodies);
+        }
+    }
+}
 
-        let script = AnkhaScriptBuilder::default()
-            .expression(AnkhaExpression::Literal(AnkhaLiteral::I32(40)))
-            .expression(AnkhaExpression::EnsureStackType {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<i32>()),
-                    ..Default::default()
-                },
-            })
-            .expression(AnkhaExpression::Unmanage)
-            .expression(AnkhaExpression::Literal(AnkhaLiteral::I32(2)))
-            .expression(AnkhaExpression::EnsureStackType {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<i32>()),
-                    ..Default::default()
-                },
-            })
-            .expression(AnkhaExpression::Unmanage)
-            .call_function(FunctionQuery {
-                name: Some("unmanaged_add".into()),
-                ..Default::default()
-            })
-            .expression(AnkhaExpression::Manage)
-            .expression(AnkhaExpression::EnsureStackType {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<i32>()),
-                    ..Default::default()
-                },
-            })
-            .build();
-        let mut vm = VmScope::new(script, Default::default());
-        vm.run(&mut context, &registry);
-        assert_eq!(
-            context
-                .stack()
-                .pop::<DynamicManaged>()
-                .unwrap()
-                .consume::<i32>()
-                .ok()
-                .unwrap(),
-            42
-        );
-        context.stack().restore(unsafe { DataStackToken::new(0) });
+enum AnkhaGlobalValue {
+    Owned(DynamicManaged),
+    Box(AnkhaBoxGlobal),
+}
 
-        let script = AnkhaScriptBuilder::default()
-            .expression(AnkhaExpression::Structure {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<Foo>()),
-                    ..Default::default()
-                },
-                fields: Default::default(),
-            })
-            .expression(AnkhaExpression::Borrow)
-            .expression(AnkhaExpression::Swap)
-            .expression(AnkhaExpression::BorrowField {
-                name: "a".to_owned(),
-                kind: AnkhaValueKind::Owned,
-                visibility: None,
-            })
-            .expression(AnkhaExpression::CopyFrom)
-            .expression(AnkhaExpression::EnsureStackType {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<i32>()),
-                    ..Default::default()
-                },
-            })
-            .expression(AnkhaExpression::Literal(AnkhaLiteral::I32(2)))
-            .expression(AnkhaExpression::EnsureStackType {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<i32>()),
-                    ..Default::default()
-                },
-            })
-            .call_function(FunctionQuery {
-                name: Some("add".into()),
+// Global is initialized once on install, after globals of modules it depends on
+// and globals declared before it in its module.
+struct AnkhaGlobalSlot {
+    name: String,
+    kind: AnkhaValueKind,
+    initializer: FunctionBody,
+    value: OnceLock<AnkhaGlobalValue>,
+    // Thread running initializer, so initializer that reaches its own global fails
+    // instead of waiting for itself.
+    initializing: Mutex<Option<ThreadId>>,
+}
+
+// Clears initializing thread of global also when its initializer fails.
+struct AnkhaGlobalInitializing<'a>(&'a Mutex<Option<ThreadId>>);
+
+impl Drop for AnkhaGlobalInitializing<'_> {
+    fn drop(&mut self) {
+        *self.0.lock().unwrap() = None;
+    }
+}
+
+impl AnkhaGlobalSlot {
+    fn lazy(&self) -> DynamicManagedLazy {
+        match self.value.get() {
+            Some(AnkhaGlobalValue::Owned(value)) => value.lazy(),
+            Some(AnkhaGlobalValue::Box(value)) => value.value.clone(),
+            None if self.is_initializing() => {
+                panic!("Global `{}` initializer depends on itself!", self.name)
+            }
+            None => panic!(
+                "Global `{}` is read before initialization, its initializer might depend on module that is not its dependency!",
+                self.name
+            ),
+        }
+    }
+
+    fn is_initializing(&self) -> bool {
+        *self.initializing.lock().unwrap() == Some(std::thread::current().id())
+    }
+
+    // Drops box value, owned value goes with the slot.
+    fn release(&self) {
+        if let Some(AnkhaGlobalValue::Box(value)) = self.value.get() {
+            value.release(&self.name);
+        }
+    }
+
+    // Initializer runs once, even when other thread asks for it meanwhile.
+    fn initialize(&self, context: &mut Context, registry: &Registry) {
+        if self.is_initializing() {
+            panic!("Global `{}` initializer depends on itself!", self.name);
+        }
+        self.value.get_or_init(|| {
+            *self.initializing.lock().unwrap() = Some(std::thread::current().id());
+            let _initializing = AnkhaGlobalInitializing(&self.initializing);
+            context.store_registers();
+            self.initializer.invoke(context, registry);
+            context.restore_registers();
+            match self.kind {
+                AnkhaValueKind::Owned => AnkhaGlobalValue::Owned(
+                    context.stack().pop::<DynamicManaged>().unwrap_or_else(|| {
+                        panic!(
+                            "Global `{}` initializer did not produce owned value!",
+                            self.name
+                        )
+                    }),
+                ),
+                AnkhaValueKind::Box => AnkhaGlobalValue::Box(
+                    context
+                        .stack()
+                        .pop::<DynamicManagedBox>()
+                        .and_then(AnkhaBoxGlobal::new)
+                        .unwrap_or_else(|| {
+                            panic!(
+                                "Global `{}` initializer did not produce box value!",
+                                self.name
+                            )
+                        }),
+                ),
+                _ => unreachable!(),
+            }
+        });
+    }
+}
+
+// Slots of globals installed by package, found by address of their accessor function
+// handle, so uninstall can release their values. Slots are owned by their functions,
+// so entries of functions dropped from registry expire.
+#[derive(Default)]
+pub struct AnkhaGlobalSlots(Mutex<HashMap<usize, Weak<AnkhaGlobalSlot>>>);
+
+impl AnkhaGlobalSlots {
+    fn insert(&self, handle: &FunctionHandle, slot: &Arc<AnkhaGlobalSlot>) {
+        let mut slots = self.0.lock().unwrap();
+        slots.retain(|_, slot| slot.strong_count() > 0);
+        slots.insert(AnkhaFunctionBody::key(handle), Arc::downgrade(slot));
+    }
+
+    fn remove(&self, handle: &FunctionHandle) -> Option<Arc<AnkhaGlobalSlot>> {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(&AnkhaFunctionBody::key(handle))?
+            .upgrade()
+    }
+
+    fn extend(&self, other: Self) {
+        self.0.lock().unwrap().extend(other.0.into_inner().unwrap());
+    }
+}
+
+impl Clone for AnkhaGlobalSlots {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.0.lock().unwrap().clone()))
+    }
+}
+
+impl std::fmt::Debug for AnkhaGlobalSlots {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("AnkhaGlobalSlots").finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnkhaGlobal {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Visibility::is_public")]
+    pub visibility: Visibility,
+    pub kind: AnkhaValueKind,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub script: AnkhaScript,
+}
+
+impl AnkhaGlobal {
+    // Registers global accessor and initializer, value gets initialized by `initialize`.
+    pub fn install<SFG>(
+        &self,
+        module_name: &str,
+        registry: &mut Registry,
+        input: SFG::Input,
+        slots: &AnkhaGlobalSlots,
+    ) where
+        SFG: ScriptFunctionGenerator<AnkhaExpression>,
+    {
+        if !matches!(self.kind, AnkhaValueKind::Owned | AnkhaValueKind::Box) {
+            panic!("Global `{}` can be only owned or box!", self.name);
+        }
+        let script = qualify_accessors(&self.script, module_name);
+        let (initializer, _) = SFG::generate_function_body(build_script(&script), input)
+            .unwrap_or_else(|| panic!("Could not generate `{}` global initializer!", self.name));
+        let slot = Arc::new(AnkhaGlobalSlot {
+            name: self.name.to_owned(),
+            kind: self.kind,
+            initializer,
+            value: Default::default(),
+            initializing: Default::default(),
+        });
+        let signature = accessor_signature(
+            GLOBAL_META,
+            global_function_name(&self.name),
+            module_name,
+            self.visibility,
+            AnkhaValueKind::Lazy,
+        )
+        .build(registry);
+        let handle = registry.add_function(CoreFunction::new(
+            signature,
+            FunctionBody::closure({
+                let slot = slot.clone();
+                move |context, _| {
+                    context.stack().push(slot.lazy());
+                }
+            }),
+        ));
+        slots.insert(&handle, &slot);
+        let signature = ScriptFunctionSignature {
+            outputs: vec![],
+            ..accessor_signature(
+                GLOBAL_META,
+                global_init_function_name(&self.name),
+                module_name,
+                Visibility::Private,
+                AnkhaValueKind::Lazy,
+            )
+        }
+        .build(registry);
+        registry.add_function(CoreFunction::new(
+            signature,
+            FunctionBody::closure(move |context, registry| slot.initialize(context, registry)),
+        ));
+    }
+
+    pub fn initialize(&self, module_name: &str, context: &mut Context, registry: &Registry) {
+        let handle = registry
+            .find_function(accessor_query(
+                is_global_meta,
+                global_init_function_name(&self.name),
+                Some(module_name),
+            ))
+            .unwrap_or_else(|| panic!("Could not find `{}` global!", self.name));
+        handle.invoke(context, registry);
+    }
+}
+
+const CONSTANT_META: &str = "ankha/const";
+const GLOBAL_META: &str = "ankha/global";
+const DEFAULT_META: &str = "ankha/default";
+
+fn is_constant_meta(meta: &Meta) -> bool {
+    matches!(meta, Meta::Identifier(name) if name == CONSTANT_META)
+}
+
+fn is_global_meta(meta: &Meta) -> bool {
+    matches!(meta, Meta::Identifier(name) if name == GLOBAL_META)
+}
+
+fn is_default_meta(meta: &Meta) -> bool {
+    matches!(meta, Meta::Identifier(name) if name == DEFAULT_META)
+}
+
+fn default_function_name(field: &str) -> String {
+    format!("default/{}", field)
+}
+
+// Accessors live under reserved names, so they never collide with functions.
+fn constant_function_name(name: &str) -> String {
+    format!("const/{}", name)
+}
+
+fn global_function_name(name: &str) -> String {
+    format!("global/{}", name)
+}
+
+fn global_init_function_name(name: &str) -> String {
+    format!("global-init/{}", name)
+}
+
+fn accessor_query<'a>(
+    meta: fn(&Meta) -> bool,
+    name: String,
+    module_name: Option<&'a str>,
+) -> FunctionQuery<'a> {
+    FunctionQuery {
+        name: Some(name.into()),
+        module_name: module_name.map(|name| name.into()),
+        meta: Some(meta),
+        ..Default::default()
+    }
+}
+
+fn accessor_signature(
+    meta: &str,
+    name: String,
+    module_name: &str,
+    visibility: Visibility,
+    kind: AnkhaValueKind,
+) -> ScriptFunctionSignature<'static> {
+    ScriptFunctionSignature {
+        meta: Some(Meta::Identifier(meta.to_owned())),
+        name,
+        module_name: Some(module_name.to_owned()),
+        type_query: None,
+        visibility,
+        inputs: vec![],
+        outputs: vec![
+            AnkhaFunctionParameter {
+                meta: None,
+                name: "value".to_owned(),
+                kind,
+                type_query: None,
+            }
+            .compile(),
+        ],
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnkhaModule {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub init: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shutdown: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constants: Vec<AnkhaConstant>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub globals: Vec<AnkhaGlobal>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub structs: Vec<AnkhaStruct>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enums: Vec<AnkhaEnum>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub functions: Vec<AnkhaFunction>,
+}
+
+impl AnkhaModule {
+    pub fn compile(&self) -> ScriptModule<'static, AnkhaExpression> {
+        ScriptModule {
+            name: self.name.to_owned(),
+            structs: self
+                .structs
+                .iter()
+                .map(|struct_type| struct_type.compile(&self.name))
+                .collect(),
+            enums: self
+                .enums
+                .iter()
+                .map(|enum_type| enum_type.compile(&self.name))
+                .collect(),
+            functions: self
+                .functions
+                .iter()
+                .cloned()
+                .chain(
+                    self.structs
+                        .iter()
+                        .flat_map(|struct_type| struct_type.default_functions(&self.name)),
+                )
+                .map(|function| function.compile(&self.name))
+                .collect(),
+        }
+    }
+
+    pub fn install_constants(&self, registry: &mut Registry) {
+        for constant in &self.constants {
+            constant.install(&self.name, registry);
+        }
+    }
+
+    pub fn install_globals<SFG>(
+        &self,
+        registry: &mut Registry,
+        input: SFG::Input,
+        slots: &AnkhaGlobalSlots,
+    ) where
+        SFG: ScriptFunctionGenerator<AnkhaExpression>,
+        SFG::Input: Clone,
+    {
+        for global in &self.globals {
+            global.install::<SFG>(&self.name, registry, input.clone(), slots);
+        }
+    }
+
+    pub fn initialize_globals(&self, context: &mut Context, registry: &Registry) {
+        for global in &self.globals {
+            global.initialize(&self.name, context, registry);
+        }
+    }
+
+    pub fn initialize(&self, context: &mut Context, registry: &Registry) {
+        if let Some(name) = self.init.as_deref() {
+            self.run_hook(name, context, registry);
+        }
+    }
+
+    pub fn finalize(&self, context: &mut Context, registry: &Registry) {
+        if let Some(name) = self.shutdown.as_deref() {
+            self.run_hook(name, context, registry);
+        }
+    }
+
+    // Removes functions and types of this module that package install added, items
+    // registered by host under the same module name stay.
+    pub fn uninstall(&self, registry: &mut Registry, installed: &AnkhaInstalledItems) {
+        let functions = registry
+            .functions()
+            .filter(|handle| {
+                handle.signature().module_name.as_deref() == Some(self.name.as_str())
+                    && installed.contains_function(handle)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        for handle in functions {
+            installed.remove_function(&handle);
+            registry.remove_function(handle);
+        }
+        let types = registry
+            .types()
+            .filter(|handle| {
+                handle.module_name() == Some(self.name.as_str()) && installed.contains_type(handle)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        for handle in types {
+            installed.remove_type(&handle);
+            registry.remove_type(handle);
+        }
+    }
+
+    // Rejects scripts that use internal expressions, these could forge call frames,
+    // try scopes or jumps out of their own scopes.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        let scripts = self
+            .functions
+            .iter()
+            .map(|function| &function.script)
+            .chain(self.globals.iter().map(|global| &global.script))
+            .chain(
+                self.structs
+                    .iter()
+                    .flat_map(|struct_type| &struct_type.fields)
+                    .filter_map(|field| field.default.as_ref()),
+            );
+        for script in scripts {
+            if let Some(expression) = find_internal_expression(script) {
+                return Err(format!(
+                    "Module `{}` uses internal expression: {:?}",
+                    self.name, expression
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    fn run_hook(&self, name: &str, context: &mut Context, registry: &Registry) {
+        let handle = registry
+            .find_function(FunctionQuery {
+                name: Some(name.into()),
+                module_name: Some(self.name.as_str().into()),
+                ..Default::default()
+            })
+            .unwrap_or_else(|| {
+                panic!(
+                    "Could not find `{}` hook function in `{}` module!",
+                    name, self.name
+                )
+            });
+        handle.invoke(context, registry);
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnkhaFile {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<AnkhaModule>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AnkhaPackage {
+    pub files: HashMap<String, AnkhaFile>,
+    // File names in dependency order (dependencies go first).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+    // Filled when package gets installed, so reload can find functions it owns.
+    #[serde(skip)]
+    pub bodies: AnkhaFunctionBodies,
+    // Filled when package gets installed, so uninstall removes only what it added.
+    #[serde(skip)]
+    pub installed: AnkhaInstalledItems,
+    // Filled when package gets installed, so uninstall releases values of its globals.
+    #[serde(skip)]
+    pub globals: AnkhaGlobalSlots,
+}
+
+impl AnkhaPackage {
+    pub fn new<CP>(path: &str, content_provider: &mut CP) -> Result<Self, Box<dyn Error>>
+    where
+        CP: ScriptContentProvider<AnkhaFile>,
+    {
+        let mut result = Self::default();
+        result.load(path, content_provider)?;
+        Ok(result)
+    }
+
+    pub fn load<CP>(&mut self, path: &str, content_provider: &mut CP) -> Result<(), Box<dyn Error>>
+    where
+        CP: ScriptContentProvider<AnkhaFile>,
+    {
+        let path = content_provider.sanitize_path(path)?;
+        if self.files.contains_key(&path) {
+            return Ok(());
+        }
+        for content in content_provider.unpack_load(&path)? {
+            if let Some(file) = content.data? {
+                let dependencies = file.dependencies.to_owned();
+                self.files.insert(content.name.to_owned(), file);
+                for relative in dependencies {
+                    let path = content_provider.join_paths(&content.path, &relative)?;
+                    self.load(&path, content_provider)?;
+                }
+                self.order.push(content.name);
+            }
+        }
+        Ok(())
+    }
+
+    // File names sorted so that dependencies go first. Files listed in `order` are
+    // visited first, then the rest alphabetically. Dependency names are matched with
+    // file names either directly or as paths relative to depending file.
+    pub fn file_order(&self) -> Result<Vec<&str>, Box<dyn Error>> {
+        let mut names = self
+            .files
+            .keys()
+            .filter(|name| !self.order.contains(name))
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>();
+        names.sort();
+        let names = self
+            .order
+            .iter()
+            .map(|name| name.as_str())
+            .filter(|name| self.files.contains_key(*name))
+            .chain(names);
+        let mut result = Vec::with_capacity(self.files.len());
+        let mut visiting = vec![];
+        for name in names {
+            self.visit_file(name, &mut visiting, &mut result)?;
+        }
+        Ok(result)
+    }
+
+    fn visit_file<'a>(
+        &'a self,
+        name: &'a str,
+        visiting: &mut Vec<&'a str>,
+        result: &mut Vec<&'a str>,
+    ) -> Result<(), Box<dyn Error>> {
+        if result.contains(&name) {
+            return Ok(());
+        }
+        if let Some(index) = visiting.iter().position(|item| *item == name) {
+            let mut cycle = visiting[index..].to_vec();
+            cycle.push(name);
+            return Err(format!("Cyclic file dependencies: {}", cycle.join(" -> ")).into());
+        }
+        let Some(file) = self.files.get(name) else {
+            return Ok(());
+        };
+        visiting.push(name);
+        for dependency in &file.dependencies {
+            if let Some(dependency) = self.find_dependency(name, dependency) {
+                self.visit_file(dependency, visiting, result)?;
+            }
+        }
+        visiting.pop();
+        result.push(name);
+        Ok(())
+    }
+
+    fn find_dependency(&self, name: &str, dependency: &str) -> Option<&str> {
+        if let Some((key, _)) = self.files.get_key_value(dependency) {
+            return Some(key);
+        }
+        let path = Path::new(name).with_file_name(dependency);
+        self.files
+            .keys()
+            .find(|key| Path::new(key) == path)
+            .map(|key| key.as_str())
+    }
+
+    // Panics on cyclic file dependencies, use `file_order` to validate package first.
+    pub fn modules(&self) -> impl Iterator<Item = &AnkhaModule> {
+        self.file_order()
+            .unwrap_or_else(|error| panic!("{}", error))
+            .into_iter()
+            .filter_map(|name| self.files.get(name))
+            .flat_map(|file| file.modules.iter())
+    }
+
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        for module in self.modules() {
+            module.validate()?;
+        }
+        Ok(())
+    }
+
+    pub fn compile(&self) -> ScriptPackage<'static, AnkhaExpression> {
+        ScriptPackage {
+            modules: self.modules().map(|module| module.compile()).collect(),
+        }
+    }
+
+    pub fn install<SFG>(&self, context: &mut Context, registry: &mut Registry, input: SFG::Input)
+    where
+        SFG: ScriptFunctionGenerator<AnkhaExpression>,
+        SFG::Input: Clone,
+    {
+        self.validate().unwrap_or_else(|error| panic!("{}", error));
+        AnkhaCapabilities::ensure_package_allowed(context, self, registry)
+            .unwrap_or_else(|error| panic!("{}", error));
+        let before = registry.clone();
+        let package = self.compile();
+        for module in &package.modules {
+            module.install_types(registry);
+        }
+        for module in &package.modules {
+            install_script_functions::<SFG>(module, registry, input.clone(), &self.bodies);
+        }
+        for module in self.modules() {
+            module.install_constants(registry);
+        }
+        for module in self.modules() {
+            module.install_globals::<SFG>(registry, input.clone(), &self.globals);
+        }
+        self.installed.record(&before, registry);
+        for module in self.modules() {
+            module.initialize_globals(context, registry);
+        }
+        self.initialize(context, registry);
+    }
+
+    // Replaces changed files of installed package with ones from reloaded package.
+    // Types and unchanged globals keep their handles and values, so reload is rejected
+    // when struct or enum layout changes, or when anything that was resolved before
+    // no longer resolves, either in this or other installed packages.
+    // Functions that keep their signature are replaced in place, so their handles run
+    // new code. Changed modules get shut down before and initialized after reload.
+    pub fn reload<SFG>(
+        &mut self,
+        package: AnkhaPackage,
+        installed: &[&AnkhaPackage],
+        context: &mut Context,
+        registry: &mut Registry,
+        input: SFG::Input,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        SFG: ScriptFunctionGenerator<AnkhaExpression>,
+        SFG::Input: Clone,
+    {
+        package.file_order()?;
+        package.validate()?;
+        AnkhaCapabilities::ensure_package_allowed(context, &package, registry)?;
+        let mut old_modules = HashMap::<&str, &AnkhaModule>::new();
+        let mut new_modules = HashMap::<&str, &AnkhaModule>::new();
+        for (name, file) in &self.files {
+            if package.files.get(name) != Some(file) {
+                for module in &file.modules {
+                    old_modules.insert(&module.name, module);
+                }
+            }
+        }
+        for (name, file) in &package.files {
+            if self.files.get(name) != Some(file) {
+                for module in &file.modules {
+                    new_modules.insert(&module.name, module);
+                }
+            }
+        }
+        let mut errors = vec![];
+        for (module_name, new_module) in &new_modules {
+            let Some(old_module) = old_modules.get(module_name) else {
+                continue;
+            };
+            for new_struct in &new_module.structs {
+                if let Some(old_struct) = old_module
+                    .structs
+                    .iter()
+                    .find(|old_struct| old_struct.name == new_struct.name)
+                    && !old_struct.has_same_layout(new_struct)
+                {
+                    errors.push(format!(
+                        "Layout of `{}::{}` struct has changed",
+                        module_name, new_struct.name
+                    ));
+                }
+            }
+            for new_enum in &new_module.enums {
+                if let Some(old_enum) = old_module
+                    .enums
+                    .iter()
+                    .find(|old_enum| old_enum.name == new_enum.name)
+                    && !old_enum.has_same_layout(new_enum)
+                {
+                    errors.push(format!(
+                        "Layout of `{}::{}` enum has changed",
+                        module_name, new_enum.name
+                    ));
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors.join("\n").into());
+        }
+
+        let mut staging = registry.clone();
+        let staged_bodies = AnkhaFunctionBodies::default();
+        let staged_globals = AnkhaGlobalSlots::default();
+        for (module_name, old_module) in &old_modules {
+            let new_module = new_modules.get(module_name);
+            let kept_globals = old_module
+                .globals
+                .iter()
+                .filter(|old_global| {
+                    new_module
+                        .map(|new_module| {
+                            new_module.globals.iter().any(|new_global| {
+                                new_global.name == old_global.name
+                                    && new_global.kind == old_global.kind
+                            })
+                        })
+                        .unwrap_or_default()
+                })
+                .flat_map(|global| {
+                    [
+                        global_function_name(&global.name),
+                        global_init_function_name(&global.name),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            let functions = staging
+                .functions()
+                .filter(|handle| {
+                    let signature = handle.signature();
+                    signature.module_name.as_deref() == Some(module_name)
+                        && !(signature
+                            .meta
+                            .as_ref()
+                            .map(is_global_meta)
+                            .unwrap_or_default()
+                            && kept_globals.contains(&signature.name))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            for handle in functions {
+                staging.remove_function(handle);
+            }
+            let removed_types = old_module
+                .structs
+                .iter()
+                .map(|struct_type| struct_type.name.as_str())
+                .filter(|name| {
+                    new_module
+                        .map(|new_module| {
+                            new_module
+                                .structs
+                                .iter()
+                                .all(|struct_type| struct_type.name != *name)
+                        })
+                        .unwrap_or(true)
+                })
+                .chain(
+                    old_module
+                        .enums
+                        .iter()
+                        .map(|enum_type| enum_type.name.as_str())
+                        .filter(|name| {
+                            new_module
+                                .map(|new_module| {
+                                    new_module
+                                        .enums
+                                        .iter()
+                                        .all(|enum_type| enum_type.name != *name)
+                                })
+                                .unwrap_or(true)
+                        }),
+                )
+                .collect::<Vec<_>>();
+            for name in removed_types {
+                if let Some(handle) = staging.find_type(TypeQuery {
+                    name: Some(name.into()),
+                    module_name: Some((*module_name).into()),
+                    ..Default::default()
+                }) {
+                    staging.remove_type(handle);
+                }
+            }
+        }
+        for (module_name, new_module) in &new_modules {
+            let old_module = old_modules.get(module_name);
+            let mut script_module = new_module.compile();
+            if let Some(old_module) = old_module {
+                script_module.structs.retain(|new_struct| {
+                    old_module
+                        .structs
+                        .iter()
+                        .all(|old_struct| old_struct.name != new_struct.name)
+                });
+                script_module.enums.retain(|new_enum| {
+                    old_module
+                        .enums
+                        .iter()
+                        .all(|old_enum| old_enum.name != new_enum.name)
+                });
+            }
+            script_module.install_types(&mut staging);
+        }
+        for new_module in new_modules.values() {
+            install_script_functions::<SFG>(
+                &new_module.compile(),
+                &mut staging,
+                input.clone(),
+                &staged_bodies,
+            );
+            new_module.install_constants(&mut staging);
+        }
+        let mut new_globals = vec![];
+        for new_module in package.modules() {
+            let module_name = new_module.name.as_str();
+            if !new_modules.contains_key(module_name) {
+                continue;
+            }
+            let old_module = old_modules.get(module_name);
+            for global in &new_module.globals {
+                let kept = old_module
+                    .map(|old_module| {
+                        old_module.globals.iter().any(|old_global| {
+                            old_global.name == global.name && old_global.kind == global.kind
+                        })
+                    })
+                    .unwrap_or_default();
+                if !kept {
+                    global.install::<SFG>(
+                        module_name,
+                        &mut staging,
+                        input.clone(),
+                        &staged_globals,
+                    );
+                    new_globals.push((module_name, global));
+                }
+            }
+        }
+
+        let mut references = vec![];
+        for module in package
+            .modules()
+            .chain(installed.iter().flat_map(|package| package.modules()))
+        {
+            AnkhaReference::collect_module(module, &mut references);
+        }
+        for reference in references {
+            if reference.resolves(registry) && !reference.resolves(&staging) {
+                errors.push(format!("Reference to removed {}", reference));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors.join("\n").into());
+        }
+
+        // Nothing runs before reload is validated.
+        let changed_modules = self
+            .modules()
+            .filter(|old_module| old_modules.contains_key(old_module.name.as_str()))
+            .collect::<Vec<_>>();
+        for old_module in changed_modules.into_iter().rev() {
+            old_module.finalize(context, registry);
+        }
+        self.apply(registry, &staging, &staged_bodies);
+        self.bodies.extend(staged_bodies);
+        self.globals.extend(staged_globals);
+        for (module_name, global) in new_globals {
+            global.initialize(module_name, context, registry);
+        }
+        for new_module in package.modules() {
+            if new_modules.contains_key(new_module.name.as_str()) {
+                new_module.initialize(context, registry);
+            }
+        }
+        let bodies = std::mem::take(&mut self.bodies);
+        let installed = std::mem::take(&mut self.installed);
+        let globals = std::mem::take(&mut self.globals);
+        *self = package;
+        self.bodies = bodies;
+        self.installed = installed;
+        self.globals = globals;
+        Ok(())
+    }
+
+    // Applies changes made in staging registry to existing one. Script functions that
+    // keep their signature get new body, so existing handles stay valid.
+    fn apply(
+        &self,
+        registry: &mut Registry,
+        staging: &Registry,
+        staged_bodies: &AnkhaFunctionBodies,
+    ) {
+        let staged = staging
+            .functions()
+            .map(AnkhaFunctionBody::key)
+            .collect::<HashSet<_>>();
+        let existing = registry
+            .functions()
+            .map(AnkhaFunctionBody::key)
+            .collect::<HashSet<_>>();
+        let mut removed = registry
+            .functions()
+            .filter(|handle| !staged.contains(&AnkhaFunctionBody::key(handle)))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut added = vec![];
+        for handle in staging.functions() {
+            if existing.contains(&AnkhaFunctionBody::key(handle)) {
+                continue;
+            }
+            let replaced = removed
+                .iter()
+                .position(|old| old.signature() == handle.signature())
+                .and_then(|index| {
+                    let old = self.bodies.find(&removed[index])?;
+                    let new = staged_bodies.remove(handle)?;
+                    old.replace(&new);
+                    Some(index)
+                });
+            match replaced {
+                Some(index) => {
+                    removed.swap_remove(index);
+                }
+                None => added.push(handle.clone()),
+            }
+        }
+        for handle in removed {
+            if let Some(slot) = self.bodies.remove(&handle) {
+                slot.clear();
+            }
+            if let Some(slot) = self.globals.remove(&handle) {
+                slot.release();
+            }
+            self.installed.remove_function(&handle);
+            registry.remove_function(handle);
+        }
+        for handle in added {
+            self.installed.insert_function(&handle);
+            registry.add_function_handle(handle);
+        }
+        let removed = registry
+            .types()
+            .filter(|handle| !staging.types().any(|item| Arc::ptr_eq(item, handle)))
+            .cloned()
+            .collect::<Vec<_>>();
+        let added = staging
+            .types()
+            .filter(|handle| !registry.types().any(|item| Arc::ptr_eq(item, handle)))
+            .cloned()
+            .collect::<Vec<_>>();
+        for handle in removed {
+            self.installed.remove_type(&handle);
+            registry.remove_type(handle);
+        }
+        for handle in added {
+            self.installed.insert_type(&handle);
+            registry.add_type_handle(handle);
+        }
+    }
+
+    pub fn initialize(&self, context: &mut Context, registry: &Registry) {
+        for module in self.modules() {
+            module.initialize(context, registry);
+        }
+    }
+
+    pub fn finalize(&self, context: &mut Context, registry: &Registry) {
+        for module in self.modules().collect::<Vec<_>>().into_iter().rev() {
+            module.finalize(context, registry);
+        }
+    }
+
+    pub fn uninstall(&self, context: &mut Context, registry: &mut Registry) {
+        self.finalize(context, registry);
+        for module in self.modules() {
+            for handle in registry.functions() {
+                if handle.signature().module_name.as_deref() != Some(module.name.as_str())
+                    || !self.installed.contains_function(handle)
+                {
+                    continue;
+                }
+                if let Some(slot) = self.bodies.remove(handle) {
+                    slot.clear();
+                }
+                if let Some(slot) = self.globals.remove(handle) {
+                    slot.release();
+                }
+            }
+            module.uninstall(registry, &self.installed);
+        }
+    }
+}
+
+// Module-level capability policy for untrusted packages.
+// Registry made by `sandbox` contains only allowed items, so packages executed with it
+// cannot find anything else, neither with `GetFunction` nor `reflect::Function::by_name`.
+// Policy enforced in context with `enforce` also guards full registry: packages that
+// do not `link` with it are refused by install and reload, and every function resolved
+// by calls and `GetFunction`, or handed to scripts by host, is checked before use.
+// Allow-list has to name modules of packages themselves, since their calls are checked too.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnkhaCapabilities {
+    // Empty list allows all modules that are not denied.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_modules: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denied_modules: Vec<String>,
+}
+
+impl AnkhaCapabilities {
+    pub fn allow(mut self, module_name: impl ToString) -> Self {
+        self.allowed_modules.push(module_name.to_string());
+        self
+    }
+
+    pub fn deny(mut self, module_name: impl ToString) -> Self {
+        self.denied_modules.push(module_name.to_string());
+        self
+    }
+
+    // Items without module name are denied once allow-list is not empty.
+    pub fn is_module_allowed(&self, module_name: Option<&str>) -> bool {
+        let Some(module_name) = module_name else {
+            return self.allowed_modules.is_empty();
+        };
+        !self.denied_modules.iter().any(|name| name == module_name)
+            && (self.allowed_modules.is_empty()
+                || self.allowed_modules.iter().any(|name| name == module_name))
+    }
+
+    // Types without module name (basic types) only hold data and are always
+    // allowed, their methods are checked by their own module.
+    pub fn is_type_allowed(&self, module_name: Option<&str>) -> bool {
+        module_name.is_none() || self.is_module_allowed(module_name)
+    }
+
+    // Makes registry that contains only allowed types and functions.
+    // Packages installed into and executed with it cannot reach anything else.
+    pub fn sandbox(&self, registry: &Registry) -> Registry {
+        let mut result = Registry::default()
+            .with_index_capacity(registry.index_capacity)
+            .with_use_indexing_threshold(registry.use_indexing_threshold);
+        for handle in registry.types() {
+            if self.is_type_allowed(handle.module_name()) {
+                result.add_type_handle(handle.clone());
+            }
+        }
+        for handle in registry.functions() {
+            let signature = handle.signature();
+            if self.is_module_allowed(signature.module_name.as_deref())
+                && signature
+                    .type_handle
+                    .as_ref()
+                    .map(|handle| self.is_type_allowed(handle.module_name()))
+                    .unwrap_or(true)
+            {
+                result.add_function_handle(handle.clone());
+            }
+        }
+        result
+    }
+
+    pub fn enforce(&self, context: &mut Context) {
+        context.set_custom(
+            CAPABILITIES_CUSTOM,
+            AnkhaCapabilityCheck {
+                capabilities: self.clone(),
+                allowed_modules: self.allowed_modules.iter().cloned().collect(),
+                denied_modules: self.denied_modules.iter().cloned().collect(),
+            },
+        );
+    }
+
+    pub(crate) fn ensure_package_allowed(
+        context: &Context,
+        package: &AnkhaPackage,
+        registry: &Registry,
+    ) -> Result<(), Box<dyn Error>> {
+        match context.custom::<AnkhaCapabilityCheck>(CAPABILITIES_CUSTOM) {
+            Some(check) => check.capabilities.link(package, registry),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn ensure_function_allowed(context: &Context, handle: &FunctionHandle) {
+        let Some(check) = context.custom::<AnkhaCapabilityCheck>(CAPABILITIES_CUSTOM) else {
+            return;
+        };
+        let signature = handle.signature();
+        if !check.is_module_allowed(signature.module_name.as_deref())
+            || !signature
+                .type_handle
+                .as_ref()
+                .map(|handle| {
+                    handle.module_name().is_none() || check.is_module_allowed(handle.module_name())
+                })
+                .unwrap_or(true)
+        {
+            panic!(
+                "Function `{}::{}` is not allowed by capabilities!",
+                signature.module_name.as_deref().unwrap_or_default(),
+                signature.name
+            );
+        }
+    }
+
+    // Checks if package uses only allowed items of registry it will be linked with.
+    pub fn link(&self, package: &AnkhaPackage, registry: &Registry) -> Result<(), Box<dyn Error>> {
+        package.validate()?;
+        let mut references = vec![];
+        for module in package.modules() {
+            AnkhaReference::collect_module(module, &mut references);
+        }
+        let errors = references
+            .into_iter()
+            .filter(|reference| match reference.resolve_module_name(registry) {
+                Some(module_name) if matches!(reference, AnkhaReference::Type(_)) => {
+                    !self.is_type_allowed(module_name.as_deref())
+                }
+                Some(module_name) => !self.is_module_allowed(module_name.as_deref()),
+                None => false,
+            })
+            .map(|reference| format!("Package is not allowed to use {}", reference))
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n").into())
+        }
+    }
+}
+
+const CAPABILITIES_CUSTOM: &str = "ankha/capabilities";
+
+// Capability policy enforced in context, with module names hashed for per-call checks.
+struct AnkhaCapabilityCheck {
+    capabilities: AnkhaCapabilities,
+    allowed_modules: HashSet<String>,
+    denied_modules: HashSet<String>,
+}
+
+impl AnkhaCapabilityCheck {
+    fn is_module_allowed(&self, module_name: Option<&str>) -> bool {
+        let Some(module_name) = module_name else {
+            return self.allowed_modules.is_empty();
+        };
+        !self.denied_modules.contains(module_name)
+            && (self.allowed_modules.is_empty() || self.allowed_modules.contains(module_name))
+    }
+}
+
+// Number of contexts running with instruction budget, so execution without one skips
+// budget and task step bookkeeping with single atomic load.
+static METERED_CONTEXTS: AtomicUsize = AtomicUsize::new(0);
+
+const FUEL_CUSTOM: &str = "ankha/fuel";
+
+// Instruction budget of task running in context, every evaluated expression consumes
+// one. Without it execution is unlimited.
+struct AnkhaFuel {
+    remaining: Option<usize>,
+    // Set when nested function call ran out of budget, until budget is set again.
+    exhausted: bool,
+}
+
+impl AnkhaFuel {
+    fn set(context: &mut Context, fuel: Option<usize>) -> Option<usize> {
+        let previous = match context.custom_mut::<Self>(FUEL_CUSTOM) {
+            Some(state) => {
+                state.exhausted = false;
+                std::mem::replace(&mut state.remaining, fuel)
+            }
+            None => {
+                context.set_custom(
+                    FUEL_CUSTOM,
+                    Self {
+                        remaining: fuel,
+                        exhausted: false,
+                    },
+                );
+                None
+            }
+        };
+        match (previous, fuel) {
+            (None, Some(_)) => {
+                METERED_CONTEXTS.fetch_add(1, AtomicOrdering::Relaxed);
+            }
+            (Some(_), None) => {
+                METERED_CONTEXTS.fetch_sub(1, AtomicOrdering::Relaxed);
+            }
+            _ => {}
+        }
+        previous
+    }
+
+    fn is_metered(context: &Context) -> bool {
+        METERED_CONTEXTS.load(AtomicOrdering::Relaxed) > 0 && Self::remaining(context).is_some()
+    }
+
+    fn remaining(context: &Context) -> Option<usize> {
+        context.custom::<Self>(FUEL_CUSTOM)?.remaining
+    }
+
+    fn exhausted(context: &Context) -> bool {
+        context
+            .custom::<Self>(FUEL_CUSTOM)
+            .is_some_and(|state| state.exhausted)
+    }
+
+    // Tasks stop before budget runs out, so running out of it means execution
+    // cannot be suspended and has to fail.
+    fn consume(context: &mut Context) {
+        let Some(state) = context.custom_mut::<Self>(FUEL_CUSTOM) else {
+            return;
+        };
+        match state.remaining {
+            Some(0) => {
+                state.exhausted = true;
+                resume_unwind(Box::new(AnkhaError::BudgetExhausted { backtrace: vec![] }))
+            }
+            Some(fuel) => state.remaining = Some(fuel - 1),
+            None => {}
+        }
+    }
+}
+
+// Runs callback and catches panic raised inside, so it can become script error.
+// Panic hook belongs to host, so it still reports panics caught this way.
+fn catch_panic<R>(callback: impl FnOnce() -> R) -> Result<R, Box<dyn Any + Send>> {
+    catch_unwind(AssertUnwindSafe(callback))
+}
+
+const TRY_CUSTOM: &str = "ankha/try";
+
+// Try scope being executed, innermost goes last.
+struct AnkhaTry {
+    state: AnkhaContextState,
+    error: Option<AnkhaError>,
+}
+
+impl AnkhaTry {
+    fn count(context: &Context) -> usize {
+        context
+            .custom::<Vec<Self>>(TRY_CUSTOM)
+            .map(|tries| tries.len())
+            .unwrap_or_default()
+    }
+
+    fn truncate(context: &mut Context, count: usize) {
+        if let Some(tries) = context.custom_mut::<Vec<Self>>(TRY_CUSTOM) {
+            tries.truncate(count);
+        }
+    }
+
+    fn enter(context: &mut Context) {
+        let state = AnkhaContextState::new(context);
+        if context.custom::<Vec<Self>>(TRY_CUSTOM).is_none() {
+            context.set_custom::<Vec<Self>>(TRY_CUSTOM, vec![]);
+        }
+        context
+            .custom_mut::<Vec<Self>>(TRY_CUSTOM)
+            .unwrap()
+            .push(Self { state, error: None });
+    }
+
+    // Try scopes entered by calls that failed are gone, error goes to the one that
+    // was innermost when failed expression started.
+    fn throw(context: &mut Context, count: usize, error: AnkhaError) {
+        Self::truncate(context, count);
+        context
+            .custom_mut::<Vec<Self>>(TRY_CUSTOM)
+            .and_then(|tries| tries.last_mut())
+            .expect("Guarded expression is not inside try scope!")
+            .error = Some(error);
+        AnkhaJump::Throw.start(context);
+    }
+
+    // Caught error is returned once scopes of try body got unwound.
+    fn exit(context: &mut Context) -> Option<AnkhaError> {
+        let item = context
+            .custom_mut::<Vec<Self>>(TRY_CUSTOM)
+            .and_then(|tries| tries.pop())
+            .expect("There is no try scope to exit!");
+        if AnkhaJump::pending(context) != Some(&AnkhaJump::Throw) {
+            return None;
+        }
+        item.state.restore(context);
+        item.error
+    }
+}
+
+// Script error that unwinds to nearest catch scope.
+// Backtrace lists names of script functions being executed when it was raised,
+// outermost first.
+pub enum AnkhaError {
+    // Owned value thrown by script.
+    Thrown {
+        value: DynamicManaged,
+        backtrace: Vec<Arc<str>>,
+    },
+    // Runtime failure message.
+    Panic {
+        message: String,
+        backtrace: Vec<Arc<str>>,
+    },
+    // Instruction budget ran out where execution cannot be suspended.
+    BudgetExhausted {
+        backtrace: Vec<Arc<str>>,
+    },
+}
+
+impl AnkhaError {
+    // Runs callback and catches errors thrown or raised inside, restoring stack,
+    // registers and call stack to state from before the call.
+    // Hosts use it as entry point to get uncaught errors reported.
+    pub fn catch<R>(
+        context: &mut Context,
+        callback: impl FnOnce(&mut Context) -> R,
+    ) -> Result<R, Self> {
+        let state = AnkhaContextState::new(context);
+        let payload = match catch_panic(|| AnkhaCallStack::guard(context, callback)) {
+            Ok(result) => return Ok(result),
+            Err(payload) => payload,
+        };
+        // Exhausted budget has to reach task that runs the script.
+        if AnkhaFuel::exhausted(context) {
+            resume_unwind(payload);
+        }
+        Err(state.recover(context, payload))
+    }
+
+    pub fn invoke(
+        function: &CoreFunction,
+        context: &mut Context,
+        registry: &Registry,
+    ) -> Result<(), Self> {
+        Self::catch(context, |context| function.invoke(context, registry))
+    }
+
+    // Invokes function with instruction budget, so runaway script cannot freeze host.
+    // Call that runs out of it fails with `BudgetExhausted` error, use `AnkhaTask`
+    // to suspend and resume execution instead.
+    pub fn invoke_with_budget(
+        function: &CoreFunction,
+        context: &mut Context,
+        registry: &Registry,
+        fuel: usize,
+    ) -> Result<(), Self> {
+        let state = AnkhaContextState::new(context);
+        let previous = AnkhaFuel::set(context, Some(fuel));
+        let result = catch_panic(|| {
+            AnkhaCallStack::guard(context, |context| function.invoke(context, registry))
+        });
+        AnkhaFuel::set(context, previous);
+        result.map_err(|payload| state.recover(context, payload))
+    }
+
+    // Errors raised without backtrace get given one.
+    fn from_payload(payload: Box<dyn Any + Send>, frames: Vec<Arc<str>>) -> Self {
+        let mut result = match payload.downcast::<Self>() {
+            Ok(error) => *error,
+            Err(payload) => Self::Panic {
+                message: payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "Unknown script failure!".to_owned()),
+                backtrace: vec![],
+            },
+        };
+        let backtrace = match &mut result {
+            Self::Thrown { backtrace, .. }
+            | Self::Panic { backtrace, .. }
+            | Self::BudgetExhausted { backtrace } => backtrace,
+        };
+        if backtrace.is_empty() {
+            *backtrace = frames;
+        }
+        result
+    }
+
+    pub fn backtrace(&self) -> &[Arc<str>] {
+        match self {
+            Self::Thrown { backtrace, .. }
+            | Self::Panic { backtrace, .. }
+            | Self::BudgetExhausted { backtrace } => backtrace,
+        }
+    }
+
+    // Runtime failures are caught as owned `String` message.
+    pub fn into_value(self) -> DynamicManaged {
+        match self {
+            Self::Thrown { value, .. } => value,
+            Self::Panic { message, .. } => DynamicManaged::new(message).ok().unwrap(),
+            Self::BudgetExhausted { .. } => DynamicManaged::new(self.to_string()).ok().unwrap(),
+        }
+    }
+}
+
+// Stack, registers and try scopes state that context gets restored to when script fails.
+struct AnkhaContextState {
+    position: usize,
+    registers: usize,
+    barriers: usize,
+    tries: usize,
+}
+
+impl AnkhaContextState {
+    fn new(context: &mut Context) -> Self {
+        Self {
+            position: context.stack().position(),
+            registers: context.registers().registers_count(),
+            barriers: context.registers_barriers().len(),
+            tries: AnkhaTry::count(context),
+        }
+    }
+
+    fn recover(&self, context: &mut Context, payload: Box<dyn Any + Send>) -> AnkhaError {
+        self.restore(context);
+        AnkhaError::from_payload(payload, vec![])
+    }
+
+    fn restore(&self, context: &mut Context) {
+        AnkhaTry::truncate(context, self.tries);
+        while context.registers_barriers().len() > self.barriers {
+            context.restore_registers();
+        }
+        while context.registers().registers_count() > self.registers {
+            context.registers().drop_register();
+        }
+        while context.stack().position() > self.position {
+            if !context.stack().drop() {
+                break;
+            }
+        }
+        AnkhaJump::finish(context);
+    }
+}
+
+impl std::fmt::Display for AnkhaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Thrown { value, .. } => match value.read::<String>() {
+                Some(message) => write!(f, "Uncaught script error: {}", *message)?,
+                None => write!(f, "Uncaught script error of {:?} type", value.type_hash())?,
+            },
+            Self::Panic { message, .. } => write!(f, "Script failure: {}", message)?,
+            Self::BudgetExhausted { .. } => write!(f, "Script instruction budget exhausted")?,
+        }
+        if !self.backtrace().is_empty() {
+            write!(f, "\n{}", backtrace(self.backtrace()))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for AnkhaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Thrown { value, backtrace } => f
+                .debug_struct("Thrown")
+                .field("value", &value.type_hash())
+                .field("backtrace", backtrace)
+                .finish(),
+            Self::Panic { message, backtrace } => f
+                .debug_struct("Panic")
+                .field("message", message)
+                .field("backtrace", backtrace)
+                .finish(),
+            Self::BudgetExhausted { backtrace } => f
+                .debug_struct("BudgetExhausted")
+                .field("backtrace", backtrace)
+                .finish(),
+        }
+    }
+}
+
+impl Error for AnkhaError {}
+
+const JUMP_CUSTOM: &str = "ankha/jump";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AnkhaJump {
+    Break(Option<String>),
+    Continue(Option<String>),
+    Return,
+    // Error caught by guarded expression, finished by try scope.
+    Throw,
+}
+
+impl AnkhaJump {
+    fn start(self, context: &mut Context) {
+        context.set_custom(JUMP_CUSTOM, Some(self));
+    }
+
+    fn pending(context: &Context) -> Option<&Self> {
+        context.custom::<Option<Self>>(JUMP_CUSTOM)?.as_ref()
+    }
+
+    fn finish(context: &mut Context) {
+        context.set_custom::<Option<Self>>(JUMP_CUSTOM, None);
+    }
+
+    // Unlabeled jump targets innermost loop, which is the first one to check it.
+    fn targets(target: &Option<String>, label: Option<&str>) -> bool {
+        target.is_none() || target.as_deref() == label
+    }
+}
+
+const CALL_STACK_CUSTOM: &str = "ankha/call_stack";
+
+// Names of script functions being executed in context, outermost first.
+#[derive(Debug, Default, Clone)]
+pub struct AnkhaCallStack {
+    pub frames: Vec<Arc<str>>,
+    // Without it call depth is unlimited, which is the default - deep recursion then
+    // ends only when context or native stack overflows.
+    pub max_depth: Option<usize>,
+}
+
+impl AnkhaCallStack {
+    pub fn current(context: &Context) -> Self {
+        context
+            .custom::<Self>(CALL_STACK_CUSTOM)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn depth(context: &Context) -> usize {
+        context
+            .custom::<Self>(CALL_STACK_CUSTOM)
+            .map(|call_stack| call_stack.frames.len())
+            .unwrap_or_default()
+    }
+
+    pub fn set_max_depth(context: &mut Context, max_depth: Option<usize>) {
+        Self::get_mut(context).max_depth = max_depth;
+    }
+
+    fn get_mut(context: &mut Context) -> &mut Self {
+        if context.custom::<Self>(CALL_STACK_CUSTOM).is_none() {
+            context.set_custom(CALL_STACK_CUSTOM, Self::default());
+        }
+        context.custom_mut::<Self>(CALL_STACK_CUSTOM).unwrap()
+    }
+
+    fn push(context: &mut Context, name: &Arc<str>) {
+        let call_stack = Self::get_mut(context);
+        if let Some(max_depth) = call_stack.max_depth
+            && call_stack.frames.len() >= max_depth
+        {
+            resume_unwind(Box::new(AnkhaError::Panic {
+                message: format!(
+                    "Script call stack exceeded maximum depth of {} at `{}`!",
+                    max_depth, name
+                ),
+                backtrace: vec![],
+            }));
+        }
+        call_stack.frames.push(name.clone());
+    }
+
+    fn pop(context: &mut Context) {
+        if let Some(call_stack) = context.custom_mut::<Self>(CALL_STACK_CUSTOM) {
+            call_stack.frames.pop();
+        }
+    }
+
+    fn extend(context: &mut Context, frames: Vec<Arc<str>>) {
+        if !frames.is_empty() {
+            Self::get_mut(context).frames.extend(frames);
+        }
+    }
+
+    // Removes frames above depth and returns whole call stack from before removal.
+    fn truncate(context: &mut Context, depth: usize) -> Vec<Arc<str>> {
+        let Some(call_stack) = context.custom_mut::<Self>(CALL_STACK_CUSTOM) else {
+            return vec![];
+        };
+        let frames = call_stack.frames.clone();
+        call_stack.frames.truncate(depth);
+        frames
+    }
+
+    fn split_off(context: &mut Context, depth: usize) -> Vec<Arc<str>> {
+        let Some(call_stack) = context.custom_mut::<Self>(CALL_STACK_CUSTOM) else {
+            return vec![];
+        };
+        let depth = depth.min(call_stack.frames.len());
+        call_stack.frames.split_off(depth)
+    }
+
+    pub fn backtrace(&self) -> String {
+        backtrace(&self.frames)
+    }
+
+    // Runs callback and turns panic raised inside into `AnkhaError` that carries
+    // script backtrace. Frames of calls that did not return are removed before
+    // error continues to unwind.
+    pub fn guard<R>(context: &mut Context, callback: impl FnOnce(&mut Context) -> R) -> R {
+        let depth = Self::depth(context);
+        let payload = match catch_unwind(AssertUnwindSafe(|| callback(context))) {
+            Ok(result) => return result,
+            Err(payload) => payload,
+        };
+        let frames = Self::truncate(context, depth);
+        resume_unwind(Box::new(AnkhaError::from_payload(payload, frames)))
+    }
+}
+
+// Innermost frame goes first.
+fn backtrace(frames: &[Arc<str>]) -> String {
+    let mut result = "Ankha backtrace:".to_owned();
+    for (index, name) in frames.iter().rev().enumerate() {
+        result.push_str(&format!("\n  {}: {}", index, name));
+    }
+    result
+}
+
+const TASK_CUSTOM: &str = "ankha/task";
+
+// Task bookkeeping of context, so script functions called by task run as its frames.
+#[derive(Default)]
+struct AnkhaTaskState {
+    // Set by task for expression its step evaluates, so script function called by it
+    // directly runs as task frame instead of on native stack.
+    step: bool,
+    // Function handle key of script function called by task step.
+    call_target: Option<usize>,
+    // Call deferred by script function to task that called it.
+    call: Option<AnkhaTaskCall>,
+}
+
+impl AnkhaTaskState {
+    fn get_mut(context: &mut Context) -> &mut Self {
+        if context.custom::<Self>(TASK_CUSTOM).is_none() {
+            context.set_custom(TASK_CUSTOM, Self::default());
+        }
+        context.custom_mut::<Self>(TASK_CUSTOM).unwrap()
+    }
+
+    fn set_step(context: &mut Context, step: bool) {
+        if step {
+            Self::get_mut(context).step = true;
+        } else if let Some(state) = context.custom_mut::<Self>(TASK_CUSTOM) {
+            state.step = false;
+        }
+    }
+
+    fn take_step(context: &mut Context) -> bool {
+        context
+            .custom_mut::<Self>(TASK_CUSTOM)
+            .is_some_and(|state| std::mem::take(&mut state.step))
+    }
+
+    fn take_call(context: &mut Context) -> Option<AnkhaTaskCall> {
+        context.custom_mut::<Self>(TASK_CUSTOM)?.call.take()
+    }
+}
+
+struct AnkhaTaskCall {
+    script: ScriptHandle<'static, AnkhaExpression>,
+    // Try scopes count of try scope that guards the call.
+    guard: Option<usize>,
+}
+
+impl AnkhaTaskCall {
+    fn invoke(handle: &FunctionHandle, direct: bool, context: &mut Context, registry: &Registry) {
+        if direct {
+            AnkhaTaskState::get_mut(context).call_target = Some(AnkhaFunctionBody::key(handle));
+        }
+        handle.invoke(context, registry);
+        if let Some(state) = context.custom_mut::<AnkhaTaskState>(TASK_CUSTOM) {
+            state.call_target = None;
+        }
+    }
+
+    // Native functions called by task step do not match its target, so script
+    // functions they call run on native stack.
+    fn targets(context: &mut Context, key: Option<usize>) -> bool {
+        let Some(state) = context.custom_mut::<AnkhaTaskState>(TASK_CUSTOM) else {
+            return false;
+        };
+        let result = key.is_some() && state.call_target == key;
+        if result {
+            state.call_target = None;
+        }
+        result
+    }
+
+    fn defer(context: &mut Context, script: ScriptHandle<'static, AnkhaExpression>) {
+        AnkhaTaskState::get_mut(context).call = Some(Self {
+            script,
+            guard: None,
+        });
+    }
+
+    fn guard(context: &mut Context, tries: usize) {
+        if let Some(state) = context.custom_mut::<AnkhaTaskState>(TASK_CUSTOM)
+            && let Some(call) = &mut state.call
+        {
+            call.guard = Some(tries);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnkhaTaskStatus {
+    Completed,
+    BudgetExhausted,
+}
+
+struct AnkhaTaskFrame {
+    scope: VmScope<'static, AnkhaExpression>,
+    // Call stack depth relative to task when frame was called.
+    depth: usize,
+    // Try scopes count of caller try scope that catches errors of this frame.
+    guard: Option<usize>,
+}
+
+// Resumable execution of script with instruction budget, used to time-slice scripts.
+// Script functions called by task script run as its frames, so they get suspended with
+// it. Calls made by native functions run on native stack and cannot be suspended, so
+// when they exhaust budget the task fails with `BudgetExhausted` error.
+pub struct AnkhaTask {
+    frames: Vec<AnkhaTaskFrame>,
+    debugger: Option<VmDebuggerHandle<AnkhaExpression>>,
+    // Call stack frames of suspended task.
+    calls: Vec<Arc<str>>,
+    state: Option<AnkhaContextState>,
+    completed: bool,
+}
+
+impl AnkhaTask {
+    pub fn new(script: ScriptHandle<'static, AnkhaExpression>) -> Self {
+        Self {
+            frames: vec![AnkhaTaskFrame {
+                scope: VmScope::new(script, Default::default()),
+                depth: 0,
+                guard: None,
+            }],
+            debugger: None,
+            calls: vec![],
+            state: None,
+            completed: false,
+        }
+    }
+
+    pub fn with_debugger(mut self, debugger: Option<VmDebuggerHandle<AnkhaExpression>>) -> Self {
+        self.frames = self
+            .frames
+            .into_iter()
+            .map(|frame| AnkhaTaskFrame {
+                scope: frame.scope.with_debugger(debugger.clone()),
+                ..frame
+            })
+            .collect();
+        self.debugger = debugger;
+        self
+    }
+
+    pub fn from_function(function: &AnkhaFunction, module_name: &str) -> Self {
+        if let Some(expression) = find_internal_expression(&function.script) {
+            panic!(
+                "Function `{}` uses internal expression: {:?}",
+                function.name, expression
+            );
+        }
+        Self::new(function.build_script(module_name))
+    }
+
+    pub fn from_package(package: &AnkhaPackage, name: &str, module_name: &str) -> Option<Self> {
+        package
+            .modules()
+            .filter(|module| module.name == module_name)
+            .flat_map(|module| module.functions.iter())
+            .find(|function| function.name == name && function.type_name_module.is_none())
+            .map(|function| Self::from_function(function, module_name))
+    }
+
+    pub fn has_completed(&self) -> bool {
+        self.completed
+    }
+
+    // Inputs have to be pushed on stack before first resume, outputs are on stack
+    // once it reports completion. Failed task is completed and context is restored
+    // to state from before its first resume.
+    pub fn resume(
+        &mut self,
+        context: &mut Context,
+        registry: &Registry,
+        fuel: usize,
+    ) -> Result<AnkhaTaskStatus, AnkhaError> {
+        if self.completed {
+            return Ok(AnkhaTaskStatus::Completed);
+        }
+        if self.state.is_none() {
+            self.state = Some(AnkhaContextState::new(context));
+            context.store_registers();
+        }
+        let depth = AnkhaCallStack::depth(context);
+        AnkhaCallStack::extend(context, std::mem::take(&mut self.calls));
+        let previous = AnkhaFuel::set(context, Some(fuel));
+        let result = loop {
+            match catch_panic(|| self.run(context, registry, depth)) {
+                Ok(status) => break Ok(status),
+                Err(payload) => {
+                    AnkhaTaskState::set_step(context, false);
+                    AnkhaTaskState::take_call(context);
+                    if let Err(payload) = self.catch(context, payload, depth) {
+                        break Err(payload);
+                    }
+                }
+            }
+        };
+        AnkhaFuel::set(context, previous);
+        match result {
+            Ok(status) => {
+                self.calls = AnkhaCallStack::split_off(context, depth);
+                if status == AnkhaTaskStatus::Completed {
+                    context.restore_registers();
+                    self.completed = true;
+                }
+                Ok(status)
+            }
+            Err(payload) => {
+                self.completed = true;
+                self.frames.clear();
+                let error =
+                    AnkhaError::from_payload(payload, AnkhaCallStack::truncate(context, depth));
+                let state = self.state.take().unwrap();
+                Err(state.recover(context, Box::new(error)))
+            }
+        }
+    }
+
+    fn run(&mut self, context: &mut Context, registry: &Registry, depth: usize) -> AnkhaTaskStatus {
+        while AnkhaFuel::remaining(context).unwrap_or_default() > 0 {
+            let frame = self.frames.last_mut().unwrap();
+            AnkhaTaskState::set_step(context, true);
+            let running = frame.scope.step(context, registry);
+            AnkhaTaskState::set_step(context, false);
+            if let Some(call) = AnkhaTaskState::take_call(context) {
+                // Registers of called function are separated like native call does.
+                context.store_registers();
+                self.frames.push(AnkhaTaskFrame {
+                    scope: VmScope::new(call.script, Default::default())
+                        .with_debugger(self.debugger.clone()),
+                    depth: AnkhaCallStack::depth(context) - depth,
+                    guard: call.guard,
+                });
+            } else if !running {
+                if self.frames.len() == 1 {
+                    return AnkhaTaskStatus::Completed;
+                }
+                self.frames.pop();
+                context.restore_registers();
+            }
+        }
+        AnkhaTaskStatus::BudgetExhausted
+    }
+
+    // Error of frame called inside try scope is thrown to that scope, frames above
+    // caller get dropped like native stack would unwind. Caller try scope restores
+    // registers of dropped frames once it catches the error.
+    fn catch(
+        &mut self,
+        context: &mut Context,
+        payload: Box<dyn Any + Send>,
+        depth: usize,
+    ) -> Result<(), Box<dyn Any + Send>> {
+        if AnkhaFuel::exhausted(context) {
+            return Err(payload);
+        }
+        let Some(index) = self.frames.iter().rposition(|frame| frame.guard.is_some()) else {
+            return Err(payload);
+        };
+        let frame = self.frames.split_off(index).swap_remove(0);
+        let frames = AnkhaCallStack::truncate(context, depth + frame.depth);
+        AnkhaTry::throw(
+            context,
+            frame.guard.unwrap(),
+            AnkhaError::from_payload(payload, frames),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        library::{AnkhaScriptBuilder, AnkhaVmScope},
+        parser::AnkhaContentParser,
+    };
+    use intuicio_core::prelude::*;
+    use intuicio_data::prelude::*;
+    use intuicio_derive::*;
+
+    pub struct LexprContentParser;
+
+    impl BytesContentParser<AnkhaFile> for LexprContentParser {
+        fn parse(&self, bytes: Vec<u8>) -> Result<AnkhaFile, Box<dyn Error>> {
+            let content = String::from_utf8(bytes)?;
+            Ok(serde_lexpr::from_str::<AnkhaFile>(&content)?)
+        }
+    }
+
+    #[intuicio_function(
+        transformer = "DynamicManagedValueTransformer",
+        module_name = "intrinsics"
+    )]
+    fn add(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    #[intuicio_function(
+        transformer = "DynamicManagedValueTransformer",
+        module_name = "intrinsics"
+    )]
+    fn sub(a: i32, b: i32) -> i32 {
+        a - b
+    }
+
+    #[intuicio_function(
+        transformer = "DynamicManagedValueTransformer",
+        module_name = "intrinsics"
+    )]
+    fn mul(a: i32, b: i32) -> i32 {
+        a * b
+    }
+
+    #[intuicio_function(
+        transformer = "DynamicManagedValueTransformer",
+        module_name = "intrinsics"
+    )]
+    fn eq(a: i32, b: i32) -> bool {
+        a == b
+    }
+
+    #[intuicio_function(module_name = "intrinsics")]
+    fn unmanaged_add(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    #[derive(IntuicioStruct)]
+    struct Foo {
+        pub a: DynamicManaged,
+        pub b: DynamicManagedBox,
+    }
+
+    impl Default for Foo {
+        fn default() -> Self {
+            let a = DynamicManaged::new(40).unwrap();
+            let b = DynamicManagedBox::new(2);
+            Self { a, b }
+        }
+    }
+
+    #[test]
+    fn test_script() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        registry.add_type(Foo::define_struct(&registry));
+        registry.add_function(add::define_function(&registry));
+        registry.add_function(unmanaged_add::define_function(&registry));
+        let mut context = Context::new(10240, 10240);
+
+        let script = AnkhaScriptBuilder::default()
+            .expression(AnkhaExpression::Literal(AnkhaLiteral::I32(40)))
+            .expression(AnkhaExpression::EnsureStackType {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<i32>()),
+                    ..Default::default()
+                },
+            })
+            .expression(AnkhaExpression::Literal(AnkhaLiteral::I32(2)))
+            .expression(AnkhaExpression::EnsureStackType {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<i32>()),
+                    ..Default::default()
+                },
+            })
+            .call_function(FunctionQuery {
+                name: Some("add".into()),
+                ..Default::default()
+            })
+            .expression(AnkhaExpression::EnsureStackType {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<i32>()),
+                    ..Default::default()
+                },
+            })
+            .build();
+        let mut vm = VmScope::new(script, Default::default());
+        vm.run(&mut context, &registry);
+        assert_eq!(
+            context
+                .stack()
+                .pop::<DynamicManaged>()
+                .unwrap()
+                .consume::<i32>()
+                .ok()
+                .unwrap(),
+            42
+        );
+        context.stack().restore(unsafe { DataStackToken::new(0) });
+
+        let script = AnkhaScriptBuilder::default()
+            .expression(AnkhaExpression::Literal(AnkhaLiteral::I32(40)))
+            .expression(AnkhaExpression::EnsureStackType {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<i32>()),
+                    ..Default::default()
+                },
+            })
+            .expression(AnkhaExpression::Unmanage)
+            .expression(AnkhaExpression::Literal(AnkhaLiteral::I32(2)))
+            .expression(AnkhaExpression::EnsureStackType {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<i32>()),
+                    ..Default::default()
+                },
+            })
+            .expression(AnkhaExpression::Unmanage)
+            .call_function(FunctionQuery {
+                name: Some("unmanaged_add".into()),
+                ..Default::default()
+            })
+            .expression(AnkhaExpression::Manage)
+            .expression(AnkhaExpression::EnsureStackType {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<i32>()),
+                    ..Default::default()
+                },
+            })
+            .build();
+        let mut vm = VmScope::new(script, Default::default());
+        vm.run(&mut context, &registry);
+        assert_eq!(
+            context
+                .stack()
+                .pop::<DynamicManaged>()
+                .unwrap()
+                .consume::<i32>()
+                .ok()
+                .unwrap(),
+            42
+        );
+        context.stack().restore(unsafe { DataStackToken::new(0) });
+
+        let script = AnkhaScriptBuilder::default()
+            .expression(AnkhaExpression::Structure {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<Foo>()),
+                    ..Default::default()
+                },
+                fields: Default::default(),
+            })
+            .expression(AnkhaExpression::Borrow)
+            .expression(AnkhaExpression::Swap)
+            .expression(AnkhaExpression::BorrowField {
+                name: "a".to_owned(),
+                kind: AnkhaValueKind::Owned,
+                visibility: None,
+            })
+            .expression(AnkhaExpression::CopyFrom)
+            .expression(AnkhaExpression::EnsureStackType {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<i32>()),
+                    ..Default::default()
+                },
+            })
+            .expression(AnkhaExpression::Literal(AnkhaLiteral::I32(2)))
+            .expression(AnkhaExpression::EnsureStackType {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<i32>()),
+                    ..Default::default()
+                },
+            })
+            .call_function(FunctionQuery {
+                name: Some("add".into()),
+                ..Default::default()
+            })
+            .expression(AnkhaExpression::EnsureStackType {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<i32>()),
+                    ..Default::default()
+                },
+            })
+            .build();
+        let mut vm = VmScope::new(script, Default::default());
+        vm.run(&mut context, &registry);
+        assert_eq!(
+            *context
+                .stack()
+                .pop::<DynamicManaged>()
+                .unwrap()
+                .read::<i32>()
+                .unwrap(),
+            42
+        );
+        context.stack().restore(unsafe { DataStackToken::new(0) });
+
+        let script = AnkhaScriptBuilder::default()
+            .define_register(TypeQuery {
+                type_hash: Some(TypeHash::of::<DynamicManaged>()),
+                ..Default::default()
+            })
+            .expression(AnkhaExpression::Structure {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<Foo>()),
+                    ..Default::default()
+                },
+                fields: Default::default(),
+            })
+            .expression(AnkhaExpression::Borrow)
+            .pop_to_register(0)
+            .expression(AnkhaExpression::EnsureStackType {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<Foo>()),
+                    ..Default::default()
+                },
+            })
+            .expression(AnkhaExpression::BorrowField {
+                name: "a".to_owned(),
+                kind: AnkhaValueKind::Owned,
+                visibility: None,
+            })
+            .expression(AnkhaExpression::CopyFrom)
+            .expression(AnkhaExpression::EnsureStackType {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<i32>()),
+                    ..Default::default()
+                },
+            })
+            .expression(AnkhaExpression::Literal(AnkhaLiteral::I32(2)))
+            .expression(AnkhaExpression::EnsureStackType {
+                type_query: AnkhaTypeQuery {
+                    type_hash: Some(TypeHash::of::<i32>()),
+                    ..Default::default()
+                },
+            })
+            .call_function(FunctionQuery {
+                name: Some("add".into()),
+                ..Default::default()
+            })
+            .push_from_register(0)
+            .expression(AnkhaExpression::BorrowMut)
+            .pop_to_register(0)
+            .expression(AnkhaExpression::BorrowMutField {
+                name: "a".to_owned(),
+                kind: AnkhaValueKind::Owned,
+                visibility: None,
+            })
+            .expression(AnkhaExpression::Swap)
+            .expression(AnkhaExpression::MoveInto)
+            .push_from_register(0)
+            .build();
+        let mut vm = VmScope::new(script, Default::default());
+        vm.run(&mut context, &registry);
+        let foo = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(*foo.read::<Foo>().unwrap().a.read::<i32>().unwrap(), 42);
+        context
+            .registers()
+            .restore(unsafe { DataStackToken::new(0) });
+        context.stack().restore(unsafe { DataStackToken::new(0) });
+    }
+
+    #[test]
+    fn test_frontend_lexpr() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        registry.add_function(add::define_function(&registry));
+        let mut content_provider = FileContentProvider::new("lexpr", LexprContentParser);
+        AnkhaPackage::new("../../resources/package.lexpr", &mut content_provider)
+            .unwrap()
+            .compile()
+            .install::<AnkhaVmScope>(
+                &mut registry,
+                None,
+                // Some(
+                //     crate::library::printables(PrintDebugger::full())
+                //         .basic_printables()
+                //         // .step_through(false)
+                //         .stack_bytes(false)
+                //         .registers_bytes(false)
+                //         .into_handle(),
+                // ),
+            );
+        assert!(
+            registry
+                .find_function(FunctionQuery {
+                    name: Some("main".into()),
+                    module_name: Some("test".into()),
+                    ..Default::default()
+                })
+                .is_some()
+        );
+
+        let mut host = Host::new(Context::new(10240, 10240), RegistryHandle::new(registry));
+        let (result,) = host
+            .call_function::<(DynamicManaged,), _>("main", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(*result.read::<i32>().unwrap(), 42);
+
+        let (result,) = host
+            .call_function::<(DynamicManaged,), _>("main2", "test", None)
+            .unwrap()
+            .run(());
+        let struct_type = host
+            .registry()
+            .find_type(TypeQuery {
+                type_hash: Some(*result.type_hash()),
+                ..Default::default()
+            })
+            .unwrap();
+        let field = struct_type
+            .find_struct_field(StructFieldQuery {
+                name: Some("a".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        let result = unsafe {
+            result
+                .as_ptr_raw()
+                .add(field.address_offset())
+                .cast::<DynamicManaged>()
+                .as_ref()
+                .unwrap()
+        };
+        assert_eq!(*result.read::<i32>().unwrap(), 42);
+    }
+
+    #[test]
+    fn test_frontend_ankha() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        registry.add_function(add::define_function(&registry));
+        registry.add_function(sub::define_function(&registry));
+        registry.add_function(mul::define_function(&registry));
+        registry.add_function(eq::define_function(&registry));
+        let mut content_provider = FileContentProvider::new("ankha", AnkhaContentParser::default());
+        AnkhaPackage::new("../../resources/package.ankha", &mut content_provider)
+            .unwrap()
+            .compile()
+            .install::<AnkhaVmScope>(
+                &mut registry,
+                None,
+                // Some(
+                //     crate::library::printables(PrintDebugger::full())
+                //         .basic_printables()
+                //         // .step_through(false)
+                //         .stack_bytes(false)
+                //         .registers_bytes(false)
+                //         .into_handle(),
+                // ),
+            );
+        assert!(
+            registry
+                .find_function(FunctionQuery {
+                    name: Some("main".into()),
+                    module_name: Some("test".into()),
+                    ..Default::default()
+                })
+                .is_some()
+        );
+        assert!(
+            registry
+                .find_function(FunctionQuery {
+                    name: Some("factorial".into()),
+                    module_name: Some("test".into()),
+                    ..Default::default()
+                })
+                .is_some()
+        );
+        let mut host = Host::new(Context::new(10240, 10240), RegistryHandle::new(registry));
+
+        let (result,) = host
+            .call_function::<(DynamicManaged,), _>("main", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(*result.read::<i32>().unwrap(), 42);
+
+        let (result,) = host
+            .call_function::<(DynamicManaged,), _>("main2", "test", None)
+            .unwrap()
+            .run(());
+        let struct_type = host
+            .registry()
+            .find_type(TypeQuery {
+                type_hash: Some(*result.type_hash()),
+                ..Default::default()
+            })
+            .unwrap();
+        let field = struct_type
+            .find_struct_field(StructFieldQuery {
+                name: Some("a".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        let result = unsafe {
+            result
+                .as_ptr_raw()
+                .add(field.address_offset())
+                .cast::<DynamicManaged>()
+                .as_ref()
+                .unwrap()
+        };
+        assert_eq!(*result.read::<i32>().unwrap(), 42);
+
+        let (result,) = host
+            .call_function::<(DynamicManaged,), _>("factorial", "test", None)
+            .unwrap()
+            .run((DynamicManaged::new(5).ok().unwrap(),));
+        assert_eq!(*result.read::<i32>().unwrap(), 120);
+    }
+
+    #[test]
+    fn test_globals() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (use "z")
+                    (mod "test"
+                        (const "MAX_HP" (lit i32 100))
+                        (global "score" (kind owned) (get_const "MAX_HP"))
+                        (global "lives" (kind box) (lit i32 3) (box))
+                        (global "bonus" (kind owned)
+                            (lit i32 1)
+                            (get_global "base" "late" (kind ref))
+                            (call_method (fn "math::add"))
+                        )
+                        (fn "bonus"
+                            (out "result" (kind ref))
+                            (body (get_global "bonus" (kind ref)))
+                        )
+                        (fn "set_score"
+                            (in "value" (kind owned))
+                            (body
+                                (get_global "score" (kind refmut))
+                                (swap)
+                                (move_into)
+                            )
+                        )
+                        (fn "score"
+                            (out "result" (kind ref))
+                            (body (get_global "score" "test" (kind ref)))
+                        )
+                        (fn "lives"
+                            (out "result" (kind lazy))
+                            (body (get_global "lives" (kind lazy)))
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        // Modules install in file name order, so `other` constant is found first,
+        // while `late` goes before `test` that depends on it.
+        let other = AnkhaContentParser::default()
+            .parse_file_content(r#"(file (mod "other" (const "MAX_HP" (lit i32 1))))"#)
+            .unwrap();
+        let late = AnkhaContentParser::default()
+            .parse_file_content(r#"(file (mod "late" (global "base" (kind owned) (lit i32 10))))"#)
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [
+                ("main".to_owned(), file),
+                ("a".to_owned(), other),
+                ("z".to_owned(), late),
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        // Box globals stay readable from other threads.
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let mut context = Context::new(10240, 10240);
+                AnkhaExpression::get_global(
+                    &mut context,
+                    &registry,
+                    "lives",
+                    Some("test"),
+                    AnkhaValueKind::Ref,
+                );
+                let value = context.stack().pop::<DynamicManagedRef>().unwrap();
+                assert_eq!(*value.read::<i32>().unwrap(), 3);
+            });
+        });
+        // Functions named like globals are not shadowed by global accessors.
+        for name in ["score", "bonus"] {
+            let handle = registry
+                .find_function(FunctionQuery {
+                    name: Some(name.into()),
+                    module_name: Some("test".into()),
+                    ..Default::default()
+                })
+                .unwrap();
+            assert!(handle.signature().meta.is_none());
+        }
+        let mut host = Host::new(context, RegistryHandle::new(registry));
+
+        let (result,) = host
+            .call_function::<(DynamicManagedRef,), _>("bonus", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(*result.read::<i32>().unwrap(), 11);
+        drop(result);
+
+        let (result,) = host
+            .call_function::<(DynamicManagedRef,), _>("score", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(*result.read::<i32>().unwrap(), 100);
+        drop(result);
+
+        host.call_function::<(), _>("set_score", "test", None)
+            .unwrap()
+            .run((DynamicManaged::new(42).ok().unwrap(),));
+        let (result,) = host
+            .call_function::<(DynamicManagedRef,), _>("score", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(*result.read::<i32>().unwrap(), 42);
+
+        let (result,) = host
+            .call_function::<(DynamicManagedLazy,), _>("lives", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(*result.read::<i32>().unwrap(), 3);
+
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file (mod "cycle" (global "value" (kind owned) (get_global "value" (kind ref)))))"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let mut context = Context::new(10240, 10240);
+        let error = AnkhaError::catch(&mut context, |context| {
+            package.install::<AnkhaVmScope>(context, &mut registry, None);
+        })
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Script failure: Global `value` initializer depends on itself!"
+        );
+
+        // Initializer that runs itself again fails instead of deadlocking.
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "cycle"
+                        (global "value" (kind owned) (call_function (fn "init")) (lit i32 0))
+                        (fn "init" (body (call_function (fn "global-init/value"))))
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let error = AnkhaError::catch(&mut context, |context| {
+            package.install::<AnkhaVmScope>(context, &mut registry, None);
+        })
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Global `value` initializer depends on itself!")
+        );
+        assert_eq!(context.stack().position(), 0);
+    }
+
+    #[test]
+    fn test_module_hooks() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let parser = AnkhaContentParser::default();
+        let file_a = parser
+            .parse_file_content(
+                r#"(file
+                    (use "b")
+                    (mod "a"
+                        (init "setup")
+                        (shutdown "teardown")
+                        (fn "setup"
+                            (body (get_global "counter" "b" (kind refmut)) (lit i32 20) (move_into))
+                        )
+                        (fn "teardown"
+                            (body (get_global "counter" "b" (kind refmut)) (lit i32 30) (move_into))
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let file_b = parser
+            .parse_file_content(
+                r#"(file
+                    (mod "b"
+                        (init "setup")
+                        (shutdown "teardown")
+                        (global "counter" (kind owned) (lit i32 0))
+                        (fn "setup"
+                            (body (get_global "counter" (kind refmut)) (lit i32 10) (move_into))
+                        )
+                        (fn "teardown"
+                            (body (get_global "counter" (kind refmut)) (lit i32 40) (move_into))
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("a".to_owned(), file_a), ("b".to_owned(), file_b)]
+                .into_iter()
+                .collect(),
+            order: vec!["b".to_owned(), "a".to_owned()],
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let read_counter = |context: &mut Context, registry: &Registry| {
+            AnkhaExpression::GetGlobal {
+                name: "counter".to_owned(),
+                module_name: Some("b".to_owned()),
+                kind: AnkhaValueKind::Ref,
+            }
+            .evaluate(context, registry);
+            let value = context.stack().pop::<DynamicManagedRef>().unwrap();
+            *value.read::<i32>().unwrap()
+        };
+        assert_eq!(read_counter(&mut context, &registry), 20);
+
+        package.finalize(&mut context, &registry);
+        assert_eq!(read_counter(&mut context, &registry), 40);
+    }
+
+    #[test]
+    fn test_file_order() {
+        let parser = AnkhaContentParser::default();
+        let mut package = AnkhaPackage {
+            files: [
+                (
+                    "main.ankha",
+                    r#"(file (use "b.ankha") (use "lib/c.ankha") (mod "main"))"#,
+                ),
+                ("b.ankha", r#"(file (use "lib/c.ankha") (mod "b"))"#),
+                ("lib/c.ankha", r#"(file (use "d.ankha") (mod "c"))"#),
+                ("lib/d.ankha", r#"(file (mod "d"))"#),
+            ]
+            .into_iter()
+            .map(|(name, content)| (name.to_owned(), parser.parse_file_content(content).unwrap()))
+            .collect(),
+            ..Default::default()
+        };
+        assert_eq!(
+            package.file_order().unwrap(),
+            vec!["lib/d.ankha", "lib/c.ankha", "b.ankha", "main.ankha"]
+        );
+        assert_eq!(
+            package
+                .modules()
+                .map(|module| module.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["d", "c", "b", "main"]
+        );
+
+        package
+            .files
+            .get_mut("lib/d.ankha")
+            .unwrap()
+            .dependencies
+            .push("c.ankha".to_owned());
+        assert_eq!(
+            package.file_order().unwrap_err().to_string(),
+            "Cyclic file dependencies: lib/c.ankha -> lib/d.ankha -> lib/c.ankha"
+        );
+    }
+
+    #[test]
+    fn test_uninstall() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let functions_count = registry.functions().count();
+        let types_count = registry.types().count();
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (struct "Foo" (field "a" (kind owned)))
+                        (const "MAX_HP" (lit i32 100))
+                        (global "score" (kind owned) (lit i32 0))
+                        (global "lives" (kind box) (lit i32 3) (box))
+                        (fn "main" (out "result" (kind owned)) (body (lit i32 42)))
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        assert_eq!(registry.functions().count(), functions_count + 6);
+        assert_eq!(registry.types().count(), types_count + 1);
+        AnkhaExpression::get_global(
+            &mut context,
+            &registry,
+            "lives",
+            Some("test"),
+            AnkhaValueKind::Lazy,
+        );
+        let lives = context.stack().pop::<DynamicManagedLazy>().unwrap();
+        assert_eq!(*lives.read::<i32>().unwrap(), 3);
+
+        // Box globals are released by uninstall.
+        package.uninstall(&mut context, &mut registry);
+        assert!(lives.read::<i32>().is_none());
+        assert_eq!(registry.functions().count(), functions_count);
+        assert_eq!(registry.types().count(), types_count);
+        assert!(
+            registry
+                .find_function(FunctionQuery {
+                    name: Some("main".into()),
+                    module_name: Some("test".into()),
+                    ..Default::default()
+                })
+                .is_none()
+        );
+
+        // Items registered by host under module name of package stay.
+        registry.add_function(add::define_function(&registry));
+        let shared = AnkhaPackage {
+            files: [(
+                "shared".to_owned(),
+                AnkhaContentParser::default()
+                    .parse_file_content(r#"(file (mod "intrinsics" (fn "helper" (body))))"#)
+                    .unwrap(),
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        shared.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        shared.uninstall(&mut context, &mut registry);
+        let intrinsics = |name: &str| {
+            registry.find_function(FunctionQuery {
+                name: Some(name.to_owned().into()),
+                module_name: Some("intrinsics".into()),
                 ..Default::default()
             })
-            .expression(AnkhaExpression::EnsureStackType {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<i32>()),
+        };
+        assert!(intrinsics("helper").is_none());
+        assert!(intrinsics("add").is_some());
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let capabilities = AnkhaCapabilities::default()
+            .allow("option")
+            .allow("array")
+            .allow("reflect")
+            .allow("test")
+            .deny("thread");
+        let parser = AnkhaContentParser::default();
+
+        let file = parser
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (fn "main" (body (call_function (fn "new" "thread"))))
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let error = capabilities.link(&package, &registry).unwrap_err();
+        assert!(error.to_string().contains("not allowed"));
+
+        let file = parser
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (fn "main"
+                            (in "handle" (kind owned))
+                            (body (call_method (fn "join" "thread")))
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let error = capabilities.link(&package, &registry).unwrap_err();
+        assert!(error.to_string().contains("thread :: join"));
+
+        let file = parser
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (fn "main"
+                            (out "result" (kind owned))
+                            (body
+                                (lit string "thread")
+                                (lit string "new")
+                                (call_function (fn "by_name" "reflect" (type "Function")))
+                            )
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        assert!(capabilities.link(&package, &registry).is_ok());
+        let mut sandbox = capabilities.sandbox(&registry);
+        assert!(
+            sandbox
+                .find_function(FunctionQuery {
+                    module_name: Some("thread".into()),
                     ..Default::default()
-                },
+                })
+                .is_none()
+        );
+        assert!(
+            sandbox
+                .find_type(TypeQuery {
+                    module_name: Some("dictionary".into()),
+                    ..Default::default()
+                })
+                .is_none()
+        );
+        assert!(sandbox.find_type(TypeQuery::of::<i32>()).is_some());
+        assert!(!capabilities.is_module_allowed(None));
+        assert!(AnkhaCapabilities::default().is_module_allowed(None));
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut sandbox, None);
+        let (result,) = sandbox
+            .find_function(FunctionQuery {
+                name: Some("main".into()),
+                module_name: Some("test".into()),
+                ..Default::default()
             })
-            .build();
-        let mut vm = VmScope::new(script, Default::default());
-        vm.run(&mut context, &registry);
-        assert_eq!(
-            *context
+            .unwrap()
+            .call::<(DynamicManaged,), ()>(&mut context, &sandbox, (), true);
+        assert!(
+            result
+                .read::<crate::library::option::AnkhaOption>()
+                .unwrap()
+                .is_none()
+        );
+
+        // Function values handed over by host are checked when called.
+        let secret = AnkhaPackage {
+            files: [(
+                "secret".to_owned(),
+                parser
+                    .parse_file_content(r#"(file (mod "secret" (fn "leak" (body))))"#)
+                    .unwrap(),
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        secret.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let leak = registry
+            .find_function(FunctionQuery {
+                name: Some("leak".into()),
+                module_name: Some("secret".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [(
+                "main".to_owned(),
+                parser
+                    .parse_file_content(
+                        r#"(file
+                            (mod "test"
+                                (fn "main" (in "function" (kind owned)) (body (call_indirect)))
+                            )
+                        )"#,
+                    )
+                    .unwrap(),
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let mut sandbox = capabilities.clone().deny("secret").sandbox(&registry);
+        package.install::<AnkhaVmScope>(&mut context, &mut sandbox, None);
+        let main = sandbox
+            .find_function(FunctionQuery {
+                name: Some("main".into()),
+                module_name: Some("test".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        let run = |context: &mut Context| {
+            context
                 .stack()
-                .pop::<DynamicManaged>()
+                .push(DynamicManaged::new(Function(leak.clone())).ok().unwrap());
+            AnkhaError::invoke(&main, context, &sandbox)
+        };
+        assert!(run(&mut context).is_ok());
+        capabilities.clone().deny("secret").enforce(&mut context);
+        let error = run(&mut context).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Function `secret::leak` is not allowed by capabilities!")
+        );
+    }
+
+    #[test]
+    fn test_capabilities_enforced() {
+        use crate::library::{channel::AnkhaReceiver, closure::Closure};
+
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let mut context = Context::new(10240, 10240);
+        let package = |content: &str| AnkhaPackage {
+            files: [(
+                "main".to_owned(),
+                AnkhaContentParser::default()
+                    .parse_file_content(content)
+                    .unwrap(),
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let denied = |result: Result<(), AnkhaError>, function: &str| {
+            let error = result.unwrap_err().to_string();
+            assert!(
+                error.contains(&format!(
+                    "Function `{}` is not allowed by capabilities!",
+                    function
+                )),
+                "{}",
+                error
+            );
+        };
+
+        // Package installed before policy is enforced is checked by every call it makes.
+        package(
+            r#"(file
+                (mod "test"
+                    (fn "call_function" (body (call_function (fn "new" "thread"))))
+                    (fn "call_method"
+                        (in "receiver" (kind owned))
+                        (body (call_method (fn "receive" "channel")))
+                    )
+                    (fn "call_indirect" (in "function" (kind owned)) (body (call_indirect)))
+                )
+            )"#,
+        )
+        .install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        AnkhaCapabilities::default()
+            .deny("thread")
+            .deny("channel")
+            .enforce(&mut context);
+        let find = |name: &str, module_name: &str| {
+            registry
+                .find_function(FunctionQuery {
+                    name: Some(name.into()),
+                    module_name: Some(module_name.into()),
+                    ..Default::default()
+                })
                 .unwrap()
-                .read::<i32>()
-                .unwrap(),
-            42
+        };
+        let thread_new = find("new", "thread");
+
+        denied(
+            AnkhaError::invoke(&find("call_function", "test"), &mut context, &registry),
+            "thread::new",
+        );
+        context
+            .stack()
+            .push(DynamicManaged::new(AnkhaReceiver::default()).ok().unwrap());
+        denied(
+            AnkhaError::invoke(&find("call_method", "test"), &mut context, &registry),
+            "channel::receive",
+        );
+        // Receiver is left on stack, since check fails before call consumes it.
+        assert!(context.stack().drop());
+        context
+            .stack()
+            .push(DynamicManaged::new(Function(thread_new.clone())).ok().unwrap());
+        denied(
+            AnkhaError::invoke(&find("call_indirect", "test"), &mut context, &registry),
+            "thread::new",
+        );
+        denied(
+            AnkhaError::catch(&mut context, |context| {
+                AnkhaExpression::GetFunction {
+                    query: AnkhaFunctionQuery {
+                        name: Some("send".to_owned()),
+                        module_name: Some("channel".to_owned()),
+                        ..Default::default()
+                    },
+                }
+                .evaluate(context, &registry);
+            }),
+            "channel::send",
+        );
+        denied(
+            AnkhaError::catch(&mut context, |context| {
+                Closure::from_function(Function(thread_new)).call(
+                    context,
+                    &registry,
+                    Default::default(),
+                );
+            }),
+            "thread::new",
+        );
+        assert_eq!(context.stack().position(), 0);
+
+        // Package that refers to denied module does not get installed.
+        let error = AnkhaError::catch(&mut context, |context| {
+            package(
+                r#"(file
+                    (mod "other"
+                        (fn "main" (body (call_function (fn "new" "thread"))))
+                    )
+                )"#,
+            )
+            .install::<AnkhaVmScope>(context, &mut registry, None);
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("not allowed"), "{}", error);
+        assert!(
+            registry
+                .find_function(FunctionQuery {
+                    module_name: Some("other".into()),
+                    ..Default::default()
+                })
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_reload() {
+        fn file(content: &str) -> AnkhaFile {
+            AnkhaContentParser::default()
+                .parse_file_content(content)
+                .unwrap()
+        }
+
+        fn package(content: &str) -> AnkhaPackage {
+            AnkhaPackage {
+                files: [("main".to_owned(), file(content))].into_iter().collect(),
+                ..Default::default()
+            }
+        }
+
+        fn call(context: &mut Context, registry: &Registry, name: &str) -> i32 {
+            let (result,) = registry
+                .find_function(FunctionQuery {
+                    name: Some(name.into()),
+                    module_name: Some("test".into()),
+                    ..Default::default()
+                })
+                .unwrap()
+                .call::<(DynamicManaged,), ()>(context, registry, (), true);
+            *result.read::<i32>().unwrap()
+        }
+
+        fn score(context: &mut Context, registry: &Registry) -> i32 {
+            let (result,) = registry
+                .find_function(FunctionQuery {
+                    name: Some("score".into()),
+                    ..Default::default()
+                })
+                .unwrap()
+                .call::<(DynamicManagedRef,), ()>(context, registry, (), true);
+            *result.read::<i32>().unwrap()
+        }
+
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let mut context = Context::new(10240, 10240);
+        let mut installed = AnkhaPackage {
+            files: [
+                (
+                    "main".to_owned(),
+                    file(
+                        r#"(file
+                            (mod "test"
+                                (struct "Foo" (field "a" (kind owned)))
+                                (global "counter" (kind owned) (lit i32 0))
+                                (fn "value" (out "result" (kind owned)) (body (lit i32 1)))
+                                (fn "other" (out "result" (kind owned)) (body (call_function (fn "value"))))
+                                (fn "bump" (body (get_global "counter" (kind refmut)) (lit i32 5) (move_into)))
+                            )
+                        )"#,
+                    ),
+                ),
+                (
+                    "extra".to_owned(),
+                    file(
+                        r#"(file
+                            (mod "extra"
+                                (shutdown "close")
+                                (fn "close" (body (get_global "counter" "test" (kind refmut)) (lit i32 7) (move_into)))
+                            )
+                        )"#,
+                    ),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        installed.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let user = package(
+            r#"(file
+                (mod "user"
+                    (fn "use" (out "result" (kind owned)) (body (call_function (fn "value" "test"))))
+                )
+            )"#,
+        );
+        user.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        assert_eq!(call(&mut context, &registry, "other"), 1);
+        registry
+            .find_function(FunctionQuery {
+                name: Some("bump".into()),
+                ..Default::default()
+            })
+            .unwrap()
+            .invoke(&mut context, &registry);
+
+        // Removed module gets shut down, kept global keeps value its hook sets.
+        installed
+            .reload::<AnkhaVmScope>(
+                package(
+                    r#"(file
+                        (mod "test"
+                            (struct "Foo" (field "a" (kind owned)))
+                            (global "counter" (kind owned) (lit i32 0))
+                            (fn "value" (out "result" (kind owned)) (body (lit i32 2)))
+                            (fn "other" (out "result" (kind owned)) (body (call_function (fn "value"))))
+                            (fn "score" (out "result" (kind ref)) (body (get_global "counter" (kind ref))))
+                        )
+                    )"#,
+                ),
+                &[&user],
+                &mut context,
+                &mut registry,
+                None,
+            )
+            .unwrap();
+        assert_eq!(call(&mut context, &registry, "other"), 2);
+        assert_eq!(score(&mut context, &registry), 7);
+        assert!(
+            registry
+                .find_function(FunctionQuery {
+                    module_name: Some("extra".into()),
+                    ..Default::default()
+                })
+                .is_none()
+        );
+
+        let error = installed
+            .reload::<AnkhaVmScope>(
+                package(
+                    r#"(file
+                        (mod "test"
+                            (struct "Foo" (field "a" (kind owned)))
+                            (global "counter" (kind owned) (lit i32 0))
+                            (fn "other" (out "result" (kind owned)) (body (call_function (fn "value"))))
+                        )
+                    )"#,
+                ),
+                &[&user],
+                &mut context,
+                &mut registry,
+                None,
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("Reference to removed"));
+        assert_eq!(call(&mut context, &registry, "other"), 2);
+
+        // Other installed package still uses removed function, and rejected reload
+        // does not run initializers.
+        let error = installed
+            .reload::<AnkhaVmScope>(
+                package(
+                    r#"(file
+                        (mod "test"
+                            (struct "Foo" (field "a" (kind owned)))
+                            (global "counter" (kind owned) (lit i32 0))
+                            (global "probe" (kind owned)
+                                (get_global "counter" (kind refmut))
+                                (lit i32 99)
+                                (move_into)
+                                (lit i32 0)
+                            )
+                            (fn "other" (out "result" (kind owned)) (body (lit i32 3)))
+                            (fn "score" (out "result" (kind ref)) (body (get_global "counter" (kind ref))))
+                        )
+                    )"#,
+                ),
+                &[&user],
+                &mut context,
+                &mut registry,
+                None,
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("test :: value"));
+        assert_eq!(call(&mut context, &registry, "other"), 2);
+        assert_eq!(score(&mut context, &registry), 7);
+
+        let error = installed
+            .reload::<AnkhaVmScope>(
+                package(
+                    r#"(file
+                        (mod "test"
+                            (struct "Foo" (field "a" (kind owned)) (field "b" (kind owned)))
+                            (global "counter" (kind owned) (lit i32 0))
+                            (fn "value" (out "result" (kind owned)) (body (lit i32 3)))
+                            (fn "other" (out "result" (kind owned)) (body (call_function (fn "value"))))
+                        )
+                    )"#,
+                ),
+                &[&user],
+                &mut context,
+                &mut registry,
+                None,
+            )
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Layout of `test::Foo` struct has changed")
+        );
+
+        // Methods called by name are validated too.
+        let mut methods = package(
+            r#"(file
+                (mod "methods"
+                    (fn "get" (in "self" (kind owned)) (out "result" (kind owned)) (body))
+                )
+            )"#,
+        );
+        methods.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let caller = package(
+            r#"(file
+                (mod "caller"
+                    (fn "call"
+                        (in "value" (kind owned))
+                        (out "result" (kind owned))
+                        (body (call_method (fn "get" "methods")))
+                    )
+                )
+            )"#,
+        );
+        caller.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let error = methods
+            .reload::<AnkhaVmScope>(
+                package(r#"(file (mod "methods"))"#),
+                &[&caller],
+                &mut context,
+                &mut registry,
+                None,
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("methods :: get"));
+    }
+
+    #[test]
+    fn test_reload_in_place() {
+        fn package(value: i32) -> AnkhaPackage {
+            let content = format!(
+                r#"(file
+                    (mod "test"
+                        (init "setup")
+                        (shutdown "teardown")
+                        (global "started" (kind owned) (lit i32 0))
+                        (global "stopped" (kind owned) (lit i32 0))
+                        (fn "setup" (body (get_global "started" (kind refmut)) (lit i32 {value}) (move_into)))
+                        (fn "teardown" (body (get_global "stopped" (kind refmut)) (lit i32 {value}) (move_into)))
+                        (fn "value" (out "result" (kind owned)) (body (lit i32 {value})))
+                    )
+                )"#
+            );
+            let file = AnkhaContentParser::default()
+                .parse_file_content(&content)
+                .unwrap();
+            AnkhaPackage {
+                files: [("main".to_owned(), file)].into_iter().collect(),
+                ..Default::default()
+            }
+        }
+
+        fn read_global(context: &mut Context, registry: &Registry, name: &str) -> i32 {
+            AnkhaExpression::GetGlobal {
+                name: name.to_owned(),
+                module_name: Some("test".to_owned()),
+                kind: AnkhaValueKind::Ref,
+            }
+            .evaluate(context, registry);
+            let value = context.stack().pop::<DynamicManagedRef>().unwrap();
+            *value.read::<i32>().unwrap()
+        }
+
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let mut context = Context::new(10240, 10240);
+        let mut installed = package(1);
+        installed.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let handle = registry
+            .find_function(FunctionQuery {
+                name: Some("value".into()),
+                module_name: Some("test".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        let (result,) = handle.call::<(DynamicManaged,), ()>(&mut context, &registry, (), true);
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 1);
+        assert_eq!(read_global(&mut context, &registry, "started"), 1);
+        assert_eq!(read_global(&mut context, &registry, "stopped"), 0);
+
+        installed
+            .reload::<AnkhaVmScope>(package(2), &[], &mut context, &mut registry, None)
+            .unwrap();
+        let (result,) = handle.call::<(DynamicManaged,), ()>(&mut context, &registry, (), true);
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 2);
+        assert!(Arc::ptr_eq(
+            &handle,
+            &registry
+                .find_function(FunctionQuery {
+                    name: Some("value".into()),
+                    module_name: Some("test".into()),
+                    ..Default::default()
+                })
+                .unwrap()
+        ));
+        assert_eq!(read_global(&mut context, &registry, "started"), 2);
+        assert_eq!(read_global(&mut context, &registry, "stopped"), 1);
+
+        installed.uninstall(&mut context, &mut registry);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            handle.call::<(DynamicManaged,), ()>(&mut context, &registry, (), true)
+        }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_task_budget() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (fn "spin"
+                            (body
+                                (lit bool true)
+                                (stack_unwrap_boolean)
+                                (loop (lit bool true) (stack_unwrap_boolean))
+                            )
+                        )
+                        (fn "guarded"
+                            (body
+                                (lit i32 1)
+                                (try
+                                    (body (call_function (fn "spin")))
+                                    (catch "error" (drop_register "error"))
+                                )
+                            )
+                        )
+                        (fn "main"
+                            (out "result" (kind owned))
+                            (body
+                                (lit i32 1)
+                                (stack_drop)
+                                (lit i32 2)
+                                (stack_drop)
+                                (lit i32 42)
+                            )
+                        )
+                        (fn "outer"
+                            (out "result" (kind owned))
+                            (body (call_function (fn "main")))
+                        )
+                        (fn "thrower" (body (lit string "boom") (throw)))
+                        (fn "caught"
+                            (out "result" (kind owned))
+                            (body
+                                (try
+                                    (body (call_function (fn "thrower")) (lit i32 2))
+                                    (catch "error" (push_from_register "error"))
+                                )
+                            )
+                        )
+                        (fn "suspended"
+                            (out "result" (kind owned))
+                            (body
+                                (try
+                                    (body
+                                        (lit i32 1)
+                                        (stack_drop)
+                                        (lit string "boom")
+                                        (throw)
+                                        (lit i32 2)
+                                    )
+                                    (catch "error" (push_from_register "error"))
+                                )
+                            )
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+
+        let mut task = AnkhaTask::from_package(&package, "spin", "test").unwrap();
+        for _ in 0..3 {
+            assert_eq!(
+                task.resume(&mut context, &registry, 100).unwrap(),
+                AnkhaTaskStatus::BudgetExhausted
+            );
+            assert!(!task.has_completed());
+        }
+
+        let mut task = AnkhaTask::from_package(&package, "main", "test").unwrap();
+        assert_eq!(
+            task.resume(&mut context, &registry, 3).unwrap(),
+            AnkhaTaskStatus::BudgetExhausted
+        );
+        assert_eq!(
+            task.resume(&mut context, &registry, 3).unwrap(),
+            AnkhaTaskStatus::BudgetExhausted
         );
-        context.stack().restore(unsafe { DataStackToken::new(0) });
+        assert_eq!(
+            task.resume(&mut context, &registry, 3).unwrap(),
+            AnkhaTaskStatus::Completed
+        );
+        assert!(task.has_completed());
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 42);
 
-        let script = AnkhaScriptBuilder::default()
-            .define_register(TypeQuery {
-                type_hash: Some(TypeHash::of::<DynamicManaged>()),
+        // Nested calls are suspended with the task.
+        let position = context.stack().position();
+        let mut task = AnkhaTask::from_package(&package, "outer", "test").unwrap();
+        let mut resumes = 1;
+        while task.resume(&mut context, &registry, 2).unwrap() == AnkhaTaskStatus::BudgetExhausted {
+            assert_eq!(AnkhaCallStack::depth(&context), 0);
+            resumes += 1;
+        }
+        assert!(resumes > 2);
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 42);
+        assert_eq!(context.stack().position(), position);
+
+        // Budget exhausted in nested call suspends the task, even inside try scope.
+        let mut task = AnkhaTask::from_package(&package, "guarded", "test").unwrap();
+        let mut task_context = Context::new(10240, 10240);
+        for _ in 0..3 {
+            assert_eq!(
+                task.resume(&mut task_context, &registry, 100).unwrap(),
+                AnkhaTaskStatus::BudgetExhausted
+            );
+            assert!(!task.has_completed());
+        }
+
+        // Error thrown by nested call is caught by try scope of its caller.
+        let mut task = AnkhaTask::from_package(&package, "caught", "test").unwrap();
+        while task.resume(&mut context, &registry, 2).unwrap() == AnkhaTaskStatus::BudgetExhausted {
+        }
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<String>().ok().unwrap(), "boom");
+        assert_eq!(context.stack().position(), position);
+
+        // Calls outside of task can be given budget too.
+        let spin = registry
+            .find_function(FunctionQuery {
+                name: Some("spin".into()),
+                module_name: Some("test".into()),
                 ..Default::default()
             })
-            .expression(AnkhaExpression::Structure {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<Foo>()),
-                    ..Default::default()
-                },
-                fields: Default::default(),
-            })
-            .expression(AnkhaExpression::Borrow)
-            .pop_to_register(0)
-            .expression(AnkhaExpression::EnsureStackType {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<Foo>()),
+            .unwrap();
+        let error =
+            AnkhaError::invoke_with_budget(&spin, &mut context, &registry, 100).unwrap_err();
+        assert!(matches!(error, AnkhaError::BudgetExhausted { .. }));
+        assert!(error.to_string().contains("budget exhausted"));
+        assert_eq!(AnkhaCallStack::depth(&context), 0);
+        assert_eq!(context.stack().position(), position);
+
+        // Try body is suspended like the rest of task script.
+        let mut task = AnkhaTask::from_package(&package, "suspended", "test").unwrap();
+        let mut resumes = 1;
+        while task.resume(&mut context, &registry, 2).unwrap() == AnkhaTaskStatus::BudgetExhausted {
+            resumes += 1;
+        }
+        assert!(resumes > 2);
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<String>().ok().unwrap(), "boom");
+        assert_eq!(context.stack().position(), position);
+    }
+
+    #[test]
+    fn test_call_stack() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (fn "recurse" (body (call_function (fn "recurse")) (lit unit)))
+                        (fn "inner" (body (get_const "MISSING")))
+                        (fn "outer" (body (call_function (fn "inner"))))
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        assert_eq!(AnkhaCallStack::current(&context).max_depth, None);
+        AnkhaCallStack::set_max_depth(&mut context, Some(8));
+
+        let mut run = |name: &str| {
+            let function = registry
+                .find_function(FunctionQuery {
+                    name: Some(name.into()),
+                    module_name: Some("test".into()),
                     ..Default::default()
-                },
-            })
-            .expression(AnkhaExpression::BorrowField {
-                name: "a".to_owned(),
+                })
+                .unwrap();
+            let error = AnkhaError::invoke(&function, &mut context, &registry).unwrap_err();
+            assert_eq!(AnkhaCallStack::depth(&context), 0);
+            error
+        };
+
+        let error = run("recurse");
+        assert!(
+            error
+                .to_string()
+                .contains("maximum depth of 8 at `test::recurse`")
+        );
+        assert!(error.to_string().contains("7: test::recurse"));
+        assert!(!error.to_string().contains("8: test::recurse"));
+        assert_eq!(error.backtrace().len(), 8);
+
+        let error = run("outer");
+        assert!(matches!(
+            &error,
+            AnkhaError::Panic { message, .. } if message == "Could not find `MISSING` constant!"
+        ));
+        assert_eq!(
+            error
+                .backtrace()
+                .iter()
+                .map(|name| name.as_ref())
+                .collect::<Vec<_>>(),
+            vec!["test::outer", "test::inner"]
+        );
+        assert!(
+            error
+                .to_string()
+                .ends_with("Ankha backtrace:\n  0: test::inner\n  1: test::outer")
+        );
+
+        // Limit belongs to context it was set for.
+        assert_eq!(AnkhaCallStack::current(&context).max_depth, Some(8));
+        let other = Context::new(10240, 10240);
+        assert_eq!(AnkhaCallStack::current(&other).max_depth, None);
+    }
+
+    fn install_decrement(registry: &mut Registry) {
+        let parameter = |name: &str| {
+            AnkhaFunctionParameter {
+                meta: None,
+                name: name.to_owned(),
                 kind: AnkhaValueKind::Owned,
-                visibility: None,
-            })
-            .expression(AnkhaExpression::CopyFrom)
-            .expression(AnkhaExpression::EnsureStackType {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<i32>()),
+                type_query: None,
+            }
+            .compile()
+        };
+        let signature = ScriptFunctionSignature {
+            meta: None,
+            name: "decrement".to_owned(),
+            module_name: Some("intrinsics".to_owned()),
+            type_query: None,
+            visibility: Visibility::Public,
+            inputs: vec![parameter("value")],
+            outputs: vec![parameter("result"), parameter("positive")],
+        }
+        .build(registry);
+        registry.add_function(CoreFunction::new(
+            signature,
+            FunctionBody::closure(|context, _| {
+                let value = context
+                    .stack()
+                    .pop::<DynamicManaged>()
+                    .unwrap()
+                    .consume::<i32>()
+                    .ok()
+                    .unwrap();
+                context
+                    .stack()
+                    .push(DynamicManaged::new(value - 1).ok().unwrap());
+                context
+                    .stack()
+                    .push(DynamicManaged::new(value > 1).ok().unwrap());
+            }),
+        ));
+    }
+
+    #[test]
+    fn test_tail_call() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        install_decrement(&mut registry);
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (fn "countdown"
+                            (in "value" (kind owned))
+                            (out "result" (kind owned))
+                            (body
+                                (make_register "value" (kind owned))
+                                (pop_to_register "value")
+                                (push_from_register "value")
+                                (call_function (fn "decrement" "intrinsics"))
+                                (stack_unwrap_boolean)
+                                (branch (success (call_function (fn "countdown"))))
+                            )
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        AnkhaCallStack::set_max_depth(&mut context, Some(1));
+        let mut host = Host::new(context, RegistryHandle::new(registry));
+
+        let (result,) = host
+            .call_function::<(DynamicManaged,), _>("countdown", "test", None)
+            .unwrap()
+            .run((DynamicManaged::new(10000).ok().unwrap(),));
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 0);
+        assert_eq!(AnkhaCallStack::depth(host.context()), 0);
+        assert_eq!(host.context().registers().registers_count(), 0);
+    }
+
+    #[test]
+    fn test_tail_group_order() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (fn "plain"
+                            (out "a" (kind owned))
+                            (out "b" (kind owned))
+                            (out "c" (kind owned))
+                            (body
+                                (group_reversed (lit i32 1) (group_reversed (lit i32 2) (lit i32 3)))
+                            )
+                        )
+                        (fn "returns"
+                            (out "a" (kind owned))
+                            (out "b" (kind owned))
+                            (out "c" (kind owned))
+                            (body
+                                (group_reversed (lit i32 1) (group_reversed (lit i32 2) (lit i32 3)))
+                                (return)
+                            )
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let mut call = |name: &str| {
+            let (a, b, c) = registry
+                .find_function(FunctionQuery {
+                    name: Some(name.into()),
+                    module_name: Some("test".into()),
                     ..Default::default()
-                },
-            })
-            .expression(AnkhaExpression::Literal(AnkhaLiteral::I32(2)))
-            .expression(AnkhaExpression::EnsureStackType {
-                type_query: AnkhaTypeQuery {
-                    type_hash: Some(TypeHash::of::<i32>()),
+                })
+                .unwrap()
+                .call::<(DynamicManaged, DynamicManaged, DynamicManaged), ()>(
+                    &mut context,
+                    &registry,
+                    (),
+                    true,
+                );
+            [a, b, c].map(|value| value.consume::<i32>().ok().unwrap())
+        };
+        assert_eq!(call("plain"), [1, 2, 3]);
+        assert_eq!(call("returns"), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_loop_jumps() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        install_decrement(&mut registry);
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (fn "main"
+                            (out "result" (kind owned))
+                            (body
+                                (make_register "value" (kind owned))
+                                (lit i32 5)
+                                (pop_to_register "value")
+                                (lit bool true)
+                                (stack_unwrap_boolean)
+                                (loop "outer"
+                                    (push_from_register 0)
+                                    (call_function (fn "decrement" "intrinsics"))
+                                    (swap)
+                                    (pop_to_register 0)
+                                    (stack_unwrap_boolean)
+                                    (branch
+                                        (success
+                                            (lit bool true)
+                                            (stack_unwrap_boolean)
+                                            (loop
+                                                (push (break))
+                                                (lit unit)
+                                            )
+                                            (continue)
+                                        )
+                                    )
+                                    (break "outer")
+                                )
+                                (push_from_register "value")
+                            )
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let mut host = Host::new(context, RegistryHandle::new(registry));
+
+        let (result,) = host
+            .call_function::<(DynamicManaged,), _>("main", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 0);
+        assert_eq!(host.context().stack().position(), 0);
+        assert_eq!(host.context().registers().registers_count(), 0);
+
+        let function = AnkhaFunction {
+            meta: None,
+            name: "main".to_owned(),
+            type_name_module: None,
+            visibility: Visibility::Public,
+            inputs: vec![],
+            outputs: vec![],
+            script: vec![AnkhaOperation::LoopScope {
+                script: vec![AnkhaOperation::Break(Some("outer".to_owned()))],
+                label: Some("inner".to_owned()),
+            }],
+        };
+        let result = std::panic::catch_unwind(|| function.compile("test"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_return() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        install_decrement(&mut registry);
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (fn "guard"
+                            (in "value" (kind owned))
+                            (out "result" (kind owned))
+                            (body
+                                (make_register "value" (kind owned))
+                                (pop_to_register "value")
+                                (push_from_register "value")
+                                (call_function (fn "decrement" "intrinsics"))
+                                (swap)
+                                (pop_to_register "value")
+                                (stack_unwrap_boolean)
+                                (branch (failure (lit i32 -1) (return)))
+                                (push_from_register "value")
+                            )
+                        )
+                        (fn "nested"
+                            (out "result" (kind owned))
+                            (body
+                                (lit bool true)
+                                (stack_unwrap_boolean)
+                                (loop
+                                    (push
+                                        (make_register "value" (kind owned))
+                                        (lit i32 7)
+                                        (pop_to_register "value")
+                                        (push_from_register "value")
+                                        (return)
+                                    )
+                                    (lit i32 0)
+                                )
+                                (lit i32 0)
+                            )
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let mut host = Host::new(context, RegistryHandle::new(registry));
+
+        let (result,) = host
+            .call_function::<(DynamicManaged,), _>("guard", "test", None)
+            .unwrap()
+            .run((DynamicManaged::new(5).ok().unwrap(),));
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 4);
+        let (result,) = host
+            .call_function::<(DynamicManaged,), _>("guard", "test", None)
+            .unwrap()
+            .run((DynamicManaged::new(1).ok().unwrap(),));
+        assert_eq!(result.consume::<i32>().ok().unwrap(), -1);
+
+        let (result,) = host
+            .call_function::<(DynamicManaged,), _>("nested", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 7);
+        assert_eq!(host.context().stack().position(), 0);
+        assert_eq!(host.context().registers().registers_count(), 0);
+        assert!(host.context().registers_barriers().is_empty());
+        assert!(AnkhaJump::pending(host.context()).is_none());
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (fn "fail" (body (lit string "boom") (throw)))
+                        (fn "thrown"
+                            (out "result" (kind owned))
+                            (body
+                                (try
+                                    (body (lit i32 1) (lit i32 2) (call_function (fn "fail")))
+                                    (catch "err" (push_from_register "err"))
+                                )
+                            )
+                        )
+                        (fn "runtime"
+                            (out "result" (kind owned))
+                            (body
+                                (try
+                                    (body (lit i32 1) (borrow_mut_field "a" (kind owned)))
+                                    (catch)
+                                )
+                            )
+                        )
+                        (fn "jumps"
+                            (out "result" (kind owned))
+                            (body
+                                (lit bool true)
+                                (stack_unwrap_boolean)
+                                (loop (try (body (break)) (catch)))
+                                (try (body (lit i32 5) (return)) (catch))
+                                (lit i32 0)
+                            )
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        // Try scopes are plain IR, so they survive serialization.
+        let file =
+            serde_lexpr::from_str::<AnkhaFile>(&serde_lexpr::to_string(&file).unwrap()).unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let find = |name: &str| {
+            registry
+                .find_function(FunctionQuery {
+                    name: Some(name.into()),
+                    module_name: Some("test".into()),
                     ..Default::default()
-                },
-            })
-            .call_function(FunctionQuery {
-                name: Some("add".into()),
-                ..Default::default()
-            })
-            .push_from_register(0)
-            .expression(AnkhaExpression::BorrowMut)
-            .pop_to_register(0)
-            .expression(AnkhaExpression::BorrowMutField {
-                name: "a".to_owned(),
-                kind: AnkhaValueKind::Owned,
-                visibility: None,
-            })
-            .expression(AnkhaExpression::Swap)
-            .expression(AnkhaExpression::MoveInto)
-            .push_from_register(0)
-            .build();
-        let mut vm = VmScope::new(script, Default::default());
-        vm.run(&mut context, &registry);
-        let foo = context.stack().pop::<DynamicManaged>().unwrap();
-        assert_eq!(*foo.read::<Foo>().unwrap().a.read::<i32>().unwrap(), 42);
-        context
-            .registers()
-            .restore(unsafe { DataStackToken::new(0) });
-        context.stack().restore(unsafe { DataStackToken::new(0) });
+                })
+                .unwrap()
+        };
+
+        AnkhaError::invoke(&find("thrown"), &mut context, &registry).unwrap();
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<String>().ok().unwrap(), "boom");
+        assert_eq!(context.stack().position(), 0);
+
+        AnkhaError::invoke(&find("runtime"), &mut context, &registry).unwrap();
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(
+            result.consume::<String>().ok().unwrap(),
+            "Cannot borrow field mutably from owned stack value!"
+        );
+
+        AnkhaError::invoke(&find("jumps"), &mut context, &registry).unwrap();
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 5);
+        assert_eq!(context.stack().position(), 0);
+
+        let error = AnkhaError::invoke(&find("fail"), &mut context, &registry).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Uncaught script error: boom\nAnkha backtrace:\n  0: test::fail"
+        );
+        assert_eq!(context.stack().position(), 0);
+        assert_eq!(context.registers().registers_count(), 0);
+        assert!(context.registers_barriers().is_empty());
+        assert_eq!(AnkhaCallStack::depth(&context), 0);
+
+        // Expressions made by lowering cannot be forged in IR.
+        assert!(serde_lexpr::from_str::<AnkhaExpression>("StackDrop").is_ok());
+        assert!(serde_lexpr::from_str::<AnkhaExpression>("TryEnter").is_err());
+        assert!(serde_lexpr::to_string(&AnkhaExpression::PopCallFrame).is_err());
+        let mut file = AnkhaContentParser::default()
+            .parse_file_content(r#"(file (mod "forged" (fn "main" (body))))"#)
+            .unwrap();
+        file.modules[0].functions[0]
+            .script
+            .push(AnkhaOperation::LoopScope {
+                script: vec![AnkhaOperation::Expression(AnkhaExpression::PopCallFrame)],
+                label: None,
+            });
+        let package = AnkhaPackage {
+            files: [("forged".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        assert_eq!(
+            package.validate().unwrap_err().to_string(),
+            "Module `forged` uses internal expression: PopCallFrame"
+        );
+        assert!(
+            AnkhaCapabilities::default()
+                .link(&package, &registry)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_enum_match() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (enum "Shape"
+                            (var "Empty")
+                            (var "Pair" (field "a" (kind owned)) (field "b" (kind owned)))
+                        )
+                        (fn "unpack"
+                            (in "value" (kind owned))
+                            (out "result" (kind owned))
+                            (body
+                                (match
+                                    (case "Empty" (lit i32 0))
+                                    (case "Pair" (swap) (stack_drop))
+                                )
+                            )
+                        )
+                        (fn "pair"
+                            (out "result" (kind owned))
+                            (body
+                                (lit string "b")
+                                (lit i32 7)
+                                (variant (type "Shape") "Pair" "a" "b")
+                                (call_function (fn "unpack"))
+                            )
+                        )
+                        (fn "empty"
+                            (out "result" (kind owned))
+                            (body
+                                (variant (type "Shape") "Empty")
+                                (call_function (fn "unpack"))
+                            )
+                        )
+                        (fn "jumps"
+                            (out "result" (kind owned))
+                            (body
+                                (lit bool true)
+                                (stack_unwrap_boolean)
+                                (loop
+                                    (variant (type "Shape") "Empty")
+                                    (match (case "Empty" (break)))
+                                )
+                                (lit i32 3)
+                            )
+                        )
+                        (fn "unmatched"
+                            (body
+                                (variant (type "Shape") "Empty")
+                                (match (case "Pair" (stack_drop) (stack_drop)))
+                            )
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let find = |name: &str| {
+            registry
+                .find_function(FunctionQuery {
+                    name: Some(name.into()),
+                    module_name: Some("test".into()),
+                    ..Default::default()
+                })
+                .unwrap()
+        };
+
+        AnkhaError::invoke(&find("pair"), &mut context, &registry).unwrap();
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 7);
+        assert_eq!(context.stack().position(), 0);
+
+        AnkhaError::invoke(&find("empty"), &mut context, &registry).unwrap();
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 0);
+        assert_eq!(context.stack().position(), 0);
+
+        AnkhaError::invoke(&find("jumps"), &mut context, &registry).unwrap();
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 3);
+        assert_eq!(context.stack().position(), 0);
+
+        let error = AnkhaError::invoke(&find("unmatched"), &mut context, &registry).unwrap_err();
+        assert!(error.to_string().starts_with(
+            "Script failure: There is no case matching `Empty` variant of `Shape` type!"
+        ));
     }
 
     #[test]
-    fn test_frontend_lexpr() {
+    fn test_typed_parameters() {
         let mut registry = Registry::default().with_basic_types();
         crate::library::install(&mut registry);
-        registry.add_function(add::define_function(&registry));
-        let mut content_provider = FileContentProvider::new("lexpr", LexprContentParser);
-        AnkhaPackage::new("../../resources/package.lexpr", &mut content_provider)
-            .unwrap()
-            .compile()
-            .install::<AnkhaVmScope>(
-                &mut registry,
-                None,
-                // Some(
-                //     crate::library::printables(PrintDebugger::full())
-                //         .basic_printables()
-                //         // .step_through(false)
-                //         .stack_bytes(false)
-                //         .registers_bytes(false)
-                //         .into_handle(),
-                // ),
-            );
-        assert!(
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (struct "Point" (field "x" (kind owned) (type "i32")))
+                        (struct "Other" (field "x" (kind owned) (type "i32")))
+                        (global "score" (kind owned) (lit i32 0))
+                        (fn "take_point"
+                            (in "point" (kind owned) (type "Point"))
+                            (body (stack_drop))
+                        )
+                        (fn "good_point"
+                            (body
+                                (lit i32 1)
+                                (structure (type "Point") "x")
+                                (call_function (fn "take_point"))
+                            )
+                        )
+                        (fn "bad_point"
+                            (body
+                                (lit i32 1)
+                                (structure (type "Other") "x")
+                                (call_function (fn "take_point"))
+                            )
+                        )
+                        (fn "accept"
+                            (in "value" (kind owned) (type "i32"))
+                            (out "result" (kind owned))
+                            (body)
+                        )
+                        (fn "good"
+                            (out "result" (kind owned))
+                            (body (lit i32 1) (call_function (fn "accept")))
+                        )
+                        (fn "bad_input"
+                            (body (lit string "x") (call_function (fn "accept")) (stack_drop))
+                        )
+                        (fn "bad_field"
+                            (body (lit string "x") (structure (type "Point") "x") (stack_drop))
+                        )
+                        (fn "bad_move"
+                            (body
+                                (get_global "score" (kind refmut))
+                                (lit string "x")
+                                (move_into)
+                            )
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let find = |name: &str| {
             registry
                 .find_function(FunctionQuery {
-                    name: Some("main".into()),
+                    name: Some(name.into()),
                     module_name: Some("test".into()),
                     ..Default::default()
                 })
-                .is_some()
-        );
+                .unwrap()
+        };
 
-        let mut host = Host::new(Context::new(10240, 10240), RegistryHandle::new(registry));
-        let (result,) = host
-            .call_function::<(DynamicManaged,), _>("main", "test", None)
-            .unwrap()
-            .run(());
-        assert_eq!(*result.read::<i32>().unwrap(), 42);
+        AnkhaError::invoke(&find("good"), &mut context, &registry).unwrap();
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 1);
 
-        let (result,) = host
-            .call_function::<(DynamicManaged,), _>("main2", "test", None)
-            .unwrap()
-            .run(());
-        let struct_type = host
-            .registry()
-            .find_type(TypeQuery {
-                type_hash: Some(*result.type_hash()),
-                ..Default::default()
-            })
-            .unwrap();
-        let field = struct_type
-            .find_struct_field(StructFieldQuery {
-                name: Some("a".into()),
-                ..Default::default()
-            })
-            .unwrap();
-        let result = unsafe {
-            result
-                .as_ptr_raw()
-                .add(field.address_offset())
-                .cast::<DynamicManaged>()
-                .as_ref()
-                .unwrap()
+        let error = AnkhaError::invoke(&find("bad_input"), &mut context, &registry).unwrap_err();
+        assert!(error.to_string().starts_with(
+            "Script failure: Input `value` of `test::accept` function expects value of `i32` type, but got `String`!"
+        ));
+
+        // Script structs share type hash, so they have to be told apart by identity.
+        AnkhaError::invoke(&find("good_point"), &mut context, &registry).unwrap();
+        let error = AnkhaError::invoke(&find("bad_point"), &mut context, &registry).unwrap_err();
+        assert!(error.to_string().starts_with(
+            "Script failure: Input `point` of `test::take_point` function expects value of `Point` type, but got `Other`!"
+        ));
+
+        let error = AnkhaError::invoke(&find("bad_field"), &mut context, &registry).unwrap_err();
+        assert!(error.to_string().starts_with(
+            "Script failure: Field `x` of `Point` expects value of `i32` type, but got `String`!"
+        ));
+
+        let error = AnkhaError::invoke(&find("bad_move"), &mut context, &registry).unwrap_err();
+        assert!(error.to_string().starts_with(
+            "Script failure: Cannot move value of `String` type into target of `i32` type!"
+        ));
+        assert_eq!(context.stack().position(), 0);
+
+        let AnkhaOption::Owned(type_) = Function(find("accept")).input_type(&registry, 0) else {
+            panic!("Missing declared input type!");
         };
-        assert_eq!(*result.read::<i32>().unwrap(), 42);
+        assert_eq!(
+            type_
+                .consume::<crate::library::reflection::Type>()
+                .ok()
+                .unwrap()
+                .name(),
+            "i32"
+        );
+        assert!(Function(find("accept")).output_type(&registry, 0).is_none());
     }
 
     #[test]
-    fn test_frontend_ankha() {
+    fn test_field_defaults() {
         let mut registry = Registry::default().with_basic_types();
         crate::library::install(&mut registry);
-        registry.add_function(add::define_function(&registry));
-        registry.add_function(sub::define_function(&registry));
-        registry.add_function(mul::define_function(&registry));
-        registry.add_function(eq::define_function(&registry));
-        let mut content_provider = FileContentProvider::new("ankha", AnkhaContentParser::default());
-        AnkhaPackage::new("../../resources/package.ankha", &mut content_provider)
-            .unwrap()
-            .compile()
-            .install::<AnkhaVmScope>(
-                &mut registry,
-                None,
-                // Some(
-                //     crate::library::printables(PrintDebugger::full())
-                //         .basic_printables()
-                //         // .step_through(false)
-                //         .stack_bytes(false)
-                //         .registers_bytes(false)
-                //         .into_handle(),
-                // ),
-            );
-        assert!(
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (struct "Config"
+                            (field "a" (kind owned) (default (lit i32 1)))
+                            (field "b" (kind owned) (default (lit string "b")))
+                            (field "c" (kind owned))
+                        )
+                        (fn "partial"
+                            (out "a" (kind owned))
+                            (out "c" (kind owned))
+                            (body
+                                (lit i32 5)
+                                (structure (type "Config") "c")
+                                (destructure "a" "c")
+                            )
+                        )
+                        (fn "missing"
+                            (body (structure (type "Config")) (stack_drop))
+                        )
+                        (struct "Boxed"
+                            (field "a" (kind box))
+                            (field "b" (kind box))
+                        )
+                        (fn "unpack"
+                            (body
+                                (lit i32 1)
+                                (box)
+                                (lit i32 2)
+                                (box)
+                                (structure (type "Boxed") "a" "b")
+                                (destructure "a")
+                                (stack_drop)
+                            )
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let find = |name: &str| {
             registry
                 .find_function(FunctionQuery {
-                    name: Some("main".into()),
+                    name: Some(name.into()),
                     module_name: Some("test".into()),
                     ..Default::default()
                 })
-                .is_some()
-        );
+                .unwrap()
+        };
+
+        AnkhaError::invoke(&find("partial"), &mut context, &registry).unwrap();
+        let a = context.stack().pop::<DynamicManaged>().unwrap();
+        let c = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(a.consume::<i32>().ok().unwrap(), 1);
+        assert_eq!(c.consume::<i32>().ok().unwrap(), 5);
+        assert_eq!(context.stack().position(), 0);
+
+        let error = AnkhaError::invoke(&find("missing"), &mut context, &registry).unwrap_err();
         assert!(
+            error
+                .to_string()
+                .starts_with("Script failure: Field `c` of `Config` type must be initialized!")
+        );
+
+        // Fields moved out by destructure leave nothing behind in managed storage.
+        let occupied = managed_storage_stats().occupied_size;
+        AnkhaError::invoke(&find("unpack"), &mut context, &registry).unwrap();
+        assert_eq!(context.stack().position(), 0);
+        assert_eq!(managed_storage_stats().occupied_size, occupied);
+    }
+
+    #[test]
+    fn test_cast() {
+        let cast = AnkhaLiteral::cast;
+        assert_eq!(
+            cast(
+                AnkhaLiteral::I32(3),
+                TypeHash::of::<f32>(),
+                AnkhaCastMode::As
+            ),
+            Some(AnkhaLiteral::F32(3.0))
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::I32(300),
+                TypeHash::of::<u8>(),
+                AnkhaCastMode::As
+            ),
+            Some(AnkhaLiteral::U8(44))
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::I32(300),
+                TypeHash::of::<u8>(),
+                AnkhaCastMode::Saturating
+            ),
+            Some(AnkhaLiteral::U8(255))
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::I32(-1),
+                TypeHash::of::<u64>(),
+                AnkhaCastMode::Saturating
+            ),
+            Some(AnkhaLiteral::U64(0))
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::I32(300),
+                TypeHash::of::<u8>(),
+                AnkhaCastMode::Checked
+            ),
+            None
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::F64(2.5),
+                TypeHash::of::<i8>(),
+                AnkhaCastMode::Checked
+            ),
+            Some(AnkhaLiteral::I8(2))
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::F64(1.0e40),
+                TypeHash::of::<f32>(),
+                AnkhaCastMode::Checked
+            ),
+            None
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::F64(1.0e40),
+                TypeHash::of::<f32>(),
+                AnkhaCastMode::Saturating
+            ),
+            Some(AnkhaLiteral::F32(f32::MAX))
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::F32(300.5),
+                TypeHash::of::<u8>(),
+                AnkhaCastMode::As
+            ),
+            Some(AnkhaLiteral::U8(255))
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::F64(f64::NAN),
+                TypeHash::of::<i32>(),
+                AnkhaCastMode::As
+            ),
+            Some(AnkhaLiteral::I32(0))
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::U8(65),
+                TypeHash::of::<char>(),
+                AnkhaCastMode::As
+            ),
+            Some(AnkhaLiteral::Char('A'))
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::F32(1.5),
+                TypeHash::of::<String>(),
+                AnkhaCastMode::As
+            ),
+            Some(AnkhaLiteral::String("1.5".to_owned()))
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::String(" 42 ".to_owned()),
+                TypeHash::of::<i64>(),
+                AnkhaCastMode::As
+            ),
+            Some(AnkhaLiteral::I64(42))
+        );
+        assert_eq!(
+            cast(
+                AnkhaLiteral::String("nope".to_owned()),
+                TypeHash::of::<i64>(),
+                AnkhaCastMode::Checked
+            ),
+            None
+        );
+
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        let mut context = Context::new(10240, 10240);
+        AnkhaLiteral::I32(7).evaluate(&mut context);
+        AnkhaExpression::Cast {
+            type_query: AnkhaTypeQuery {
+                name: Some("f32".to_owned()),
+                ..Default::default()
+            },
+            mode: AnkhaCastMode::As,
+        }
+        .evaluate(&mut context, &registry);
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<f32>().ok().unwrap(), 7.0);
+
+        AnkhaLiteral::String("x".to_owned()).evaluate(&mut context);
+        AnkhaExpression::Cast {
+            type_query: AnkhaTypeQuery {
+                name: Some("u8".to_owned()),
+                ..Default::default()
+            },
+            mode: AnkhaCastMode::Checked,
+        }
+        .evaluate(&mut context, &registry);
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert!(result.consume::<AnkhaOption>().ok().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_math() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::library::install(&mut registry);
+        registry.add_function(add::define_function(&registry));
+        let file = AnkhaContentParser::default()
+            .parse_file_content(
+                r#"(file
+                    (mod "test"
+                        (fn "sum"
+                            (out "result" (kind owned))
+                            (body
+                                (lit i32 2)
+                                (lit i32 40)
+                                (call_method (fn "math::add"))
+                            )
+                        )
+                        (fn "compare"
+                            (out "result" (kind owned))
+                            (body
+                                (lit f64 2.5)
+                                (lit f64 1.5)
+                                (call_method (fn "math::gt"))
+                            )
+                        )
+                        (fn "bounded"
+                            (out "result" (kind owned))
+                            (body
+                                (lit u8 10)
+                                (lit u8 1)
+                                (lit u8 20)
+                                (call_method (fn "math::clamp"))
+                            )
+                        )
+                        (fn "overflow"
+                            (body
+                                (lit u8 1)
+                                (lit u8 255)
+                                (call_method (fn "math::add"))
+                                (stack_drop)
+                            )
+                        )
+                        (fn "host_sum"
+                            (out "result" (kind owned))
+                            (body
+                                (lit i32 2)
+                                (lit i32 40)
+                                (call_function (fn "add"))
+                            )
+                        )
+                        (fn "nan_bound"
+                            (body
+                                (lit f64 2.0)
+                                (lit f64 0.0)
+                                (lit f64 0.0)
+                                (call_method (fn "math::div"))
+                                (lit f64 1.0)
+                                (call_method (fn "math::clamp"))
+                                (stack_drop)
+                            )
+                        )
+                    )
+                )"#,
+            )
+            .unwrap();
+        let package = AnkhaPackage {
+            files: [("main".to_owned(), file)].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
+        let find = |name: &str| {
             registry
                 .find_function(FunctionQuery {
-                    name: Some("factorial".into()),
+                    name: Some(name.into()),
                     module_name: Some("test".into()),
                     ..Default::default()
                 })
-                .is_some()
-        );
-        let mut host = Host::new(Context::new(10240, 10240), RegistryHandle::new(registry));
+                .unwrap()
+        };
 
-        let (result,) = host
-            .call_function::<(DynamicManaged,), _>("main", "test", None)
-            .unwrap()
-            .run(());
-        assert_eq!(*result.read::<i32>().unwrap(), 42);
+        AnkhaError::invoke(&find("sum"), &mut context, &registry).unwrap();
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 42);
 
-        let (result,) = host
-            .call_function::<(DynamicManaged,), _>("main2", "test", None)
-            .unwrap()
-            .run(());
-        let struct_type = host
-            .registry()
-            .find_type(TypeQuery {
-                type_hash: Some(*result.type_hash()),
-                ..Default::default()
-            })
-            .unwrap();
-        let field = struct_type
-            .find_struct_field(StructFieldQuery {
-                name: Some("a".into()),
+        AnkhaError::invoke(&find("compare"), &mut context, &registry).unwrap();
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert!(!result.consume::<bool>().ok().unwrap());
+
+        AnkhaError::invoke(&find("bounded"), &mut context, &registry).unwrap();
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<u8>().ok().unwrap(), 10);
+        assert_eq!(context.stack().position(), 0);
+
+        // Math functions carry module prefix, so plain name finds host function.
+        let add = registry
+            .find_function(FunctionQuery {
+                name: Some("add".into()),
                 ..Default::default()
             })
             .unwrap();
-        let result = unsafe {
-            result
-                .as_ptr_raw()
-                .add(field.address_offset())
-                .cast::<DynamicManaged>()
-                .as_ref()
-                .unwrap()
-        };
-        assert_eq!(*result.read::<i32>().unwrap(), 42);
+        assert_eq!(add.signature().module_name.as_deref(), Some("intrinsics"));
+        AnkhaError::invoke(&find("host_sum"), &mut context, &registry).unwrap();
+        let result = context.stack().pop::<DynamicManaged>().unwrap();
+        assert_eq!(result.consume::<i32>().ok().unwrap(), 42);
 
-        let (result,) = host
-            .call_function::<(DynamicManaged,), _>("factorial", "test", None)
-            .unwrap()
-            .run((DynamicManaged::new(5).ok().unwrap(),));
-        assert_eq!(*result.read::<i32>().unwrap(), 120);
+        let error = AnkhaError::invoke(&find("overflow"), &mut context, &registry).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Script failure: Overflow in `add` of `u8`!")
+        );
+
+        let error = AnkhaError::invoke(&find("nan_bound"), &mut context, &registry).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Script failure: NaN bound in `clamp` of `f64`!")
+        );
     }
 }
@@ -7,7 +7,7 @@ use intuicio_data::managed::DynamicManaged;
 use std::time::Instant;
 
 fn main() {
-    let original_registry = Registry::default()
+    let mut registry = Registry::default()
         .with_basic_types()
         .with_install(ankha::library::install)
         .with_install(crate::library::install);
@@ -28,7 +28,6 @@ fn main() {
             ast = true;
             line = &line[(b"ast".len())..];
         }
-        let mut registry = original_registry.clone();
         let mut timer = Instant::now();
         let file = match AnkhaContentParser::default()
             .with_setup(crate::parser::install)
@@ -48,9 +47,7 @@ fn main() {
         let mut package = AnkhaPackage::default();
         package.files.insert("main.ankha".to_owned(), file);
         timer = Instant::now();
-        package
-            .compile()
-            .install::<AnkhaVmScope>(&mut registry, None);
+        package.install::<AnkhaVmScope>(&mut context, &mut registry, None);
         println!("* Compiling: {:?}", timer.elapsed());
         timer = Instant::now();
         registry
@@ -70,6 +67,7 @@ fn main() {
             .ok()
             .unwrap();
         println!("* Result: {}", result);
+        package.uninstall(&mut context, &mut registry);
     }
 }
 
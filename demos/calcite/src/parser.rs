@@ -65,7 +65,7 @@ fn expr() -> ParserHandle {
                     "+".to_owned(),
                     |lhs, rhs| {
                         format!(
-                            "(group_reversed (call_function (fn \"add\")) {} {})",
+                            "(group_reversed (call_method (fn \"math::add\")) {} {})",
                             lhs, rhs
                         )
                     },
@@ -75,7 +75,7 @@ fn expr() -> ParserHandle {
                     "-".to_owned(),
                     |lhs, rhs| {
                         format!(
-                            "(group_reversed (call_function (fn \"sub\")) {} {})",
+                            "(group_reversed (call_method (fn \"math::sub\")) {} {})",
                             lhs, rhs
                         )
                     },
@@ -87,7 +87,7 @@ fn expr() -> ParserHandle {
                     "*".to_owned(),
                     |lhs, rhs| {
                         format!(
-                            "(group_reversed (call_function (fn \"mul\")) {} {})",
+                            "(group_reversed (call_method (fn \"math::mul\")) {} {})",
                             lhs, rhs
                         )
                     },
@@ -97,7 +97,7 @@ fn expr() -> ParserHandle {
                     "/".to_owned(),
                     |lhs, rhs| {
                         format!(
-                            "(group_reversed (call_function (fn \"div\")) {} {})",
+                            "(group_reversed (call_method (fn \"math::div\")) {} {})",
                             lhs, rhs
                         )
                     },
@@ -170,7 +170,7 @@ mod tests {
         assert_eq!(rest, "");
         assert_eq!(
             value.consume::<String>().ok().unwrap().as_str(),
-            "(group_reversed (call_function (fn \"sub\")) (group_reversed (call_function (fn \"mul\")) (group_reversed (call_function (fn \"add\")) (lit f64 3) (lit f64 4)) (lit f64 2)) (group_reversed (call_function (fn \"div\")) (lit f64 1) (lit f64 5)))"
+            "(group_reversed (call_method (fn \"math::sub\")) (group_reversed (call_method (fn \"math::mul\")) (group_reversed (call_method (fn \"math::add\")) (lit f64 3) (lit f64 4)) (lit f64 2)) (group_reversed (call_method (fn \"math::div\")) (lit f64 1) (lit f64 5)))"
         );
 
         let (rest, value) = registry.parse("calcite/file", "42").unwrap();
@@ -180,6 +180,10 @@ mod tests {
             AnkhaFile {
                 modules: vec![AnkhaModule {
                     name: "main".to_owned(),
+                    init: None,
+                    shutdown: None,
+                    constants: vec![],
+                    globals: vec![],
                     structs: vec![],
                     enums: vec![],
                     functions: vec![AnkhaFunction {
@@ -191,7 +195,8 @@ mod tests {
                         outputs: vec![AnkhaFunctionParameter {
                             meta: None,
                             name: "result".to_owned(),
-                            kind: AnkhaValueKind::Owned
+                            kind: AnkhaValueKind::Owned,
+                            type_query: None
                         }],
                         script: vec![AnkhaOperation::Expression(AnkhaExpression::Literal(
                             AnkhaLiteral::F64(42.0)